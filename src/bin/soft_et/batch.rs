@@ -0,0 +1,305 @@
+use anyhow::Result;
+use std::fs;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::{Component, Path, PathBuf};
+use std::time::Instant;
+
+use erosion_thickness::et_algorithm::parallel;
+use erosion_thickness::skeleton::compression;
+
+use super::config::RunConfig;
+use super::{compute, ComputeSummary, Outputs};
+
+const PLY_EXTENSIONS: [&str; 3] = [".ply", ".ply.gz", ".ply.zst"];
+
+/// Outcome of the computation on one file of the batch
+struct BatchEntry {
+    input: PathBuf,
+    result: Result<ComputeSummary>,
+    seconds: f32,
+}
+
+fn is_ply_file(path: &Path) -> bool {
+    let name = path.file_name().and_then(|n| n.to_str()).unwrap_or("");
+    path.is_file() && PLY_EXTENSIONS.iter().any(|ext| name.ends_with(ext))
+}
+
+/// File name split into stem and ply (and compression) extension
+fn split_ply_name(path: &Path) -> (String, &'static str) {
+    let name = path.file_name().and_then(|n| n.to_str()).unwrap_or("");
+    PLY_EXTENSIONS
+        .iter()
+        .rev()
+        .find_map(|ext| name.strip_suffix(ext).map(|stem| (stem.to_string(), *ext)))
+        .unwrap_or((name.to_string(), ".ply"))
+}
+
+fn glob_files(pattern: &str) -> Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    for entry in glob::glob(pattern)? {
+        let path = entry?;
+        if is_ply_file(&path) {
+            files.push(path);
+        }
+    }
+    Ok(files)
+}
+
+/// Input files, and root directory of the input tree
+///
+/// The input is a directory (searched recursively), a manifest file listing one
+/// skeleton per line (relative to the manifest directory, `#` for comments), or a
+/// glob pattern.
+fn input_files(input: &str) -> Result<(Vec<PathBuf>, PathBuf)> {
+    let input_path = Path::new(input);
+    let (mut files, root) = if input_path.is_dir() {
+        let pattern = input_path.join("**").join("*");
+        (
+            glob_files(&pattern.to_string_lossy())?,
+            input_path.to_path_buf(),
+        )
+    } else if input_path.is_file() && !is_ply_file(input_path) {
+        let root = input_path.parent().unwrap_or(Path::new("")).to_path_buf();
+        let files = fs::read_to_string(input_path)?
+            .lines()
+            .map(|line| line.trim())
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(|line| root.join(line))
+            .collect();
+        (files, root)
+    } else {
+        // root: components before the first one containing a glob character
+        let root = input_path
+            .components()
+            .take_while(|c| !c.as_os_str().to_string_lossy().contains(['*', '?', '[']))
+            .collect::<PathBuf>();
+        let root = if root == input_path {
+            root.parent().unwrap_or(Path::new("")).to_path_buf()
+        } else {
+            root
+        };
+        (glob_files(input)?, root)
+    };
+    files.sort();
+    files.dedup();
+    Ok((files, root))
+}
+
+/// Outputs of an input file, mirroring its position in the input tree
+fn batch_outputs(
+    input: &Path,
+    root: &Path,
+    out_path: &Path,
+    no_erosion_path: bool,
+    subdivided_graph: bool,
+    arrays: bool,
+    report: Option<&Path>,
+) -> Outputs {
+    // files outside of the input tree are written at the output root
+    let relative = match input.strip_prefix(root) {
+        Ok(rel) if rel.components().all(|c| matches!(c, Component::Normal(_))) => rel,
+        _ => Path::new(input.file_name().unwrap_or_default()),
+    };
+    let out_dir = out_path.join(relative.parent().unwrap_or(Path::new("")));
+    // outputs are compressed as their input
+    let (stem, ext) = split_ply_name(input);
+    Outputs {
+        skeleton: out_dir.join(format!("{}{}", stem, ext)),
+        erosion_path: if no_erosion_path {
+            None
+        } else {
+            Some(out_dir.join(format!("{}_erosion_path{}", stem, ext)))
+        },
+        subdivided_graph: if subdivided_graph {
+            Some(out_dir.join(format!("{}_subdivided_graph{}", stem, ext)))
+        } else {
+            None
+        },
+        arrays: if arrays {
+            Some(out_dir.join(format!("{}_arrays.npz", stem)))
+        } else {
+            None
+        },
+        // reports keep the format of the configured one
+        report: report.map(|report| {
+            let ext = compression::format_extension(report).unwrap_or("json");
+            out_dir.join(format!("{}_report.{}", stem, ext))
+        }),
+        components: out_dir.join(format!("{}_components.csv", stem)),
+    }
+}
+
+/// Runs the computation of an input file, a panic becoming an error
+fn run_entry<F: FnOnce() -> Result<ComputeSummary>>(input: &Path, computation: F) -> BatchEntry {
+    let start = Instant::now();
+    // a failure on one file must not stop the batch, even a panic
+    let result = panic::catch_unwind(AssertUnwindSafe(computation)).unwrap_or_else(|payload| {
+        let msg = payload
+            .downcast_ref::<&str>()
+            .map(|s| s.to_string())
+            .or_else(|| payload.downcast_ref::<String>().cloned())
+            .unwrap_or_else(|| "unknown panic".to_string());
+        Err(anyhow::Error::msg(format!("panic: {}", msg)))
+    });
+    BatchEntry {
+        input: input.to_path_buf(),
+        result,
+        seconds: start.elapsed().as_secs_f32(),
+    }
+}
+
+/// Summary table of the batch, one line per file
+fn summary_lines(entries: &[BatchEntry]) -> Vec<String> {
+    let rows: Vec<[String; 5]> = entries
+        .iter()
+        .map(|entry| {
+            let input = entry.input.display().to_string();
+            let seconds = format!("{:.2}", entry.seconds);
+            match &entry.result {
+                Ok(summary) => {
+                    let et_range = match summary.et_range {
+                        Some((et_min, et_max)) => format!("[{}, {}]", et_min, et_max),
+                        None => "inf".to_string(),
+                    };
+                    [
+                        input,
+                        "ok".to_string(),
+                        seconds,
+                        summary.nb_vertices.to_string(),
+                        et_range,
+                    ]
+                }
+                Err(err) => [
+                    input,
+                    format!("failed: {}", err),
+                    seconds,
+                    "-".to_string(),
+                    "-".to_string(),
+                ],
+            }
+        })
+        .collect();
+
+    let header = ["file", "status", "time (s)", "vertices", "et range"].map(String::from);
+    let mut widths = header.clone().map(|h| h.len());
+    for row in rows.iter() {
+        for i in 0..widths.len() {
+            widths[i] = widths[i].max(row[i].len());
+        }
+    }
+    let mut lines = Vec::new();
+    for row in std::iter::once(&header).chain(rows.iter()) {
+        lines.push(format!(
+            "{:<w0$}  {:<w1$}  {:>w2$}  {:>w3$}  {}",
+            row[0],
+            row[1],
+            row[2],
+            row[3],
+            row[4],
+            w0 = widths[0],
+            w1 = widths[1],
+            w2 = widths[2],
+            w3 = widths[3]
+        ));
+    }
+    lines
+}
+
+/// Processes all skeletons of `input` in parallel, then prints a summary table
+///
+/// Outputs are written in `config.output.pathout`, and fails if at least one file
+/// failed, after processing all of them.
+pub fn run(input: &str, config: &RunConfig) -> Result<()> {
+    let (files, root) = input_files(input)?;
+    if files.is_empty() {
+        return Err(anyhow::Error::msg(format!(
+            "No skeleton found in {}",
+            input
+        )));
+    }
+    log::info!("batch: {} skeletons", files.len());
+
+    let entries = parallel::map_range(0..files.len(), |i| {
+        let outputs = batch_outputs(
+            &files[i],
+            &root,
+            &config.output.pathout,
+            config.output.no_erosion_path,
+            config.output.subdivided_graph.is_some(),
+            config.output.arrays.is_some(),
+            config.output.report.as_deref(),
+        );
+        let entry = run_entry(&files[i], || compute(&files[i], config, &outputs));
+        match &entry.result {
+            Ok(_) => log::info!("{}: done", entry.input.display()),
+            Err(err) => log::error!("{}: {}", entry.input.display(), err),
+        }
+        entry
+    });
+    for line in summary_lines(&entries) {
+        println!("{}", line);
+    }
+
+    let nb_failed = entries.iter().filter(|entry| entry.result.is_err()).count();
+    if nb_failed != 0 {
+        return Err(anyhow::Error::msg(format!(
+            "{} of {} skeletons failed",
+            nb_failed,
+            entries.len()
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn panics_become_errors() {
+        let entry = run_entry(Path::new("a.ply"), || panic!("out of range"));
+        assert_eq!(
+            entry.result.err().unwrap().to_string(),
+            "panic: out of range"
+        );
+        let entry = run_entry(Path::new("a.ply"), || panic!("vertex {}", 3));
+        assert_eq!(entry.result.err().unwrap().to_string(), "panic: vertex 3");
+        let entry = run_entry(Path::new("a.ply"), || Err(anyhow::Error::msg("no face")));
+        assert_eq!(entry.result.err().unwrap().to_string(), "no face");
+    }
+
+    #[test]
+    fn summary_table() {
+        let entry = |input: &str, result: Result<ComputeSummary>| BatchEntry {
+            input: PathBuf::from(input),
+            result,
+            seconds: 1.5,
+        };
+        let entries = [
+            entry(
+                "a.ply",
+                Ok(ComputeSummary {
+                    nb_vertices: 1200,
+                    et_range: Some((0.5, 2.0)),
+                }),
+            ),
+            entry(
+                "sub/b.ply",
+                Ok(ComputeSummary {
+                    nb_vertices: 3,
+                    et_range: None,
+                }),
+            ),
+            entry("c.ply", Err(anyhow::Error::msg("no face"))),
+        ];
+        assert_eq!(
+            summary_lines(&entries),
+            [
+                "file       status           time (s)  vertices  et range",
+                "a.ply      ok                   1.50      1200  [0.5, 2]",
+                "sub/b.ply  ok                   1.50         3  inf",
+                "c.ply      failed: no face      1.50         -  -",
+            ]
+        );
+    }
+}
@@ -0,0 +1,448 @@
+use anyhow::Result;
+use clap::ValueEnum;
+use erosion_thickness::skeleton::colormap::{ColorRange, ColorScale, Colormap};
+use erosion_thickness::skeleton::io::RadiusConvention;
+use ply_rs::ply::Encoding;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+#[derive(Clone, Copy, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Metric {
+    /// Distance between vertices
+    Euclidean,
+    /// Distance between the medial ball surfaces, `|p1 - p2| + |r1 - r2|`
+    MedialBall,
+    /// `|p1 - p2| + radius_scale * |r1 - r2|`
+    ScaledRadius,
+}
+
+#[derive(Clone, Copy, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum PlyEncoding {
+    Ascii,
+    BinaryLittleEndian,
+    BinaryBigEndian,
+}
+
+impl From<PlyEncoding> for Encoding {
+    fn from(encoding: PlyEncoding) -> Encoding {
+        match encoding {
+            PlyEncoding::Ascii => Encoding::Ascii,
+            PlyEncoding::BinaryLittleEndian => Encoding::BinaryLittleEndian,
+            PlyEncoding::BinaryBigEndian => Encoding::BinaryBigEndian,
+        }
+    }
+}
+
+/// Erosion thickness written for vertices never reached by the burn front
+#[derive(Clone, Copy, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum InfinityPolicy {
+    /// Maximum finite erosion thickness
+    Max,
+    /// Positive infinity
+    Inf,
+    /// Not a number
+    Nan,
+}
+
+impl InfinityPolicy {
+    /// Value replacing infinite erosion thickness, None to keep the maximum
+    pub fn value(&self) -> Option<f32> {
+        match self {
+            InfinityPolicy::Max => None,
+            InfinityPolicy::Inf => Some(f32::INFINITY),
+            InfinityPolicy::Nan => Some(f32::NAN),
+        }
+    }
+}
+
+/// Colormap of vertex colors
+#[derive(Clone, Copy, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ColormapName {
+    /// Blue (low) to red (high)
+    RedBlue,
+    Viridis,
+    Magma,
+    Coolwarm,
+    Grayscale,
+    /// No color properties
+    None,
+}
+
+/// Where radii of obj and off skeletons are stored
+#[derive(Clone, Copy, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum RadiusSource {
+    /// Fourth vertex coordinate
+    FourthCoordinate,
+    /// Companion file, one radius per line
+    Companion,
+    /// No radius, a constant value on import
+    Constant,
+}
+
+/// Reading of obj and off skeletons, ply ones having their own radius
+#[derive(Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct InputConfig {
+    pub radius: RadiusSource,
+    /// companion file, the input with a `.rad` extension by default
+    pub radius_file: Option<PathBuf>,
+    /// radius of all vertices with the constant source
+    pub radius_value: f64,
+}
+
+impl Default for InputConfig {
+    fn default() -> Self {
+        InputConfig {
+            radius: RadiusSource::FourthCoordinate,
+            radius_file: None,
+            radius_value: 0.0,
+        }
+    }
+}
+
+impl InputConfig {
+    /// Radius convention of an input skeleton
+    pub fn convention(&self, file_path: &Path) -> RadiusConvention {
+        match self.radius {
+            RadiusSource::FourthCoordinate => RadiusConvention::FourthCoordinate,
+            RadiusSource::Companion => RadiusConvention::Companion(
+                self.radius_file
+                    .clone()
+                    .unwrap_or_else(|| file_path.with_extension("rad")),
+            ),
+            RadiusSource::Constant => RadiusConvention::Constant(self.radius_value as f32),
+        }
+    }
+}
+
+/// Radius convention of an obj or off output, companion files taking a `.rad` extension
+pub fn output_convention(radius: RadiusSource, file_path: &Path) -> RadiusConvention {
+    match radius {
+        RadiusSource::FourthCoordinate => RadiusConvention::FourthCoordinate,
+        RadiusSource::Companion => RadiusConvention::Companion(file_path.with_extension("rad")),
+        RadiusSource::Constant => RadiusConvention::Constant(0.0),
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SubdivisionConfig {
+    pub dist_max: f64,
+    pub subdiv_max: usize,
+}
+
+impl Default for SubdivisionConfig {
+    fn default() -> Self {
+        SubdivisionConfig {
+            dist_max: 0.005,
+            subdiv_max: 1,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BurnConfig {
+    pub metric: Metric,
+    pub radius_scale: f64,
+    pub infinity: InfinityPolicy,
+}
+
+impl Default for BurnConfig {
+    fn default() -> Self {
+        BurnConfig {
+            metric: Metric::Euclidean,
+            radius_scale: 1.0,
+            infinity: InfinityPolicy::Max,
+        }
+    }
+}
+
+#[derive(Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RepairConfig {
+    pub enabled: bool,
+    pub weld_tol: f64,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct OutputConfig {
+    pub pathout: PathBuf,
+    pub skeleton: PathBuf,
+    pub erosion_path: PathBuf,
+    pub no_erosion_path: bool,
+    /// graph the burning is computed on, not written by default
+    pub subdivided_graph: Option<PathBuf>,
+    /// numpy arrays of the subdivided graph (subdivided faces padded with -1), a `.npz`
+    /// archive or a directory of `.npy` files
+    pub arrays: Option<PathBuf>,
+    /// summary statistics, csv if the name ends with `.csv`, json otherwise
+    pub report: Option<PathBuf>,
+    pub components: PathBuf,
+    pub encoding: PlyEncoding,
+    /// radius of obj and off outputs, not written with the constant source
+    pub radius: RadiusSource,
+}
+
+impl Default for OutputConfig {
+    fn default() -> Self {
+        OutputConfig {
+            pathout: PathBuf::from("./output/"),
+            skeleton: PathBuf::from("skeleton_erosion_thickness.ply"),
+            erosion_path: PathBuf::from("erosion_path.ply"),
+            no_erosion_path: false,
+            subdivided_graph: None,
+            arrays: None,
+            report: None,
+            components: PathBuf::from("components.csv"),
+            encoding: PlyEncoding::Ascii,
+            radius: RadiusSource::FourthCoordinate,
+        }
+    }
+}
+
+/// Vertex colors, from erosion thickness in computations
+#[derive(Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ColorConfig {
+    pub colormap: ColormapName,
+    /// fixed range, min and max of the values by default
+    pub range: Option<[f64; 2]>,
+    /// percentile range, from 0 to 100
+    pub percentile: Option<[f64; 2]>,
+    /// logarithmic scale, non positive values getting the invalid color
+    pub log: bool,
+    /// color of infinite, NaN and (log scale) non positive values
+    pub invalid_color: [u8; 3],
+    /// svg legend next to colored ply outputs
+    pub legend: bool,
+    /// png legend next to colored ply outputs
+    pub legend_png: bool,
+}
+
+impl Default for ColorConfig {
+    fn default() -> Self {
+        let scale = ColorScale::default();
+        ColorConfig {
+            colormap: ColormapName::RedBlue,
+            range: None,
+            percentile: None,
+            log: scale.log,
+            invalid_color: scale.invalid_color,
+            legend: true,
+            legend_png: false,
+        }
+    }
+}
+
+impl ColorConfig {
+    /// Color scale, None if no color is written
+    pub fn scale(&self) -> Result<Option<ColorScale>> {
+        let colormap = match self.colormap {
+            ColormapName::RedBlue => Colormap::RedBlue,
+            ColormapName::Viridis => Colormap::Viridis,
+            ColormapName::Magma => Colormap::Magma,
+            ColormapName::Coolwarm => Colormap::Coolwarm,
+            ColormapName::Grayscale => Colormap::Grayscale,
+            ColormapName::None => return Ok(None),
+        };
+        let range = match (self.range, self.percentile) {
+            (Some(_), Some(_)) => {
+                return Err(anyhow::Error::msg(
+                    "Color range and percentile range are exclusive",
+                ))
+            }
+            (Some([lo, hi]), None) => ColorRange::Fixed(lo as f32, hi as f32),
+            (None, Some([lo, hi])) => ColorRange::Percentile(lo as f32, hi as f32),
+            (None, None) => ColorRange::MinMax,
+        };
+        Ok(Some(ColorScale {
+            colormap,
+            range,
+            log: self.log,
+            invalid_color: self.invalid_color,
+        }))
+    }
+}
+
+/// Settings of a run, every field being optional in configuration files
+///
+/// Floats are stored as f64, so that written configurations keep their short decimal form.
+#[derive(Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RunConfig {
+    /// number of threads, 0 for one per core
+    pub threads: usize,
+    pub input: InputConfig,
+    pub subdivision: SubdivisionConfig,
+    pub burn: BurnConfig,
+    pub repair: RepairConfig,
+    pub color: ColorConfig,
+    pub output: OutputConfig,
+}
+
+impl RunConfig {
+    /// Reads a configuration file, json if its extension is `.json`, toml otherwise
+    pub fn load(file_path: &Path) -> Result<RunConfig> {
+        let text = fs::read_to_string(file_path)?;
+        let config = if file_path.extension().is_some_and(|ext| ext == "json") {
+            serde_json::from_str(&text).map_err(anyhow::Error::msg)
+        } else {
+            toml::from_str(&text).map_err(anyhow::Error::msg)
+        };
+        config.map_err(|err| {
+            anyhow::Error::msg(format!(
+                "Invalid configuration {}: {}",
+                file_path.display(),
+                err
+            ))
+        })
+    }
+
+    /// Writes the configuration as toml
+    pub fn save(&self, file_path: &Path) -> Result<()> {
+        fs::write(file_path, toml::to_string(self)?)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Configuration file of a test, in a fresh directory
+    fn config_file(name: &str, text: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("soft_et_config_{}_{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let file_path = dir.join(name);
+        fs::write(&file_path, text).unwrap();
+        file_path
+    }
+
+    fn remove(file_path: &Path) {
+        fs::remove_dir_all(file_path.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn load_toml() {
+        let file_path = config_file(
+            "run.toml",
+            "threads = 2\n\
+             [subdivision]\ndist_max = 0.01\n\
+             [burn]\nmetric = \"medial-ball\"\n\
+             [color]\ncolormap = \"viridis\"\nrange = [0.0, 1.0]\n\
+             [output]\nencoding = \"binary-little-endian\"\n",
+        );
+        let config = RunConfig::load(&file_path).unwrap();
+        assert_eq!(config.threads, 2);
+        assert_eq!(config.subdivision.dist_max, 0.01);
+        assert!(matches!(config.burn.metric, Metric::MedialBall));
+        assert!(matches!(config.color.colormap, ColormapName::Viridis));
+        assert_eq!(config.color.range, Some([0.0, 1.0]));
+        assert!(matches!(
+            config.output.encoding,
+            PlyEncoding::BinaryLittleEndian
+        ));
+        // missing fields keep their default
+        assert_eq!(config.subdivision.subdiv_max, 1);
+        assert_eq!(config.burn.radius_scale, 1.0);
+        assert_eq!(config.output.pathout, PathBuf::from("./output/"));
+        remove(&file_path);
+    }
+
+    #[test]
+    fn load_json() {
+        let file_path = config_file(
+            "run.json",
+            r#"{"subdivision": {"subdiv_max": 3}, "burn": {"infinity": "nan"},
+                "repair": {"enabled": true, "weld_tol": 0.001}}"#,
+        );
+        let config = RunConfig::load(&file_path).unwrap();
+        assert_eq!(config.subdivision.subdiv_max, 3);
+        assert_eq!(config.subdivision.dist_max, 0.005);
+        assert!(matches!(config.burn.infinity, InfinityPolicy::Nan));
+        assert!(config.repair.enabled);
+        assert_eq!(config.repair.weld_tol, 0.001);
+        remove(&file_path);
+
+        // a json file without the extension is read as toml
+        let file_path = config_file("run.cfg", r#"{"threads": 2}"#);
+        assert!(RunConfig::load(&file_path).is_err());
+        remove(&file_path);
+    }
+
+    #[test]
+    fn unknown_fields() {
+        for (name, text) in [
+            ("top.toml", "thread = 2\n"),
+            ("nested.toml", "[burn]\nseeds = [0]\n"),
+            ("nested.json", r#"{"output": {"path": "out"}}"#),
+        ] {
+            let file_path = config_file(name, text);
+            let err = RunConfig::load(&file_path).err().unwrap().to_string();
+            assert!(err.starts_with("Invalid configuration"), "{}", err);
+            assert!(err.contains("unknown field"), "{}", err);
+            remove(&file_path);
+        }
+    }
+
+    #[test]
+    fn invalid_values() {
+        for (name, text) in [
+            ("metric.toml", "[burn]\nmetric = \"manhattan\"\n"),
+            ("range.toml", "[color]\nrange = [0.0]\n"),
+            ("type.json", r#"{"threads": -1}"#),
+        ] {
+            let file_path = config_file(name, text);
+            let err = RunConfig::load(&file_path).err().unwrap().to_string();
+            assert!(err.starts_with("Invalid configuration"), "{}", err);
+            remove(&file_path);
+        }
+    }
+
+    #[test]
+    fn save_then_load() {
+        let mut config = RunConfig::default();
+        config.subdivision.dist_max = 0.02;
+        config.burn.metric = Metric::ScaledRadius;
+        config.burn.radius_scale = 0.5;
+        config.color.percentile = Some([5.0, 95.0]);
+        config.output.report = Some(PathBuf::from("report.csv"));
+        let file_path = config_file("saved.toml", "");
+        config.save(&file_path).unwrap();
+
+        let loaded = RunConfig::load(&file_path).unwrap();
+        assert_eq!(
+            toml::to_string(&loaded).unwrap(),
+            toml::to_string(&config).unwrap()
+        );
+        // written floats keep their short form
+        let text = fs::read_to_string(&file_path).unwrap();
+        assert!(text.contains("dist_max = 0.02\n"), "{}", text);
+        remove(&file_path);
+    }
+
+    #[test]
+    fn color_ranges() {
+        let mut config = ColorConfig::default();
+        let scale = config.scale().unwrap().unwrap();
+        assert!(matches!(scale.range, ColorRange::MinMax));
+        config.range = Some([1.0, 2.0]);
+        let scale = config.scale().unwrap().unwrap();
+        assert!(matches!(scale.range, ColorRange::Fixed(lo, hi) if lo == 1.0 && hi == 2.0));
+        config.percentile = Some([5.0, 95.0]);
+        assert!(config.scale().is_err());
+        config.range = None;
+        config.colormap = ColormapName::None;
+        assert!(config.scale().unwrap().is_none());
+    }
+}
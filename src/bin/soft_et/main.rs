@@ -0,0 +1,914 @@
+use anyhow::Result;
+use clap::{Args, Parser, Subcommand};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+use erosion_thickness::et_algorithm::algorithm::{
+    erosion_thickness_computation_with_graph, erosion_thickness_computation_with_metric,
+};
+use erosion_thickness::et_algorithm::metric::{BurnMetric, Euclidean, MedialBall, ScaledRadius};
+use erosion_thickness::et_algorithm::parallel;
+use erosion_thickness::et_algorithm::report::Report;
+use erosion_thickness::skeleton::colormap::{ColorRange, ColorScale};
+use erosion_thickness::skeleton::compression::{self, Compression};
+use erosion_thickness::skeleton::descriptor::{Descriptor, HistogramDistance, DEFAULT_NB_BINS};
+use erosion_thickness::skeleton::erosion_path::ErosionPath;
+use erosion_thickness::skeleton::gltf;
+use erosion_thickness::skeleton::graph::{self, GraphFormat};
+use erosion_thickness::skeleton::io::{self, MeshFormat};
+use erosion_thickness::skeleton::legend::Legend;
+use erosion_thickness::skeleton::npy;
+use erosion_thickness::skeleton::skeleton::Skeleton;
+use erosion_thickness::skeleton::vtk::{self, VtkFormat};
+use ply_rs::ply::Encoding;
+
+mod batch;
+mod config;
+mod provenance;
+
+use config::{
+    ColorConfig, ColormapName, InfinityPolicy, InputConfig, Metric, OutputConfig, PlyEncoding,
+    RadiusSource, RunConfig,
+};
+use provenance::{Provenance, PROVENANCE_PREFIX};
+
+const COLOR_PROPERTIES: [&str; 3] = ["red", "green", "blue"];
+
+/// Effective configuration, written next to the outputs
+const CONFIG_FILE_NAME: &str = "run_config.toml";
+
+/// Radius convention of obj and off inputs
+#[derive(Args)]
+struct RadiusArgs {
+    /// Radius of obj and off inputs [default: fourth-coordinate]
+    #[arg(value_enum, long = "radius")]
+    radius: Option<RadiusSource>,
+    /// Radius of all vertices with the constant radius source [default: 0.0]
+    #[arg(long = "radius_value")]
+    radius_value: Option<f64>,
+}
+
+impl RadiusArgs {
+    fn apply(&self, config: &mut InputConfig) {
+        if let Some(radius) = self.radius {
+            config.radius = radius;
+        }
+        if let Some(radius_value) = self.radius_value {
+            config.radius_value = radius_value;
+        }
+    }
+}
+
+/// Input options, shared by subcommands
+#[derive(Args)]
+struct InputArgs {
+    /// Input skeleton (ply, obj, off or ma), `-` for standard input
+    #[arg(long = "input_skel")]
+    ply_in_path: PathBuf,
+    #[command(flatten)]
+    radius: RadiusArgs,
+    /// Companion radius file [default: input with a .rad extension]
+    #[arg(long = "radius_file")]
+    radius_file: Option<PathBuf>,
+}
+
+impl InputArgs {
+    fn apply(&self, config: &mut InputConfig) {
+        self.radius.apply(config);
+        if let Some(radius_file) = &self.radius_file {
+            config.radius_file = Some(radius_file.clone());
+        }
+    }
+
+    /// Reads the input skeleton
+    fn import(&self) -> Result<Skeleton> {
+        let mut config = InputConfig::default();
+        self.apply(&mut config);
+        import_skeleton(&self.ply_in_path, &config)
+    }
+}
+
+/// Output options, shared by subcommands
+#[derive(Args)]
+struct OutputArgs {
+    /// Output directory, output files are relative to it [default: ./output/]
+    #[arg(long = "pathout")]
+    out_path: Option<PathBuf>,
+    /// Output skeleton, `-` for standard output (default name depends on the subcommand)
+    #[arg(long = "output_skel")]
+    ply_out_path: Option<PathBuf>,
+    /// Radius of obj and off outputs [default: fourth-coordinate]
+    #[arg(value_enum, long = "output_radius")]
+    output_radius: Option<RadiusSource>,
+}
+
+impl OutputArgs {
+    fn skeleton_path(&self, default_name: &str) -> PathBuf {
+        let out_path = self
+            .out_path
+            .clone()
+            .unwrap_or_else(|| OutputConfig::default().pathout);
+        let file_path = self
+            .ply_out_path
+            .clone()
+            .unwrap_or_else(|| PathBuf::from(default_name));
+        output_path(&out_path, &file_path)
+    }
+
+    fn radius(&self) -> RadiusSource {
+        self.output_radius
+            .unwrap_or_else(|| OutputConfig::default().radius)
+    }
+}
+
+/// Computation options, overriding those of the configuration file
+///
+/// Defaults are those of `RunConfig`.
+#[derive(Args)]
+struct ComputeArgs {
+    /// Run configuration file (toml, or json with a `.json` extension)
+    #[arg(long = "config")]
+    config: Option<PathBuf>,
+    /// Maximal length of subdivided edges [default: 0.005]
+    #[arg(long = "dist_max")]
+    dist_max: Option<f64>,
+    /// Maximal number of subdivisions of an edge [default: 1]
+    #[arg(long = "subdiv_max")]
+    subdiv_max: Option<usize>,
+    /// Cost of burning along an arc of the graph [default: euclidean]
+    #[arg(value_enum, long = "metric")]
+    metric: Option<Metric>,
+    /// Weight of the radius difference in the scaled-radius metric, finite and non negative
+    /// [default: 1.0]
+    #[arg(long = "radius_scale")]
+    radius_scale: Option<f64>,
+    /// Erosion thickness written for vertices never burned [default: max]
+    #[arg(value_enum, long = "infinity")]
+    infinity: Option<InfinityPolicy>,
+    /// Encoding of output ply files [default: ascii]
+    #[arg(value_enum, long = "encoding")]
+    encoding: Option<PlyEncoding>,
+    /// Weld vertices, drop degenerate and duplicate faces, remove unreferenced vertices and orient faces
+    #[arg(long = "repair")]
+    repair: bool,
+    /// Distance below which repair welds vertices [default: 0.0]
+    #[arg(long = "weld_tol")]
+    weld_tol: Option<f64>,
+    /// Number of threads, 0 for one per core [default: 0]
+    #[arg(long = "threads")]
+    threads: Option<usize>,
+    #[command(flatten)]
+    color: ColorArgs,
+}
+
+/// Vertex color options, overriding those of the configuration file
+#[derive(Args)]
+struct ColorArgs {
+    /// Colormap of vertex colors [default: red-blue]
+    #[arg(value_enum, long = "colormap")]
+    colormap: Option<ColormapName>,
+    /// Fixed color range, min and max of the values by default
+    #[arg(long = "color_range", value_delimiter = ',', value_name = "MIN,MAX")]
+    range: Option<Vec<f64>>,
+    /// Color range between two percentiles (0 to 100) of the values
+    #[arg(
+        long = "color_percentile",
+        value_delimiter = ',',
+        value_name = "LOW,HIGH",
+        conflicts_with = "range"
+    )]
+    percentile: Option<Vec<f64>>,
+    /// Logarithmic color scale, non positive values getting the invalid color
+    #[arg(long = "color_log")]
+    log: bool,
+    /// Color of infinite, NaN and (log scale) non positive values [default: 255,0,255]
+    #[arg(long = "invalid_color", value_delimiter = ',', value_name = "R,G,B")]
+    invalid_color: Option<Vec<u8>>,
+    /// Do not write svg legends next to colored ply outputs
+    #[arg(long = "no_legend")]
+    no_legend: bool,
+    /// Also write png legends
+    #[arg(long = "legend_png")]
+    legend_png: bool,
+}
+
+impl ColorArgs {
+    fn apply(&self, config: &mut ColorConfig) -> Result<()> {
+        if let Some(colormap) = self.colormap {
+            config.colormap = colormap;
+        }
+        if let Some(range) = &self.range {
+            config.range = Some(fixed_len(range, "--color_range")?);
+            config.percentile = None;
+        }
+        if let Some(percentile) = &self.percentile {
+            config.percentile = Some(fixed_len(percentile, "--color_percentile")?);
+            config.range = None;
+        }
+        if self.log {
+            config.log = true;
+        }
+        if let Some(color) = &self.invalid_color {
+            config.invalid_color = fixed_len(color, "--invalid_color")?;
+        }
+        if self.no_legend {
+            config.legend = false;
+        }
+        if self.legend_png {
+            config.legend_png = true;
+        }
+        Ok(())
+    }
+}
+
+/// Comma separated values of a flag, as an array
+fn fixed_len<T: Copy, const N: usize>(values: &[T], flag: &str) -> Result<[T; N]> {
+    values
+        .try_into()
+        .map_err(|_| anyhow::Error::msg(format!("{} takes {} comma separated values", flag, N)))
+}
+
+impl ComputeArgs {
+    /// Configuration file (or defaults) overridden by the given flags
+    fn run_config(&self) -> Result<RunConfig> {
+        let mut config = match &self.config {
+            Some(path) => RunConfig::load(path)?,
+            None => RunConfig::default(),
+        };
+        if let Some(dist_max) = self.dist_max {
+            config.subdivision.dist_max = dist_max;
+        }
+        if let Some(subdiv_max) = self.subdiv_max {
+            config.subdivision.subdiv_max = subdiv_max;
+        }
+        if let Some(metric) = self.metric {
+            config.burn.metric = metric;
+        }
+        if let Some(radius_scale) = self.radius_scale {
+            config.burn.radius_scale = radius_scale;
+        }
+        if let Some(infinity) = self.infinity {
+            config.burn.infinity = infinity;
+        }
+        if let Some(encoding) = self.encoding {
+            config.output.encoding = encoding;
+        }
+        if self.repair {
+            config.repair.enabled = true;
+        }
+        if let Some(weld_tol) = self.weld_tol {
+            config.repair.weld_tol = weld_tol;
+        }
+        if let Some(threads) = self.threads {
+            config.threads = threads;
+        }
+        self.color.apply(&mut config.color)?;
+        Ok(config)
+    }
+}
+
+#[derive(Args)]
+struct ComputeCommand {
+    #[command(flatten)]
+    input: InputArgs,
+    #[command(flatten)]
+    compute: ComputeArgs,
+    #[command(flatten)]
+    output: OutputArgs,
+    /// [default: erosion_path.ply]
+    #[arg(long = "output_erosion_path")]
+    ply_erosion_out_path: Option<PathBuf>,
+    /// Do not write the erosion path
+    #[arg(long = "no_erosion_path")]
+    no_erosion_path: bool,
+    /// Also write the subdivided graph the burning is computed on (ply)
+    #[arg(long = "output_subdivided_graph")]
+    ply_subdivided_graph_out_path: Option<PathBuf>,
+    /// Also write numpy arrays of the subdivided graph, as a .npz archive or a directory of
+    /// .npy files: vertex arrays, skeleton vertices being the first rows, and the
+    /// subdivided faces, padded with -1
+    #[arg(long = "output_arrays")]
+    arrays_out_path: Option<PathBuf>,
+    /// Also write summary statistics, as csv if the name ends with .csv, json otherwise
+    #[arg(long = "report")]
+    report_out_path: Option<PathBuf>,
+    /// [default: components.csv]
+    #[arg(long = "output_components")]
+    csv_components_out_path: Option<PathBuf>,
+}
+
+#[derive(Args)]
+struct BatchCommand {
+    /// Directory (searched recursively), glob pattern or manifest file (one path per line)
+    #[arg(long = "input")]
+    input: String,
+    #[command(flatten)]
+    compute: ComputeArgs,
+    /// Output directory [default: ./output/]
+    #[arg(long = "pathout")]
+    out_path: Option<PathBuf>,
+    /// Do not write erosion paths
+    #[arg(long = "no_erosion_path")]
+    no_erosion_path: bool,
+}
+
+#[derive(Args)]
+struct StatsCommand {
+    #[command(flatten)]
+    input: InputArgs,
+}
+
+#[derive(Args)]
+struct PruneCommand {
+    #[command(flatten)]
+    input: InputArgs,
+    #[command(flatten)]
+    output: OutputArgs,
+    /// Float vertex property used for pruning
+    #[arg(default_value = "erosion_thickness", long = "property")]
+    property: String,
+    /// Faces with a vertex below this value are removed
+    #[arg(long = "threshold")]
+    threshold: f32,
+}
+
+#[derive(Args)]
+struct ConvertCommand {
+    #[command(flatten)]
+    input: InputArgs,
+    #[command(flatten)]
+    output: OutputArgs,
+    #[arg(value_enum, default_value = "ascii", long = "encoding")]
+    encoding: PlyEncoding,
+}
+
+#[derive(Args)]
+struct ColorCommand {
+    #[command(flatten)]
+    input: InputArgs,
+    #[command(flatten)]
+    output: OutputArgs,
+    /// Float vertex property to color
+    #[arg(default_value = "erosion_thickness", long = "property")]
+    property: String,
+    #[command(flatten)]
+    color: ColorArgs,
+}
+
+#[derive(Args)]
+struct CompareCommand {
+    /// First annotated skeleton
+    first: PathBuf,
+    /// Second annotated skeleton
+    second: PathBuf,
+    /// Number of bins of each descriptor histogram
+    #[arg(default_value_t = DEFAULT_NB_BINS, long = "bins")]
+    nb_bins: usize,
+    /// Radius convention of both skeletons, companion files being next to each input
+    #[command(flatten)]
+    radius: RadiusArgs,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Compute erosion thickness of a skeleton
+    Compute(ComputeCommand),
+    /// Check the input skeleton and report its defects
+    Validate(InputArgs),
+    /// Print a summary of radius and erosion thickness values of an annotated skeleton
+    Stats(StatsCommand),
+    /// Remove the parts of a skeleton below a threshold (erosion thickness by default)
+    Prune(PruneCommand),
+    /// Convert a skeleton between supported formats
+    Convert(ConvertCommand),
+    /// Set vertex colors from a float vertex property
+    Color(ColorCommand),
+    /// Compute erosion thickness of many skeletons, outputs mirroring the input tree
+    Batch(BatchCommand),
+    /// Print distances between the erosion thickness descriptors of two annotated skeletons
+    ///
+    /// Descriptors are weighted by face area. A skeleton whose faces all have zero area
+    /// (or without face) is described by its vertices instead, with equal weights.
+    Compare(CompareCommand),
+}
+
+#[derive(Parser)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+/// Output files of one computation
+struct Outputs {
+    skeleton: PathBuf,
+    erosion_path: Option<PathBuf>,
+    subdivided_graph: Option<PathBuf>,
+    arrays: Option<PathBuf>,
+    report: Option<PathBuf>,
+    components: PathBuf,
+}
+
+/// Summary of one computation
+struct ComputeSummary {
+    nb_vertices: usize,
+    /// range of finite erosion thickness values, None if all are infinite
+    et_range: Option<(f32, f32)>,
+}
+
+/// Path as a string, for the io functions
+fn path_str(path: &Path) -> Result<&str> {
+    path.to_str().ok_or(anyhow::Error::msg(format!(
+        "Invalid path {}",
+        path.display()
+    )))
+}
+
+/// Output file: `-` stands for standard output, other paths are relative to `out_path`
+fn output_path(out_path: &Path, file_path: &Path) -> PathBuf {
+    if compression::is_std_stream(file_path) {
+        file_path.to_path_buf()
+    } else {
+        out_path.join(file_path)
+    }
+}
+
+fn create_parent_dir(path: &Path) -> Result<()> {
+    if let Some(dir) = path.parent() {
+        if !compression::is_std_stream(path) {
+            fs::create_dir_all(dir)?;
+        }
+    }
+    Ok(())
+}
+
+fn validate(args: &InputArgs) -> Result<()> {
+    let skeleton = args.import()?;
+    let report = skeleton.validate();
+    println!("{}", report);
+    if report.has_errors() {
+        return Err(anyhow::Error::msg("Invalid skeleton"));
+    }
+    Ok(())
+}
+
+/// Min, max, mean and median of values
+fn value_summary(values: &[f32]) -> String {
+    let mut sorted: Vec<f32> = values.iter().copied().filter(|v| !v.is_nan()).collect();
+    if sorted.is_empty() {
+        return "no value".to_string();
+    }
+    sorted.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    let mean = sorted.iter().sum::<f32>() / sorted.len() as f32;
+    format!(
+        "min {} max {} mean {} median {}",
+        sorted[0],
+        sorted[sorted.len() - 1],
+        mean,
+        sorted[sorted.len() / 2]
+    )
+}
+
+fn stats(args: &StatsCommand) -> Result<()> {
+    let skeleton = args.input.import()?;
+    println!(
+        "{} vertices, {} faces",
+        skeleton.get_vertices().len(),
+        skeleton.get_face_vertices().len()
+    );
+    println!("radius: {}", value_summary(skeleton.get_radii()));
+    match skeleton.get_property_f32("erosion_thickness") {
+        Ok(et) => println!("erosion_thickness: {}", value_summary(&et)),
+        Err(_) => println!("erosion_thickness: not computed"),
+    }
+    Ok(())
+}
+
+fn compare(args: &CompareCommand) -> Result<()> {
+    let mut config = InputConfig::default();
+    args.radius.apply(&mut config);
+    let mut descriptors = Vec::new();
+    for path in [&args.first, &args.second] {
+        let skeleton = import_skeleton(path, &config)?;
+        let descriptor = Descriptor::new(&skeleton, args.nb_bins)
+            .map_err(|err| anyhow::Error::msg(format!("{}: {}", path.display(), err)))?;
+        descriptors.push(descriptor);
+    }
+    for distance in HistogramDistance::ALL {
+        println!(
+            "{} {}",
+            distance,
+            descriptors[0].distance(&descriptors[1], distance)?
+        );
+    }
+    Ok(())
+}
+
+fn prune(args: &PruneCommand) -> Result<()> {
+    let mut skeleton = args.input.import()?;
+    let summary = skeleton.prune_below(&args.property, args.threshold)?;
+    log::info!("prune: {}", summary);
+    let ply_out_path = args.output.skeleton_path("skeleton_pruned.ply");
+    create_parent_dir(&ply_out_path)?;
+    export_skeleton(
+        &skeleton,
+        &ply_out_path,
+        Encoding::Ascii,
+        args.output.radius(),
+    )
+}
+
+fn convert(args: &ConvertCommand) -> Result<()> {
+    let skeleton = args.input.import()?;
+    let ply_out_path = args.output.skeleton_path("skeleton.ply");
+    create_parent_dir(&ply_out_path)?;
+    export_skeleton(
+        &skeleton,
+        &ply_out_path,
+        args.encoding.into(),
+        args.output.radius(),
+    )
+}
+
+/// Reads a skeleton as obj, off or ma following its extension, as ply otherwise
+fn import_skeleton(file_path: &Path, config: &InputConfig) -> Result<Skeleton> {
+    match MeshFormat::from_path(file_path) {
+        Some(format) => {
+            let radius = config.convention(file_path);
+            io::import_from_mesh(path_str(file_path)?, format, &radius)
+        }
+        None => io::import_from_ply(path_str(file_path)?),
+    }
+}
+
+/// Writes a skeleton as vtk, glb, obj, off or ma following its extension, as ply with the
+/// given encoding otherwise
+fn export_skeleton(
+    skeleton: &Skeleton,
+    file_path: &Path,
+    encoding: Encoding,
+    radius: RadiusSource,
+) -> Result<()> {
+    if compression::format_extension(file_path) == Some("glb") {
+        return gltf::export_to_glb(skeleton, None, path_str(file_path)?);
+    }
+    if let Some(format) = VtkFormat::from_path(file_path) {
+        return vtk::export_to_vtk(skeleton, path_str(file_path)?, format);
+    }
+    match MeshFormat::from_path(file_path) {
+        Some(format) => {
+            let radius = config::output_convention(radius, file_path);
+            io::export_to_mesh(skeleton, path_str(file_path)?, format, &radius)
+        }
+        None => io::export_to_ply_with_encoding(skeleton, path_str(file_path)?, encoding),
+    }
+}
+
+/// Writes an erosion path as ply with the given encoding, or as vtk, glb or graph
+/// (graphml, dot, json) following its extension
+fn export_erosion_path(
+    erosion_path: &ErosionPath,
+    file_path: &Path,
+    encoding: Encoding,
+) -> Result<()> {
+    if compression::format_extension(file_path) == Some("glb") {
+        return gltf::export_erosion_path_to_glb(erosion_path, path_str(file_path)?);
+    }
+    if let Some(format) = GraphFormat::from_path(file_path) {
+        return graph::export_erosion_path_to_graph(erosion_path, path_str(file_path)?, format);
+    }
+    match VtkFormat::from_path(file_path) {
+        Some(format) => vtk::export_erosion_path_to_vtk(erosion_path, path_str(file_path)?, format),
+        None => io::export_erosion_path_to_ply_with_encoding(
+            erosion_path,
+            path_str(file_path)?,
+            encoding,
+        ),
+    }
+}
+
+/// File name without its format and compression extensions
+fn output_stem(file_path: &Path) -> String {
+    let file_path = match Compression::from_path(file_path) {
+        Compression::None => file_path.to_path_buf(),
+        _ => file_path.with_extension(""),
+    };
+    file_path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_default()
+}
+
+/// Writes the legends of the colors given by `scale` to `values`, next to `ply_path`
+fn write_legends(
+    ply_path: &Path,
+    title: &str,
+    scale: &ColorScale,
+    values: &[f32],
+    config: &ColorConfig,
+) -> Result<()> {
+    if compression::is_std_stream(ply_path) {
+        return Ok(());
+    }
+    let legend = Legend::new(title, scale, values)?;
+    let stem = output_stem(ply_path);
+    if config.legend {
+        legend.write_svg(&ply_path.with_file_name(format!("{}_legend.svg", stem)))?;
+    }
+    if config.legend_png {
+        legend.write_png(&ply_path.with_file_name(format!("{}_legend.png", stem)))?;
+    }
+    Ok(())
+}
+
+fn color(args: &ColorCommand) -> Result<()> {
+    let mut config = ColorConfig::default();
+    args.color.apply(&mut config)?;
+    let mut skeleton = args.input.import()?;
+    let scale = config.scale()?;
+    match &scale {
+        Some(scale) => skeleton.set_vertex_color_with_scale(&args.property, scale)?,
+        None => {
+            for color in COLOR_PROPERTIES {
+                skeleton.remove_vertex_property(color);
+            }
+        }
+    }
+    let ply_out_path = args.output.skeleton_path("skeleton_colored.ply");
+    create_parent_dir(&ply_out_path)?;
+    export_skeleton(
+        &skeleton,
+        &ply_out_path,
+        Encoding::Ascii,
+        args.output.radius(),
+    )?;
+    if let Some(scale) = &scale {
+        let values = skeleton.get_property_f32(&args.property)?;
+        write_legends(&ply_out_path, &args.property, scale, &values, &config)?;
+    }
+    Ok(())
+}
+
+/// Computes erosion thickness of one skeleton and writes the outputs
+fn compute(ply_in_path: &Path, config: &RunConfig, outputs: &Outputs) -> Result<ComputeSummary> {
+    let metric: Box<dyn BurnMetric> = match config.burn.metric {
+        Metric::Euclidean => Box::new(Euclidean),
+        Metric::MedialBall => Box::new(MedialBall),
+        Metric::ScaledRadius => Box::new(ScaledRadius::new(config.burn.radius_scale as f32)?),
+    };
+
+    let start = Instant::now();
+    let mut skeleton = import_skeleton(ply_in_path, &config.input)?;
+    let mut timings = vec![("import", start.elapsed().as_secs_f32())];
+    if config.repair.enabled {
+        let start = Instant::now();
+        let summary = skeleton.repair(config.repair.weld_tol as f32)?;
+        log::info!("repair: {}", summary);
+        timings.push(("repair", start.elapsed().as_secs_f32()));
+    }
+    // the subdivided graph is only kept when exported
+    let computation = if outputs.subdivided_graph.is_some() || outputs.arrays.is_some() {
+        erosion_thickness_computation_with_graph
+    } else {
+        erosion_thickness_computation_with_metric
+    };
+    let mut result = computation(
+        &mut skeleton,
+        config.subdivision.dist_max as f32,
+        config.subdivision.subdiv_max,
+        metric.as_ref(),
+    )?;
+    // labelled once the computation has validated the skeleton
+    let components = skeleton.label_components()?;
+    let component_stats = components.statistics(&skeleton, &result.erosion_thickness)?;
+    for stat in component_stats.iter() {
+        log::info!("{}", stat);
+    }
+
+    if let Some(value) = config.burn.infinity.value() {
+        let et_values: Vec<f32> = result
+            .erosion_thickness
+            .iter()
+            .map(|et| et.unwrap_or(value))
+            .collect();
+        skeleton.set_property_f32("erosion_thickness", &et_values)?;
+    }
+    // vertices never burnt get the invalid color, whatever value is written for them
+    let et_colored: Vec<f32> = result
+        .erosion_thickness
+        .iter()
+        .map(|et| et.unwrap_or(f32::INFINITY))
+        .collect();
+    // burn times have their own range
+    let scales = config.color.scale()?.map(|scale| {
+        let burntime_scale = ColorScale {
+            range: ColorRange::MinMax,
+            ..scale
+        };
+        (scale, burntime_scale)
+    });
+    match &scales {
+        Some((scale, burntime_scale)) => {
+            skeleton.set_vertex_color_from_values(&et_colored, scale)?;
+            result
+                .erosion_path
+                .set_vertex_color_with_scale("burntime", burntime_scale)?;
+        }
+        None => {
+            for color in COLOR_PROPERTIES {
+                skeleton.remove_vertex_property(color);
+                result.erosion_path.remove_vertex_property(color);
+            }
+        }
+    }
+
+    let et_range = result
+        .erosion_thickness
+        .iter()
+        .flatten()
+        .fold(None, |range, &et| match range {
+            None => Some((et, et)),
+            Some((et_min, et_max)) => Some((f32::min(et_min, et), f32::max(et_max, et))),
+        });
+    // the provenance of a previous run is replaced
+    let provenance = Provenance::new(ply_in_path, config, &result, et_range, timings)?;
+    skeleton.retain_comments(|comment| !comment.starts_with(PROVENANCE_PREFIX));
+    for comment in provenance.comments() {
+        skeleton.add_comment(&comment);
+        result.erosion_path.add_comment(&comment);
+        if let Some(graph) = &mut result.subdivided_graph {
+            graph.add_comment(&comment);
+        }
+    }
+
+    let mut out_paths = vec![&outputs.skeleton, &outputs.components];
+    out_paths.extend(outputs.erosion_path.iter());
+    out_paths.extend(outputs.subdivided_graph.iter());
+    out_paths.extend(outputs.arrays.iter());
+    out_paths.extend(outputs.report.iter());
+    for path in out_paths {
+        create_parent_dir(path)?;
+    }
+    let encoding = config.output.encoding.into();
+    if compression::format_extension(&outputs.skeleton) == Some("glb") {
+        // the erosion path is part of the glb scene
+        let erosion_path = outputs.erosion_path.as_ref().map(|_| &result.erosion_path);
+        gltf::export_to_glb(&skeleton, erosion_path, path_str(&outputs.skeleton)?)?;
+    } else {
+        export_skeleton(&skeleton, &outputs.skeleton, encoding, config.output.radius)?;
+    }
+    if let Some(erosion_path) = &outputs.erosion_path {
+        export_erosion_path(&result.erosion_path, erosion_path, encoding)?;
+    }
+    if let (Some(graph), Some(path)) = (&result.subdivided_graph, &outputs.subdivided_graph) {
+        io::export_subdivided_graph_to_ply_with_encoding(graph, path_str(path)?, encoding)?;
+    }
+    if let (Some(graph), Some(path)) = (&result.subdivided_graph, &outputs.arrays) {
+        if compression::format_extension(path) == Some("npz") {
+            npy::export_graph_to_npz(graph, path_str(path)?)?;
+        } else {
+            npy::export_graph_to_npy_dir(graph, path_str(path)?)?;
+        }
+    }
+    io::export_component_stats_to_csv(&component_stats, path_str(&outputs.components)?)?;
+    if let Some(path) = &outputs.report {
+        Report::new(&skeleton, &result, &provenance.timings).save(path_str(path)?)?;
+    }
+    if let Some((scale, burntime_scale)) = &scales {
+        write_legends(
+            &outputs.skeleton,
+            "erosion_thickness",
+            scale,
+            &et_colored,
+            &config.color,
+        )?;
+        if let Some(erosion_path) = &outputs.erosion_path {
+            let values = result.erosion_path.get_property_f32("burntime")?;
+            write_legends(
+                erosion_path,
+                "burntime",
+                burntime_scale,
+                &values,
+                &config.color,
+            )?;
+        }
+    }
+
+    Ok(ComputeSummary {
+        nb_vertices: skeleton.get_vertices().len(),
+        et_range,
+    })
+}
+
+fn compute_command(args: &ComputeCommand) -> Result<()> {
+    let mut config = args.compute.run_config()?;
+    if let Some(out_path) = &args.output.out_path {
+        config.output.pathout = out_path.clone();
+    }
+    if let Some(ply_out_path) = &args.output.ply_out_path {
+        config.output.skeleton = ply_out_path.clone();
+    }
+    if let Some(radius) = args.output.output_radius {
+        config.output.radius = radius;
+    }
+    args.input.apply(&mut config.input);
+    if let Some(ply_erosion_out_path) = &args.ply_erosion_out_path {
+        config.output.erosion_path = ply_erosion_out_path.clone();
+    }
+    if args.no_erosion_path {
+        config.output.no_erosion_path = true;
+    }
+    if let Some(ply_subdivided_graph_out_path) = &args.ply_subdivided_graph_out_path {
+        config.output.subdivided_graph = Some(ply_subdivided_graph_out_path.clone());
+    }
+    if let Some(arrays_out_path) = &args.arrays_out_path {
+        config.output.arrays = Some(arrays_out_path.clone());
+    }
+    if let Some(report_out_path) = &args.report_out_path {
+        config.output.report = Some(report_out_path.clone());
+    }
+    if let Some(csv_components_out_path) = &args.csv_components_out_path {
+        config.output.components = csv_components_out_path.clone();
+    }
+
+    let out_path = &config.output.pathout;
+    let outputs = Outputs {
+        skeleton: output_path(out_path, &config.output.skeleton),
+        erosion_path: if config.output.no_erosion_path {
+            None
+        } else {
+            Some(output_path(out_path, &config.output.erosion_path))
+        },
+        subdivided_graph: config
+            .output
+            .subdivided_graph
+            .as_ref()
+            .map(|path| output_path(out_path, path)),
+        arrays: config
+            .output
+            .arrays
+            .as_ref()
+            .map(|path| output_path(out_path, path)),
+        report: config
+            .output
+            .report
+            .as_ref()
+            .map(|path| output_path(out_path, path)),
+        components: output_path(out_path, &config.output.components),
+    };
+    let nb_std_outputs = [
+        Some(&outputs.skeleton),
+        outputs.erosion_path.as_ref(),
+        outputs.subdivided_graph.as_ref(),
+        outputs.arrays.as_ref(),
+        outputs.report.as_ref(),
+    ]
+    .into_iter()
+    .flatten()
+    .chain([&outputs.components])
+    .filter(|path| compression::is_std_stream(path))
+    .count();
+    if nb_std_outputs > 1 {
+        return Err(anyhow::Error::msg(
+            "Only one output can be written to standard output",
+        ));
+    }
+
+    parallel::init_thread_pool(config.threads)?;
+    compute(&args.input.ply_in_path, &config, &outputs)?;
+    // effective configuration, next to the output skeleton
+    if !compression::is_std_stream(&outputs.skeleton) {
+        config.save(&outputs.skeleton.with_file_name(CONFIG_FILE_NAME))?;
+    }
+    Ok(())
+}
+
+fn batch_command(args: &BatchCommand) -> Result<()> {
+    let mut config = args.compute.run_config()?;
+    if let Some(out_path) = &args.out_path {
+        config.output.pathout = out_path.clone();
+    }
+    if args.no_erosion_path {
+        config.output.no_erosion_path = true;
+    }
+    parallel::init_thread_pool(config.threads)?;
+    fs::create_dir_all(&config.output.pathout)?;
+    config.save(&config.output.pathout.join(CONFIG_FILE_NAME))?;
+    batch::run(&args.input, &config)
+}
+
+fn main() -> Result<()> {
+    let args = Cli::parse();
+    env_logger::init();
+
+    match &args.command {
+        Command::Compute(args) => compute_command(args),
+        Command::Validate(args) => validate(args),
+        Command::Stats(args) => stats(args),
+        Command::Prune(args) => prune(args),
+        Command::Convert(args) => convert(args),
+        Command::Color(args) => color(args),
+        Command::Batch(args) => batch_command(args),
+        Command::Compare(args) => compare(args),
+    }
+}
@@ -0,0 +1,105 @@
+use anyhow::Result;
+use sha2::{Digest, Sha256};
+use std::fs::File;
+use std::path::Path;
+
+use erosion_thickness::et_algorithm::algorithm::ETResult;
+use erosion_thickness::skeleton::compression;
+
+use super::config::RunConfig;
+
+/// Prefix of the ply comments written by soft_et, replaced on each run
+pub const PROVENANCE_PREFIX: &str = "soft_et ";
+
+/// How an annotated skeleton was produced
+pub struct Provenance {
+    /// input path, and sha256 of the file as stored on disk (None for standard input)
+    pub input: String,
+    pub input_sha256: Option<String>,
+    pub config_json: String,
+    pub nb_vertices: usize,
+    pub nb_boundary: usize,
+    pub nb_infinite: usize,
+    pub et_range: Option<(f32, f32)>,
+    /// phase names and wall-clock times in seconds
+    pub timings: Vec<(&'static str, f32)>,
+}
+
+/// Hex sha256 of a file
+pub fn file_sha256(file_path: &Path) -> Result<String> {
+    let mut hasher = Sha256::new();
+    std::io::copy(&mut File::open(file_path)?, &mut hasher)?;
+    Ok(hasher
+        .finalize()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect())
+}
+
+impl Provenance {
+    /// Provenance of a computation, `timings` holding the phases before it
+    pub fn new(
+        ply_in_path: &Path,
+        config: &RunConfig,
+        result: &ETResult,
+        et_range: Option<(f32, f32)>,
+        mut timings: Vec<(&'static str, f32)>,
+    ) -> Result<Provenance> {
+        let input_sha256 = if compression::is_std_stream(ply_in_path) {
+            None
+        } else {
+            Some(file_sha256(ply_in_path)?)
+        };
+        timings.extend([
+            ("graph", result.timings.graph),
+            ("burn", result.timings.burn),
+            ("result", result.timings.result),
+        ]);
+        Ok(Provenance {
+            input: ply_in_path.display().to_string(),
+            input_sha256,
+            config_json: serde_json::to_string(config)?,
+            nb_vertices: result.erosion_thickness.len(),
+            nb_boundary: result.nb_boundary,
+            nb_infinite: result
+                .erosion_thickness
+                .iter()
+                .filter(|et| et.is_none())
+                .count(),
+            et_range,
+            timings,
+        })
+    }
+
+    /// Ply comment lines, all starting with `PROVENANCE_PREFIX`
+    pub fn comments(&self) -> Vec<String> {
+        let input = match &self.input_sha256 {
+            Some(hash) => format!("input {} sha256 {}", self.input, hash),
+            None => format!("input {} (standard input)", self.input),
+        };
+        let et_range = match self.et_range {
+            Some((et_min, et_max)) => format!("erosion_thickness min {} max {}", et_min, et_max),
+            None => "erosion_thickness min inf max inf".to_string(),
+        };
+        let timings = self
+            .timings
+            .iter()
+            .map(|(phase, seconds)| format!("{} {:.3}", phase, seconds))
+            .collect::<Vec<String>>()
+            .join(" ");
+        [
+            format!("version {}", env!("CARGO_PKG_VERSION")),
+            input,
+            format!("config {}", self.config_json),
+            format!(
+                "vertices {} boundary {} infinite {}",
+                self.nb_vertices, self.nb_boundary, self.nb_infinite
+            ),
+            et_range,
+            format!("time_s {}", timings),
+        ]
+        .into_iter()
+        .map(|comment| format!("{}{}", PROVENANCE_PREFIX, comment))
+        .collect()
+    }
+}
@@ -1,92 +1,177 @@
 use anyhow::Result;
 use log;
+use std::time::Instant;
 use std::{cmp::Ordering, collections::HashSet};
 
+use super::metric::{BurnMetric, Euclidean};
 use super::{burntime::BurnTime, graph::ETGraph, vertex::ErosionThickness};
 use crate::skeleton::erosion_path::ErosionPath;
 use crate::skeleton::skeleton::Skeleton;
+use crate::skeleton::subdivided_graph::SubdividedGraph;
+use crate::skeleton::validation::Severity;
+
+/// Output of the erosion thickness computation
+pub struct ETResult {
+    pub erosion_path: ErosionPath,
+    /// burn time of each skeleton vertex, None if never reached by the burn front
+    pub burntime: Vec<Option<f32>>,
+    /// erosion thickness of each skeleton vertex, None if never reached by the burn front
+    pub erosion_thickness: Vec<Option<f32>>,
+    /// number of skeleton vertices on the boundary, where the burn front starts
+    pub nb_boundary: usize,
+    /// graph the burning was computed on, if asked for
+    pub subdivided_graph: Option<SubdividedGraph>,
+    pub timings: Timings,
+}
+
+/// Wall-clock time of each phase of the computation, in seconds
+pub struct Timings {
+    /// subdivision and sector computation
+    pub graph: f32,
+    /// burn front propagation
+    pub burn: f32,
+    /// erosion path and skeleton properties
+    pub result: f32,
+}
 
 pub fn erosion_thickness_computation(
     skeleton: &mut Skeleton,
     dist_max: f32,
     subdiv_max: usize,
 ) -> Result<ErosionPath> {
-    let mut et_graph = ETGraph::new(skeleton, dist_max, subdiv_max);
+    let result =
+        erosion_thickness_computation_with_metric(skeleton, dist_max, subdiv_max, &Euclidean)?;
+    Ok(result.erosion_path)
+}
+
+pub fn erosion_thickness_computation_with_metric(
+    skeleton: &mut Skeleton,
+    dist_max: f32,
+    subdiv_max: usize,
+    metric: &dyn BurnMetric,
+) -> Result<ETResult> {
+    computation(skeleton, dist_max, subdiv_max, metric, false)
+}
+
+/// Same as `erosion_thickness_computation_with_metric`, also returning the subdivided
+/// graph the burning was computed on, for inspection
+pub fn erosion_thickness_computation_with_graph(
+    skeleton: &mut Skeleton,
+    dist_max: f32,
+    subdiv_max: usize,
+    metric: &dyn BurnMetric,
+) -> Result<ETResult> {
+    computation(skeleton, dist_max, subdiv_max, metric, true)
+}
+
+/// Erosion thickness computation, keeping the subdivided faces and returning the
+/// subdivided graph with `keep_graph`
+fn computation(
+    skeleton: &mut Skeleton,
+    dist_max: f32,
+    subdiv_max: usize,
+    metric: &dyn BurnMetric,
+    keep_graph: bool,
+) -> Result<ETResult> {
+    let report = skeleton.validate();
+    for issue in report.issues() {
+        log::debug!("{}", issue);
+    }
+    if report.has_errors() {
+        let first_error = report
+            .issues()
+            .iter()
+            .find(|issue| issue.severity == Severity::Error)
+            .unwrap();
+        return Err(anyhow::Error::msg(format!(
+            "Invalid skeleton, {} error(s), first one: {}",
+            report.count(Severity::Error),
+            first_error
+        )));
+    }
+
+    let start = Instant::now();
+    let mut et_graph = ETGraph::new(skeleton, dist_max, subdiv_max, keep_graph)?;
+    let graph_time = start.elapsed().as_secs_f32();
+    let nb_boundary = (0..skeleton.get_vertices().len())
+        .filter(|&i| et_graph.is_boundary(i))
+        .count();
+
+    let start = Instant::now();
 
     let mut q = HashSet::new();
-    for i in 0..et_graph.get_vertices().len() {
-        if et_graph.get_vertices()[i].is_boundary() {
-            let rad = et_graph.get_vertices()[i].rad();
-            et_graph.get_vertices()[i].set_time(rad);
+    for i in 0..et_graph.nb_vertices() {
+        if et_graph.is_boundary(i) {
+            let rad = et_graph.rad(i);
+            et_graph.set_time(i, rad);
             q.insert(i);
         }
     }
 
     let mut cpt = 0;
     while !q.is_empty() {
-        cpt = cpt + 1;
+        cpt += 1;
         let mut v = None;
         let mut t_min = BurnTime::Infinity;
         for &ind_v in q.iter() {
-            if et_graph.get_vertices()[ind_v].time().inf_eq(&t_min) {
+            if et_graph.time(ind_v).inf_eq(&t_min) {
                 v = Some(ind_v);
-                t_min = *et_graph.get_vertices()[ind_v].time();
+                t_min = *et_graph.time(ind_v);
             }
         }
         let v = v.unwrap();
         q.remove(&v);
-        let v_time = if let &BurnTime::Time(t) = et_graph.get_vertices()[v].time() {
+        let v_time = if let &BurnTime::Time(t) = et_graph.time(v) {
             t
         } else {
             continue;
         };
 
-        if et_graph.get_vertices()[v].is_burned() {
+        if et_graph.is_burned(v) {
             continue;
         }
         log::info!("step {}: q: {}, v: {}, v_time: {}", cpt, q.len(), v, v_time);
 
-        if let &Some(prime_sector) = et_graph.get_vertices()[v].prime_sector() {
-            et_graph.get_vertices()[v].burn_sector(prime_sector);
+        if let Some(prime_sector) = et_graph.prime_sector(v) {
+            et_graph.burn_sector(v, prime_sector);
         }
 
-        let exposed_sectors = et_graph.get_vertices()[v].get_exposed_sectors();
+        let exposed_sectors = et_graph.get_exposed_sectors(v);
         for ind_sec in exposed_sectors {
-            et_graph.get_vertices()[v].burn_sector(ind_sec);
-            et_graph.get_vertices()[v].sectors()[ind_sec].set_time(v_time);
+            et_graph.burn_sector(v, ind_sec);
+            et_graph.set_sector_time(v, ind_sec, v_time);
         }
 
-        let unburned_sectors = et_graph.get_vertices()[v].get_unburned_sectors();
+        let unburned_sectors = et_graph.get_unburned_sectors(v);
         if unburned_sectors.is_empty() {
-            et_graph.get_vertices()[v].burn();
+            et_graph.burn(v);
             // update all Neighbors
-            for i in 0..et_graph.get_vertices()[v].neigh().len() {
-                let u = et_graph.get_vertices()[v].neigh()[i];
-                if !et_graph.get_vertices()[u].is_burned() {
+            for i in 0..et_graph.neigh(v).len() {
+                let u = et_graph.neigh(v)[i] as usize;
+                if !et_graph.is_burned(u) {
                     // detection of sector and arc of u that contains v
-                    let num_neigh_v = et_graph.get_vertices()[u].get_num_neigh(v).unwrap();
-                    let vec_t = et_graph.get_vertices()[u].attached_sectors(num_neigh_v);
-
-                    let arc_norm = (et_graph.get_vertices()[v].pos()
-                        - et_graph.get_vertices()[u].pos())
-                    .norm();
-                    for t in vec_t {
-                        if !et_graph.get_vertices()[u].sectors()[t].is_burned() {
+                    let num_neigh_v = et_graph.get_num_neigh(u, v).unwrap();
+
+                    let arc_cost = metric.arc_cost(
+                        &et_graph.pos(v),
+                        et_graph.rad(v),
+                        &et_graph.pos(u),
+                        et_graph.rad(u),
+                    );
+                    for j in 0..et_graph.attached_sectors(u, num_neigh_v).len() {
+                        let t = et_graph.attached_sectors(u, num_neigh_v)[j] as usize;
+                        if !et_graph.sector_is_burned(u, t) {
                             // computation of new burn time for u
-                            let h = arc_norm + v_time;
-                            if BurnTime::Time(h)
-                                .inf_eq(et_graph.get_vertices()[u].sectors()[t].time())
-                            {
+                            let h = arc_cost + v_time;
+                            if BurnTime::Time(h).inf_eq(et_graph.sector_time(u, t)) {
                                 // update on sector t if burntime is lower
-                                et_graph.get_vertices()[u].sectors()[t].set_time(h);
-                                let ind_prime_arc = et_graph.get_vertices()[u].sectors()[t]
-                                    .get_arc(num_neigh_v)
-                                    .unwrap();
-                                et_graph.get_vertices()[u].sectors()[t]
-                                    .set_prime_arc(ind_prime_arc);
-                                if BurnTime::Time(h).inf_eq(et_graph.get_vertices()[u].time()) {
-                                    et_graph.get_vertices()[u].set_time(h);
-                                    et_graph.get_vertices()[u].set_prime_sector(t);
+                                et_graph.set_sector_time(u, t, h);
+                                let ind_prime_arc =
+                                    et_graph.sector_get_arc(u, t, num_neigh_v).unwrap();
+                                et_graph.set_sector_prime_arc(u, t, ind_prime_arc);
+                                if BurnTime::Time(h).inf_eq(et_graph.time(u)) {
+                                    et_graph.set_time(u, h);
+                                    et_graph.set_prime_sector(u, t);
                                     q.insert(u);
                                 }
                             }
@@ -98,8 +183,8 @@ pub fn erosion_thickness_computation(
             let sec_min = unburned_sectors
                 .into_iter()
                 .min_by(|&s1, &s2| {
-                    let &t1 = et_graph.get_vertices()[v].sectors()[s1].time();
-                    let &t2 = et_graph.get_vertices()[v].sectors()[s2].time();
+                    let &t1 = et_graph.sector_time(v, s1);
+                    let &t2 = et_graph.sector_time(v, s2);
                     if t1.inf_eq(&t2) {
                         Ordering::Less
                     } else {
@@ -108,31 +193,32 @@ pub fn erosion_thickness_computation(
                 })
                 .unwrap();
 
-            if let &BurnTime::Time(t) = et_graph.get_vertices()[v].sectors()[sec_min].time() {
-                et_graph.get_vertices()[v].set_prime_sector(sec_min);
-                et_graph.get_vertices()[v].set_time(t);
+            if let &BurnTime::Time(t) = et_graph.sector_time(v, sec_min) {
+                et_graph.set_prime_sector(v, sec_min);
+                et_graph.set_time(v, t);
                 q.insert(v);
             } else {
-                et_graph.get_vertices()[v].reset_prime_sector();
-                et_graph.get_vertices()[v].reset_time();
+                et_graph.reset_prime_sector(v);
+                et_graph.reset_time(v);
                 q.remove(&v);
             }
         }
     }
 
+    let burn_time = start.elapsed().as_secs_f32();
+
+    let start = Instant::now();
     let mut erosion_path = ErosionPath::new();
 
     let mut bt_max = 0.0;
     let mut et_max = 0.0;
     for i in 0..skeleton.get_vertices().len() {
-        let bt = et_graph.get_vertices()[i].time();
-        if let &BurnTime::Time(bt) = bt {
+        if let &BurnTime::Time(bt) = et_graph.time(i) {
             if bt > bt_max {
                 bt_max = bt;
             }
         }
-        let et = et_graph.get_vertices()[i].erosion_thickness();
-        if let ErosionThickness::ET(et) = et {
+        if let ErosionThickness::ET(et) = et_graph.erosion_thickness(i) {
             if et > et_max {
                 et_max = et;
             }
@@ -142,37 +228,101 @@ pub fn erosion_thickness_computation(
     // building erosion path structure
     let mut prime_arcs = Vec::new();
 
-    for i in 0..et_graph.get_vertices().len() {
-        let v = &et_graph.get_vertices()[i];
-        if let Some(ind_prime) = v.prime_neighbor() {
+    for i in 0..et_graph.nb_vertices() {
+        if let Some(ind_prime) = et_graph.prime_neighbor(i) {
             prime_arcs.push([i, ind_prime]);
         }
-        let bt = if let &BurnTime::Time(bt) = v.time() {
+        let bt = if let &BurnTime::Time(bt) = et_graph.time(i) {
             bt
         } else {
             bt_max
         };
-        erosion_path.add_vertex(v.pos(), bt);
+        erosion_path.add_vertex(et_graph.pos(i), bt);
     }
 
     for i in 0..prime_arcs.len() {
         erosion_path.add_edge(prime_arcs[i]);
     }
+    // skeleton vertices come first in the graph
+    erosion_path.set_original_vertices(skeleton.get_vertices().len());
     erosion_path.set_vertex_color_from_property_f32("burntime")?;
 
     // updating skeleton structure
     let mut et_values: Vec<f32> = Vec::new();
+    let mut burntime = Vec::new();
+    let mut erosion_thickness = Vec::new();
     for i in 0..skeleton.get_vertices().len() {
-        let et = et_graph.get_vertices()[i].erosion_thickness();
-        if let ErosionThickness::ET(et) = et {
+        if let ErosionThickness::ET(et) = et_graph.erosion_thickness(i) {
             et_values.push(et);
+            erosion_thickness.push(Some(et));
         } else {
             et_values.push(et_max);
+            erosion_thickness.push(None);
+        }
+        if let &BurnTime::Time(bt) = et_graph.time(i) {
+            burntime.push(Some(bt));
+        } else {
+            burntime.push(None);
         }
     }
 
     skeleton.set_property_f32("erosion_thickness", &et_values)?;
     skeleton.set_vertex_color_from_property_f32("erosion_thickness")?;
 
-    Ok(erosion_path)
+    let subdivided_graph = if keep_graph {
+        Some(subdivided_graph(&et_graph, skeleton.get_vertices().len()))
+    } else {
+        None
+    };
+
+    Ok(ETResult {
+        erosion_path,
+        burntime,
+        erosion_thickness,
+        nb_boundary,
+        subdivided_graph,
+        timings: Timings {
+            graph: graph_time,
+            burn: burn_time,
+            result: start.elapsed().as_secs_f32(),
+        },
+    })
+}
+
+/// Copy of the burnt graph: vertices with their state, pairs of neighbors and faces
+fn subdivided_graph(et_graph: &ETGraph, nb_original: usize) -> SubdividedGraph {
+    let mut graph = SubdividedGraph::new(nb_original);
+    for i in 0..et_graph.nb_vertices() {
+        let burntime = if let &BurnTime::Time(bt) = et_graph.time(i) {
+            Some(bt)
+        } else {
+            None
+        };
+        let erosion_thickness = if let ErosionThickness::ET(et) = et_graph.erosion_thickness(i) {
+            Some(et)
+        } else {
+            None
+        };
+        graph.add_vertex(
+            et_graph.pos(i),
+            et_graph.rad(i),
+            burntime,
+            erosion_thickness,
+            et_graph.is_boundary(i),
+            et_graph.prime_neighbor(i),
+        );
+    }
+    for i in 0..et_graph.nb_vertices() {
+        for &j in et_graph.neigh(i) {
+            if i < j as usize {
+                graph.add_edge([i, j as usize]);
+            }
+        }
+    }
+    if let Some(faces) = et_graph.faces() {
+        for face in faces {
+            graph.add_face(face.iter().map(|&v| v as usize).collect());
+        }
+    }
+    graph
 }
@@ -0,0 +1,70 @@
+/// Set of flags, packed in 64 bits words
+#[derive(Clone)]
+pub struct BitSet {
+    words: Vec<u64>,
+}
+
+impl BitSet {
+    pub fn new() -> BitSet {
+        BitSet { words: Vec::new() }
+    }
+
+    pub fn with_len(len: usize) -> BitSet {
+        BitSet {
+            words: vec![0; len.div_ceil(64)],
+        }
+    }
+
+    pub fn get(&self, i: usize) -> bool {
+        self.words
+            .get(i / 64)
+            .is_some_and(|w| (w >> (i % 64)) & 1 == 1)
+    }
+
+    pub fn set(&mut self, i: usize, val: bool) {
+        if i / 64 >= self.words.len() {
+            self.words.resize(i / 64 + 1, 0);
+        }
+        if val {
+            self.words[i / 64] |= 1 << (i % 64);
+        } else {
+            self.words[i / 64] &= !(1 << (i % 64));
+        }
+    }
+
+    pub fn heap_size(&self) -> usize {
+        self.words.capacity() * std::mem::size_of::<u64>()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn set_and_get() {
+        let mut bits = BitSet::with_len(130);
+        assert_eq!(bits.heap_size(), 3 * 8);
+        for i in [0, 63, 64, 129] {
+            assert!(!bits.get(i));
+            bits.set(i, true);
+            assert!(bits.get(i));
+        }
+        assert!(!bits.get(1) && !bits.get(65) && !bits.get(128));
+        bits.set(63, false);
+        assert!(!bits.get(63));
+        assert!(bits.get(0) && bits.get(64));
+    }
+
+    #[test]
+    fn grows_on_set() {
+        let mut bits = BitSet::new();
+        assert!(!bits.get(1000));
+        bits.set(200, false);
+        assert!(!bits.get(200));
+        bits.set(1000, true);
+        assert!(bits.get(1000));
+        assert!(!bits.get(999));
+        assert!(bits.heap_size() >= 16 * 8);
+    }
+}
@@ -1,63 +1,147 @@
+use anyhow::Result;
 use log;
+use nalgebra::base::*;
+use std::mem::size_of;
 
-use super::vertex::Vertex;
+use super::bitset::BitSet;
+use super::burntime::BurnTime;
+use super::parallel;
+use super::sector::Sectors;
+use super::vertex::{ErosionThickness, VertexLink};
 use crate::skeleton::skeleton::Skeleton;
+use crate::skeleton::topology::Topology;
 
-pub struct ETGraph<'a> {
-    skel: &'a Skeleton,
+/// Graph on which the burning is computed: skeleton vertices and subdivision vertices,
+/// with their link and sectors
+///
+/// Storage is flat: per vertex arrays, and compressed rows (offsets + values) for
+/// neighbors and sectors. The link adjacency is only needed to compute sectors, after
+/// that it is summed up by the sectors attached to each neighbor. Neighbors of a vertex
+/// are referred to by their slot, i.e. their position among the vertex neighbors, and
+/// sectors by their position among the vertex sectors.
+///
+/// Indices are stored as u32: the graph is limited to `u32::MAX` vertices and vertex
+/// occurrences in subdivided faces, `ETGraph::new` failing beyond.
+pub struct ETGraph {
+    pos: Vec<Vector3<f32>>,
+    rad: Vec<f32>,
+    time: Vec<BurnTime>,
+    prime_sector: Vec<Option<u32>>,
+    burned: BitSet,
+    boundary: BitSet,
 
-    vert: Vec<Vertex>,
+    // vertex -> neighbor slots
+    neigh_offsets: Vec<usize>,
+    neigh: Vec<u32>,
+    // neighbor slot -> sectors containing the slot
+    slot_sector_offsets: Vec<usize>,
+    slot_sectors: Vec<u32>,
+    // vertex -> sectors
+    sector_offsets: Vec<usize>,
+    sectors: Sectors,
+    // subdivided faces, only kept for export
+    faces: Option<SubdivFaces>,
 }
 
-impl<'a> ETGraph<'a> {
-    pub fn new(skel: &'a Skeleton, dist_max: f32, subdiv_max: usize) -> ETGraph<'a> {
+/// Number of vertex links computed at once
+const LINK_CHUNK: usize = 1 << 14;
+
+/// Largest index stored in the graph
+const MAX_INDEX: usize = u32::MAX as usize;
+
+/// Boundaries of the subdivided faces, as compressed rows
+struct SubdivFaces {
+    offsets: Vec<usize>,
+    vertices: Vec<u32>,
+}
+
+impl SubdivFaces {
+    fn boundary(&self, face: usize) -> &[u32] {
+        &self.vertices[self.offsets[face]..self.offsets[face + 1]]
+    }
+}
+
+impl ETGraph {
+    /// Graph of a skeleton, keeping the subdivided faces with `keep_faces`
+    pub fn new(
+        skel: &Skeleton,
+        dist_max: f32,
+        subdiv_max: usize,
+        keep_faces: bool,
+    ) -> Result<ETGraph> {
+        log::info!("build_topology");
+        let topo = Topology::new(skel)?;
+
         let mut etgraph = ETGraph {
-            skel,
-            vert: Vec::new(),
+            pos: Vec::new(),
+            rad: Vec::new(),
+            time: Vec::new(),
+            prime_sector: Vec::new(),
+            burned: BitSet::new(),
+            boundary: BitSet::new(),
+            neigh_offsets: vec![0],
+            neigh: Vec::new(),
+            slot_sector_offsets: vec![0],
+            slot_sectors: Vec::new(),
+            sector_offsets: vec![0],
+            sectors: Sectors::new(),
+            faces: None,
         };
 
         log::info!("build_subdiv_vertices");
-        let subdiv_inds = etgraph.build_subdiv_vertices(dist_max, subdiv_max);
+        let subdiv_inds = etgraph.build_subdiv_vertices(skel, &topo, dist_max, subdiv_max);
 
-        log::info!("build_subdiv_faces");
-        etgraph.build_subdiv_faces(subdiv_inds);
+        log::info!("build_subdiv_topology");
+        let subdiv_topo = topo.subdivide(etgraph.pos.len(), &subdiv_inds);
+        drop(subdiv_inds);
+        drop(topo);
+        // face positions are below the number of half-edges, local neighbor and sector
+        // indices below the number of vertices
+        if etgraph.pos.len() > MAX_INDEX || subdiv_topo.nb_half_edges() > MAX_INDEX {
+            return Err(anyhow::Error::msg(format!(
+                "Subdivided graph too large, {} vertices and {} face vertices (at most {})",
+                etgraph.pos.len(),
+                subdiv_topo.nb_half_edges(),
+                MAX_INDEX
+            )));
+        }
 
         log::info!("build_sectors");
-        etgraph.build_sectors();
+        etgraph.build_sectors(&subdiv_topo);
+        if keep_faces {
+            etgraph.faces = Some(Self::build_subdiv_faces(&subdiv_topo));
+        }
 
-        etgraph
-    }
+        log::info!("ETGraph memory: {} bytes", etgraph.heap_size());
 
-    pub(super) fn get_vertices(&mut self) -> &mut Vec<Vertex> {
-        &mut self.vert
+        Ok(etgraph)
     }
 
-    fn build_subdiv_vertices(&mut self, dist_max: f32, subdiv_max: usize) -> Vec<Vec<usize>> {
+    fn build_subdiv_vertices(
+        &mut self,
+        skel: &Skeleton,
+        topo: &Topology,
+        dist_max: f32,
+        subdiv_max: usize,
+    ) -> Vec<Vec<usize>> {
         // include original vertices and subdivision vertices in graph
         // return subdivided edges
 
-        for i in 0..self.skel.get_vertices().len() {
-            self.vert.push(Vertex::new(
-                self.skel.get_vertices()[i],
-                self.skel.get_radii()[i],
-            ));
-        }
+        let vertices = skel.get_vertices();
+        let radii = skel.get_radii();
+        self.pos.extend_from_slice(vertices);
+        self.rad.extend_from_slice(radii);
 
-        // subdivisions
-        // subdiv_inds -> to each edge, associates a set of points which subdivises it
-        // (including extremities)
-        let mut subdivs_ind = Vec::new();
-        for i in 0..self.skel.get_edges().len() {
-            let [ind_v1, ind_v2] = self.skel.get_edges()[i];
+        // inner subdivision points of each edge, computed independently
+        let inner_points = parallel::map_range(0..topo.nb_edges(), |i| {
+            let [ind_v1, ind_v2] = topo.edge(i);
 
-            let v1 = self.vert[ind_v1].pos();
-            let v2 = self.vert[ind_v2].pos();
+            let v1 = vertices[ind_v1];
+            let v2 = vertices[ind_v2];
             let len_edg = (v1 - v2).norm();
 
-            let r1 = self.vert[ind_v1].rad();
-            let r2 = self.vert[ind_v2].rad();
-
-            let mut vec_sub = Vec::new();
+            let r1 = radii[ind_v1];
+            let r2 = radii[ind_v2];
 
             let nb_subdiv = (len_edg / dist_max).floor() as usize;
             let nb_subdiv = if nb_subdiv > subdiv_max {
@@ -65,88 +149,401 @@ impl<'a> ETGraph<'a> {
             } else {
                 nb_subdiv
             };
-            vec_sub.push(ind_v1);
+            let mut points = Vec::new();
             for j in 1..nb_subdiv {
                 let prop = (j as f32) / (nb_subdiv as f32);
                 let cur_pos = (1.0 - prop) * v1 + prop * v2;
                 let cur_rad = (1.0 - prop) * r1 + prop * r2;
-                let ind = self.vert.len();
-                self.vert.push(Vertex::new(cur_pos, cur_rad));
-                vec_sub.push(ind);
+                points.push((cur_pos, cur_rad));
+            }
+            points
+        });
+
+        // subdivisions
+        // subdiv_inds -> to each edge, associates a set of points which subdivises it
+        // (including extremities)
+        let mut subdivs_ind = Vec::new();
+        for i in 0..topo.nb_edges() {
+            let [ind_v1, ind_v2] = topo.edge(i);
+            let mut vec_sub = vec![ind_v1];
+            for &(cur_pos, cur_rad) in inner_points[i].iter() {
+                vec_sub.push(self.pos.len());
+                self.pos.push(cur_pos);
+                self.rad.push(cur_rad);
             }
             vec_sub.push(ind_v2);
             subdivs_ind.push(vec_sub);
         }
 
+        let nb_vertices = self.pos.len();
+        self.time = vec![BurnTime::Infinity; nb_vertices];
+        self.prime_sector = vec![None; nb_vertices];
+        self.burned = BitSet::with_len(nb_vertices);
+        self.boundary = BitSet::with_len(nb_vertices);
+
         subdivs_ind
     }
 
-    fn build_subdiv_faces(&mut self, subdivs_ind: Vec<Vec<usize>>) -> () {
-        let nb_faces = self.skel.get_faces().len();
-
-        for i in 0..nb_faces {
-            let ind_edges = &self.skel.get_faces()[i];
-            let mut subdivs = vec![subdivs_ind[ind_edges[0]].clone()];
-            for i in 1..ind_edges.len() {
-                let ind_edge = ind_edges[i];
-                let subdiv_cur = subdivs_ind[ind_edge].clone();
-                if *subdiv_cur.first().unwrap() == *subdivs[i - 1].last().unwrap() {
-                    subdivs.push(subdiv_cur.clone());
-                } else if *subdiv_cur.last().unwrap() == *subdivs[i - 1].last().unwrap() {
-                    subdivs.push(subdiv_cur.iter().map(|&x| x).rev().collect());
-                } else if *subdiv_cur.last().unwrap() == *subdivs[0].first().unwrap() {
-                    subdivs.insert(0, subdiv_cur.clone());
-                } else {
-                    subdivs.insert(0, subdiv_cur.iter().map(|&x| x).rev().collect());
-                }
+    /// Boundaries of all subdivided faces, as compressed rows
+    fn build_subdiv_faces(subdiv_topo: &Topology) -> SubdivFaces {
+        let mut faces = SubdivFaces {
+            offsets: vec![0],
+            vertices: Vec::new(),
+        };
+        for face in 0..subdiv_topo.nb_faces() {
+            faces.vertices.extend(
+                subdiv_topo
+                    .face_half_edges(face)
+                    .into_iter()
+                    .map(|ind_he| subdiv_topo.half_edge(ind_he).origin as u32),
+            );
+            faces.offsets.push(faces.vertices.len());
+        }
+        faces
+    }
+
+    fn push_vertex_link(&mut self, ind_vert: usize, link: VertexLink) {
+        self.boundary.set(ind_vert, link.is_boundary());
+        for num_neigh in 0..link.neigh().len() {
+            self.neigh.push(link.neigh()[num_neigh] as u32);
+            self.slot_sectors.extend(
+                link.attached_sectors(num_neigh)
+                    .into_iter()
+                    .map(|s| s as u32),
+            );
+            self.slot_sector_offsets.push(self.slot_sectors.len());
+        }
+        self.neigh_offsets.push(self.neigh.len());
+        for sector in link.sectors() {
+            self.sectors
+                .push(&sector.arc, &sector.beg_sec, &sector.end_sec);
+        }
+        self.sector_offsets.push(self.sectors.len());
+    }
+
+    /// Sectors of all vertices, from their link in the subdivided faces
+    fn build_sectors(&mut self, subdiv_topo: &Topology) {
+        let nb_vertices = self.pos.len();
+
+        // links are computed independently, by chunks to bound memory
+        let mut chunk_start = 0;
+        while chunk_start < nb_vertices {
+            let chunk_end = (chunk_start + LINK_CHUNK).min(nb_vertices);
+            let links = parallel::map_range(chunk_start..chunk_end, |i| {
+                VertexLink::new(&subdiv_topo.link_graph(i))
+            });
+            for (i, link) in (chunk_start..chunk_end).zip(links) {
+                self.push_vertex_link(i, link);
             }
+            chunk_start = chunk_end;
+        }
+    }
 
-            for i in 0..subdivs.len() {
-                // extremity of i-th subdivision
-                let ind_vert = subdivs[i][0];
-                let mut prev_ind = subdivs[(i + 1) % subdivs.len()][0];
-                // all chains in between
-                for j in 1..(subdivs.len() - 1) {
-                    let j_cur = (i + j) % subdivs.len();
-                    for k in 0..subdivs[j_cur].len() {
-                        let cur_ind = subdivs[j_cur][k];
-                        if prev_ind != cur_ind {
-                            self.vert[ind_vert].add_couple_neigh(prev_ind, cur_ind);
-                        }
-                        prev_ind = cur_ind;
-                    }
-                }
+    /// Memory used by the graph, in bytes
+    pub fn heap_size(&self) -> usize {
+        self.pos.capacity() * size_of::<Vector3<f32>>()
+            + self.rad.capacity() * size_of::<f32>()
+            + self.time.capacity() * size_of::<BurnTime>()
+            + self.prime_sector.capacity() * size_of::<Option<u32>>()
+            + self.burned.heap_size()
+            + self.boundary.heap_size()
+            + (self.neigh_offsets.capacity()
+                + self.slot_sector_offsets.capacity()
+                + self.sector_offsets.capacity())
+                * size_of::<usize>()
+            + (self.neigh.capacity() + self.slot_sectors.capacity()) * size_of::<u32>()
+            + self.sectors.heap_size()
+            + self.faces.as_ref().map_or(0, |faces| {
+                faces.offsets.capacity() * size_of::<usize>()
+                    + faces.vertices.capacity() * size_of::<u32>()
+            })
+    }
+
+    pub fn nb_vertices(&self) -> usize {
+        self.pos.len()
+    }
+
+    pub fn pos(&self, ind_vert: usize) -> Vector3<f32> {
+        self.pos[ind_vert]
+    }
+
+    pub fn rad(&self, ind_vert: usize) -> f32 {
+        self.rad[ind_vert]
+    }
 
-                // inner node of i-th subdivision
-                for ii in 1..(subdivs[i].len() - 1) {
-                    let ind_vert = subdivs[i][ii];
-                    let mut prev_ind = subdivs[i][ii + 1];
-                    // all chains in between
-                    for j in 1..subdivs.len() {
-                        let j_cur = (i + j) % subdivs.len();
-                        let range = if j == 1 {
-                            1..subdivs[j_cur].len()
-                        } else if j == subdivs.len() - 1 {
-                            0..(subdivs[j_cur].len() - 1)
-                        } else {
-                            0..subdivs[j_cur].len()
-                        };
-                        for k in range {
-                            let cur_ind = subdivs[j_cur][k];
-                            if prev_ind != cur_ind {
-                                self.vert[ind_vert].add_couple_neigh(prev_ind, cur_ind);
-                            }
-                            prev_ind = cur_ind;
-                        }
+    pub fn time(&self, ind_vert: usize) -> &BurnTime {
+        &self.time[ind_vert]
+    }
+
+    pub fn set_time(&mut self, ind_vert: usize, time: f32) {
+        self.time[ind_vert] = BurnTime::Time(time);
+    }
+
+    pub fn reset_time(&mut self, ind_vert: usize) {
+        self.time[ind_vert] = BurnTime::Infinity;
+    }
+
+    pub fn erosion_thickness(&self, ind_vert: usize) -> ErosionThickness {
+        match self.time[ind_vert] {
+            BurnTime::Infinity => ErosionThickness::Infinity,
+            BurnTime::Time(t) => ErosionThickness::ET(t - self.rad[ind_vert]),
+        }
+    }
+
+    pub fn is_burned(&self, ind_vert: usize) -> bool {
+        self.burned.get(ind_vert)
+    }
+
+    pub fn burn(&mut self, ind_vert: usize) {
+        self.burned.set(ind_vert, true)
+    }
+
+    pub fn is_boundary(&self, ind_vert: usize) -> bool {
+        self.boundary.get(ind_vert)
+    }
+
+    pub fn prime_sector(&self, ind_vert: usize) -> Option<usize> {
+        self.prime_sector[ind_vert].map(|s| s as usize)
+    }
+
+    pub fn set_prime_sector(&mut self, ind_vert: usize, sec: usize) {
+        self.prime_sector[ind_vert] = Some(sec as u32);
+    }
+
+    pub fn reset_prime_sector(&mut self, ind_vert: usize) {
+        self.prime_sector[ind_vert] = None;
+    }
+
+    pub fn prime_neighbor(&self, ind_vert: usize) -> Option<usize> {
+        let sec = self.sector_offsets[ind_vert] + self.prime_sector(ind_vert)?;
+        let arc = self.sectors.prime_arc(sec)?;
+        let num_neigh = self.sectors.arc(sec)[arc] as usize;
+        Some(self.neigh(ind_vert)[num_neigh] as usize)
+    }
+
+    /// Boundaries of the subdivided faces, as cyclic sequences of vertices, None if they
+    /// were not kept
+    pub fn faces(&self) -> Option<Vec<&[u32]>> {
+        let faces = self.faces.as_ref()?;
+        Some(
+            (0..faces.offsets.len() - 1)
+                .map(|f| faces.boundary(f))
+                .collect(),
+        )
+    }
+
+    pub fn neigh(&self, ind_vert: usize) -> &[u32] {
+        &self.neigh[self.neigh_offsets[ind_vert]..self.neigh_offsets[ind_vert + 1]]
+    }
+
+    pub fn get_num_neigh(&self, ind_vert: usize, ind_neigh: usize) -> Option<usize> {
+        self.neigh(ind_vert)
+            .iter()
+            .position(|&n| n as usize == ind_neigh)
+    }
+
+    /// Sectors containing a neighbor slot, sorted
+    pub fn attached_sectors(&self, ind_vert: usize, num_neigh: usize) -> &[u32] {
+        let slot = self.neigh_offsets[ind_vert] + num_neigh;
+        &self.slot_sectors[self.slot_sector_offsets[slot]..self.slot_sector_offsets[slot + 1]]
+    }
+
+    fn nb_sectors(&self, ind_vert: usize) -> usize {
+        self.sector_offsets[ind_vert + 1] - self.sector_offsets[ind_vert]
+    }
+
+    pub fn sector_is_burned(&self, ind_vert: usize, ind_sec: usize) -> bool {
+        self.sectors
+            .is_burned(self.sector_offsets[ind_vert] + ind_sec)
+    }
+
+    pub fn sector_time(&self, ind_vert: usize, ind_sec: usize) -> &BurnTime {
+        self.sectors.time(self.sector_offsets[ind_vert] + ind_sec)
+    }
+
+    pub fn set_sector_time(&mut self, ind_vert: usize, ind_sec: usize, time: f32) {
+        self.sectors
+            .set_time(self.sector_offsets[ind_vert] + ind_sec, time)
+    }
+
+    pub fn sector_get_arc(
+        &self,
+        ind_vert: usize,
+        ind_sec: usize,
+        num_neigh: usize,
+    ) -> Option<usize> {
+        self.sectors
+            .get_arc(self.sector_offsets[ind_vert] + ind_sec, num_neigh)
+    }
+
+    pub fn set_sector_prime_arc(&mut self, ind_vert: usize, ind_sec: usize, prime_arc: usize) {
+        self.sectors
+            .set_prime_arc(self.sector_offsets[ind_vert] + ind_sec, prime_arc)
+    }
+
+    pub fn burn_sector(&mut self, ind_vert: usize, ind_sec: usize) {
+        let first = self.sector_offsets[ind_vert];
+        self.sectors.burn(first + ind_sec);
+        let mut exposed = vec![ind_sec];
+        while let Some(ind_exp) = exposed.pop() {
+            for j in 0..self.nb_sectors(ind_vert) {
+                if !self.sectors.is_exposed(first + j) {
+                    self.sectors.expose_neigh(first + j, ind_exp);
+                    if self.sectors.is_exposed(first + j) {
+                        exposed.push(j);
                     }
                 }
             }
         }
     }
 
-    fn build_sectors(&mut self) -> () {
-        for i in 0..self.vert.len() {
-            self.vert[i].compute_sectors();
+    pub fn get_exposed_sectors(&self, ind_vert: usize) -> Vec<usize> {
+        let first = self.sector_offsets[ind_vert];
+        (0..self.nb_sectors(ind_vert))
+            .filter(|&j| self.sectors.is_exposed(first + j) && !self.sectors.is_burned(first + j))
+            .collect()
+    }
+
+    pub fn get_unburned_sectors(&self, ind_vert: usize) -> Vec<usize> {
+        let first = self.sector_offsets[ind_vert];
+        (0..self.nb_sectors(ind_vert))
+            .filter(|&j| !self.sectors.is_burned(first + j))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::skeleton::io;
+
+    /// Two triangles sharing the edge (1, 2)
+    fn strip() -> Skeleton {
+        let ply = "ply\nformat ascii 1.0\nelement vertex 4\nproperty float x\nproperty float y\n\
+                   property float z\nproperty float radius\nelement face 2\n\
+                   property list uchar uint vertex_indices\nend_header\n\
+                   0 0 0 0.1\n1 0 0 0.1\n0 1 0 0.1\n1 1 0 0.1\n3 0 1 2\n3 1 3 2\n";
+        io::import_from_ply_reader(&mut ply.as_bytes()).unwrap()
+    }
+
+    #[test]
+    fn subdivided_strip() {
+        let skel = strip();
+        // one midpoint on each of the 5 edges
+        let graph = ETGraph::new(&skel, 0.1, 2, true).unwrap();
+        assert_eq!(graph.nb_vertices(), 9);
+
+        let faces = graph.faces().unwrap();
+        assert_eq!(faces.len(), 2);
+        for face in faces.iter() {
+            assert_eq!(face.len(), 6);
+            // original and subdivision vertices alternate
+            for k in 0..6 {
+                assert_eq!((face[k] < 4), k % 2 == 0);
+            }
         }
+
+        // neighbors are the other vertices of the subdivided faces around
+        assert_eq!(graph.neigh(0).len(), 5);
+        assert_eq!(graph.neigh(1).len(), 8);
+        // only the midpoint of the shared edge is inside the strip
+        let inner: Vec<u32> = faces[0]
+            .iter()
+            .copied()
+            .filter(|&v| v >= 4 && faces[1].contains(&v))
+            .collect();
+        assert_eq!(inner.len(), 1);
+        assert!(!graph.is_boundary(inner[0] as usize));
+        assert!(graph.is_boundary(0) && graph.is_boundary(3));
+        for v in 0..graph.nb_vertices() {
+            for (num_neigh, &u) in graph.neigh(v).iter().enumerate() {
+                assert_eq!(graph.get_num_neigh(v, u as usize), Some(num_neigh));
+                assert!(graph.get_num_neigh(u as usize, v).is_some());
+                let sectors = graph.attached_sectors(v, num_neigh);
+                assert!(!sectors.is_empty());
+                assert!(sectors.iter().all(|&s| (s as usize) < graph.nb_sectors(v)));
+            }
+        }
+
+        assert!(ETGraph::new(&skel, 0.1, 2, false)
+            .unwrap()
+            .faces()
+            .is_none());
+    }
+
+    /// Triangulated n x n grid of unit squares
+    #[cfg(feature = "parallel")]
+    fn grid(n: usize) -> Skeleton {
+        let mut ply = format!(
+            "ply\nformat ascii 1.0\nelement vertex {}\nproperty float x\nproperty float y\n\
+             property float z\nproperty float radius\nelement face {}\n\
+             property list uchar uint vertex_indices\nend_header\n",
+            (n + 1) * (n + 1),
+            2 * n * n
+        );
+        for i in 0..=n {
+            for j in 0..=n {
+                ply += &format!("{} {} 0 0.1\n", i, j);
+            }
+        }
+        for i in 0..n {
+            for j in 0..n {
+                let v = i * (n + 1) + j;
+                ply += &format!("3 {} {} {}\n", v, v + n + 1, v + 1);
+                ply += &format!("3 {} {} {}\n", v + 1, v + n + 1, v + n + 2);
+            }
+        }
+        io::import_from_ply_reader(&mut ply.as_bytes()).unwrap()
+    }
+
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn serial_and_parallel_builds_match() {
+        let skel = grid(12);
+        let build = |threads: usize| {
+            rayon::ThreadPoolBuilder::new()
+                .num_threads(threads)
+                .build()
+                .unwrap()
+                .install(|| ETGraph::new(&skel, 0.3, 4, true).unwrap())
+        };
+        let serial = build(1);
+        let parallel = build(4);
+
+        assert_eq!(serial.nb_vertices(), parallel.nb_vertices());
+        assert_eq!(serial.faces(), parallel.faces());
+        for v in 0..serial.nb_vertices() {
+            assert_eq!(serial.pos(v), parallel.pos(v));
+            assert_eq!(serial.rad(v), parallel.rad(v));
+            assert_eq!(serial.is_boundary(v), parallel.is_boundary(v));
+            assert_eq!(serial.neigh(v), parallel.neigh(v));
+            assert_eq!(serial.nb_sectors(v), parallel.nb_sectors(v));
+            for num_neigh in 0..serial.neigh(v).len() {
+                assert_eq!(
+                    serial.attached_sectors(v, num_neigh),
+                    parallel.attached_sectors(v, num_neigh)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn non_manifold_fans() {
+        // closed fan of three triangles around vertex 0, and an open one
+        let ply = "ply\nformat ascii 1.0\nelement vertex 6\nproperty float x\nproperty float y\n\
+                   property float z\nproperty float radius\nelement face 4\n\
+                   property list uchar uint vertex_indices\nend_header\n\
+                   0 0 0 0.1\n1 0 0 0.1\n0 1 0 0.1\n0 0 1 0.1\n-1 0 0 0.1\n0 -1 0 0.1\n\
+                   3 0 1 2\n3 0 2 3\n3 0 3 1\n3 0 4 5\n";
+        let mut skel = io::import_from_ply_reader(&mut ply.as_bytes()).unwrap();
+        let graph = ETGraph::new(&skel, 0.1, 1, false).unwrap();
+        assert_eq!(graph.nb_vertices(), 6);
+        assert_eq!(graph.neigh(0).len(), 5);
+        assert_eq!(graph.nb_sectors(0), 2);
+        assert!(graph.is_boundary(0));
+
+        crate::et_algorithm::algorithm::erosion_thickness_computation(&mut skel, 0.1, 1).unwrap();
+        let et = skel.get_property_f32("erosion_thickness").unwrap();
+        assert!(et.iter().all(|v| v.is_finite()));
     }
 }
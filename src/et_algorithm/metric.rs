@@ -0,0 +1,148 @@
+use anyhow::Result;
+use nalgebra::base::*;
+
+/// Cost of propagating the burn front along an arc of the graph.
+///
+/// The front goes from the burned vertex (`pos_from`, `rad_from`) to its
+/// unburned neighbor (`pos_to`, `rad_to`).
+pub trait BurnMetric: Sync {
+    fn arc_cost(
+        &self,
+        pos_from: &Vector3<f32>,
+        rad_from: f32,
+        pos_to: &Vector3<f32>,
+        rad_to: f32,
+    ) -> f32;
+}
+
+/// Euclidean distance between vertex positions (original erosion thickness).
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Euclidean;
+
+impl BurnMetric for Euclidean {
+    fn arc_cost(
+        &self,
+        pos_from: &Vector3<f32>,
+        _rad_from: f32,
+        pos_to: &Vector3<f32>,
+        _rad_to: f32,
+    ) -> f32 {
+        (pos_to - pos_from).norm()
+    }
+}
+
+/// Distance between the surfaces of the two medial balls, i.e. the Hausdorff
+/// distance between the spheres: `|p1 - p2| + |r1 - r2|`.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct MedialBall;
+
+impl BurnMetric for MedialBall {
+    fn arc_cost(
+        &self,
+        pos_from: &Vector3<f32>,
+        rad_from: f32,
+        pos_to: &Vector3<f32>,
+        rad_to: f32,
+    ) -> f32 {
+        (pos_to - pos_from).norm() + (rad_to - rad_from).abs()
+    }
+}
+
+/// Distance between the surfaces of the medial balls scaled by `scale`, in the
+/// style of the scale axis transform: `|p1 - p2| + scale * |r1 - r2|`.
+///
+/// A scale of 0 gives back the euclidean metric, a scale of 1 the medial ball one.
+#[derive(Clone, Copy, Debug)]
+pub struct ScaledRadius {
+    scale: f32,
+}
+
+impl ScaledRadius {
+    /// Metric of a finite and non negative scale, arc costs being non negative
+    pub fn new(scale: f32) -> Result<ScaledRadius> {
+        if !scale.is_finite() || scale < 0.0 {
+            return Err(anyhow::Error::msg(format!(
+                "Invalid radius scale {}, finite and non negative expected",
+                scale
+            )));
+        }
+        Ok(ScaledRadius { scale })
+    }
+
+    pub fn scale(&self) -> f32 {
+        self.scale
+    }
+}
+
+impl BurnMetric for ScaledRadius {
+    fn arc_cost(
+        &self,
+        pos_from: &Vector3<f32>,
+        rad_from: f32,
+        pos_to: &Vector3<f32>,
+        rad_to: f32,
+    ) -> f32 {
+        (pos_to - pos_from).norm() + self.scale * (rad_to - rad_from).abs()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const POINTS: [([f32; 3], f32); 4] = [
+        ([0.0, 0.0, 0.0], 1.0),
+        ([3.0, 4.0, 0.0], 3.0),
+        ([0.1, -0.7, 2.3], 0.25),
+        ([1e-3, 2e-3, -5e-4], 0.5),
+    ];
+
+    fn for_each_arc(mut f: impl FnMut(&Vector3<f32>, f32, &Vector3<f32>, f32)) {
+        for (p1, r1) in POINTS {
+            for (p2, r2) in POINTS {
+                f(&Vector3::from(p1), r1, &Vector3::from(p2), r2);
+            }
+        }
+    }
+
+    #[test]
+    fn euclidean_is_the_original_cost() {
+        for_each_arc(|p1, r1, p2, r2| {
+            // cost computed by the burning loop before metrics
+            let arc_norm = (p1 - p2).norm();
+            assert_eq!(Euclidean.arc_cost(p1, r1, p2, r2), arc_norm);
+        });
+    }
+
+    #[test]
+    fn medial_ball_and_scaled_radius() {
+        let p1 = Vector3::new(0.0, 0.0, 0.0);
+        let p2 = Vector3::new(3.0, 4.0, 0.0);
+        assert_eq!(MedialBall.arc_cost(&p1, 1.0, &p2, 3.0), 7.0);
+        assert_eq!(MedialBall.arc_cost(&p2, 3.0, &p1, 1.0), 7.0);
+        let half = ScaledRadius::new(0.5).unwrap();
+        assert_eq!(half.scale(), 0.5);
+        assert_eq!(half.arc_cost(&p1, 1.0, &p2, 3.0), 6.0);
+
+        let zero = ScaledRadius::new(0.0).unwrap();
+        let one = ScaledRadius::new(1.0).unwrap();
+        for_each_arc(|p1, r1, p2, r2| {
+            assert_eq!(
+                zero.arc_cost(p1, r1, p2, r2),
+                Euclidean.arc_cost(p1, r1, p2, r2)
+            );
+            assert_eq!(
+                one.arc_cost(p1, r1, p2, r2),
+                MedialBall.arc_cost(p1, r1, p2, r2)
+            );
+            assert!(half.arc_cost(p1, r1, p2, r2) >= 0.0);
+        });
+    }
+
+    #[test]
+    fn invalid_radius_scales() {
+        for scale in [-1.0, -f32::MIN_POSITIVE, f32::NAN, f32::INFINITY] {
+            assert!(ScaledRadius::new(scale).is_err(), "{}", scale);
+        }
+    }
+}
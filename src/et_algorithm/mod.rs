@@ -1,5 +1,9 @@
 pub mod algorithm;
+mod bitset;
 mod burntime;
 mod graph;
+pub mod metric;
+pub mod parallel;
+pub mod report;
 mod sector;
 mod vertex;
@@ -0,0 +1,63 @@
+use anyhow::Result;
+use std::ops::Range;
+
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+
+/// Sets the number of threads used to build the ET graph, 0 for one per core
+///
+/// Must be called before any computation. The global pool can only be set up
+/// once: later calls, or calls after rayon was used, keep the existing pool
+/// with a warning. Without the `parallel` feature, computations are always
+/// serial.
+pub fn init_thread_pool(threads: usize) -> Result<()> {
+    #[cfg(feature = "parallel")]
+    {
+        if let Err(err) = rayon::ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .build_global()
+        {
+            log::warn!(
+                "{}, keeping its {} threads",
+                err,
+                rayon::current_num_threads()
+            );
+        }
+    }
+    #[cfg(not(feature = "parallel"))]
+    {
+        if threads > 1 {
+            log::warn!("built without the parallel feature, using one thread");
+        }
+    }
+    Ok(())
+}
+
+/// Maps `f` on `range`, in parallel if enabled, results in range order
+pub fn map_range<U, F>(range: Range<usize>, f: F) -> Vec<U>
+where
+    U: Send,
+    F: Fn(usize) -> U + Sync + Send,
+{
+    #[cfg(feature = "parallel")]
+    {
+        range.into_par_iter().map(f).collect()
+    }
+    #[cfg(not(feature = "parallel"))]
+    {
+        range.map(f).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn init_thread_pool_twice() {
+        init_thread_pool(2).unwrap();
+        init_thread_pool(3).unwrap();
+        let squares = map_range(0..100, |i| i * i);
+        assert_eq!(squares, (0..100).map(|i| i * i).collect::<Vec<_>>());
+    }
+}
@@ -0,0 +1,349 @@
+use anyhow::Result;
+use serde::Serialize;
+use std::io::Write;
+use std::path::Path;
+
+use super::algorithm::ETResult;
+use crate::skeleton::compression;
+use crate::skeleton::skeleton::Skeleton;
+
+/// Percentiles given in reports, besides the median
+pub const PERCENTILES: [f32; 6] = [1.0, 5.0, 25.0, 75.0, 95.0, 99.0];
+
+/// Number of histogram bins in reports
+pub const HISTOGRAM_BINS: usize = 20;
+
+/// Equal width bins between the min and max of values
+#[derive(Serialize, Clone, Debug)]
+pub struct Histogram {
+    /// bin bounds, one more than the bins
+    pub edges: Vec<f32>,
+    pub counts: Vec<usize>,
+}
+
+/// Value below which a given percentage (0 to 100) of values lie
+#[derive(Serialize, Clone, Debug)]
+pub struct Percentile {
+    pub percentile: f32,
+    pub value: f32,
+}
+
+/// Summary of the finite values of a property
+#[derive(Serialize, Clone, Debug)]
+pub struct ValueStats {
+    pub count: usize,
+    pub min: f32,
+    pub max: f32,
+    pub mean: f32,
+    pub median: f32,
+    /// nearest rank percentiles, see `PERCENTILES`
+    pub percentiles: Vec<Percentile>,
+    pub histogram: Histogram,
+}
+
+impl ValueStats {
+    /// Statistics of the finite values, None if there is none
+    pub fn new(values: &[f32], nb_bins: usize) -> Option<ValueStats> {
+        let mut sorted: Vec<f32> = values.iter().copied().filter(|v| v.is_finite()).collect();
+        if sorted.is_empty() {
+            return None;
+        }
+        sorted.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        let at = |p: f32| sorted[(p / 100.0 * (sorted.len() - 1) as f32).round() as usize];
+        let min = sorted[0];
+        let max = sorted[sorted.len() - 1];
+        let mean = (sorted.iter().map(|&v| v as f64).sum::<f64>() / sorted.len() as f64) as f32;
+
+        let nb_bins = nb_bins.max(1);
+        let width = (max - min) / nb_bins as f32;
+        let edges = (0..=nb_bins).map(|i| min + width * i as f32).collect();
+        let mut counts = vec![0; nb_bins];
+        for &v in sorted.iter() {
+            let bin = if width > 0.0 {
+                (((v - min) / width) as usize).min(nb_bins - 1)
+            } else {
+                0
+            };
+            counts[bin] += 1;
+        }
+
+        Some(ValueStats {
+            count: sorted.len(),
+            min,
+            max,
+            mean,
+            median: at(50.0),
+            percentiles: PERCENTILES
+                .iter()
+                .map(|&percentile| Percentile {
+                    percentile,
+                    value: at(percentile),
+                })
+                .collect(),
+            histogram: Histogram { edges, counts },
+        })
+    }
+}
+
+/// Machine-readable summary of an erosion thickness computation
+#[derive(Serialize, Clone, Debug)]
+pub struct Report {
+    pub nb_vertices: usize,
+    pub nb_edges: usize,
+    pub nb_faces: usize,
+    /// number of skeleton vertices on the boundary
+    pub nb_boundary: usize,
+    /// number of vertices never reached by the burn front
+    pub nb_infinite: usize,
+    /// statistics of finite values, None if there is none
+    pub erosion_thickness: Option<ValueStats>,
+    pub burntime: Option<ValueStats>,
+    pub radius: Option<ValueStats>,
+    pub timings: Vec<Timing>,
+}
+
+/// Wall-clock time of a phase, in seconds
+#[derive(Serialize, Clone, Debug)]
+pub struct Timing {
+    pub phase: String,
+    pub seconds: f32,
+}
+
+fn finite_values(values: &[Option<f32>]) -> Vec<f32> {
+    values.iter().flatten().copied().collect()
+}
+
+/// Flat columns of the statistics of a property, empty values if there are none
+fn stats_columns(name: &str, stats: Option<&ValueStats>) -> Vec<(String, String)> {
+    let mut columns = Vec::new();
+    let mut push = |suffix: String, value: Option<String>| {
+        columns.push((format!("{}_{}", name, suffix), value.unwrap_or_default()));
+    };
+    push("count".to_string(), stats.map(|s| s.count.to_string()));
+    push("min".to_string(), stats.map(|s| s.min.to_string()));
+    push("max".to_string(), stats.map(|s| s.max.to_string()));
+    push("mean".to_string(), stats.map(|s| s.mean.to_string()));
+    push("median".to_string(), stats.map(|s| s.median.to_string()));
+    for i in 0..PERCENTILES.len() {
+        push(
+            format!("p{}", PERCENTILES[i]),
+            stats.map(|s| s.percentiles[i].value.to_string()),
+        );
+    }
+    for i in 0..=HISTOGRAM_BINS {
+        push(
+            format!("edge_{}", i),
+            stats.map(|s| s.histogram.edges[i].to_string()),
+        );
+    }
+    for i in 0..HISTOGRAM_BINS {
+        push(
+            format!("hist_{}", i),
+            stats.map(|s| s.histogram.counts[i].to_string()),
+        );
+    }
+    columns
+}
+
+impl Report {
+    /// Report of a computation on `skeleton`, with the given phase timings
+    pub fn new(skeleton: &Skeleton, result: &ETResult, timings: &[(&str, f32)]) -> Report {
+        Report {
+            nb_vertices: skeleton.get_vertices().len(),
+            nb_edges: skeleton.get_edges().len(),
+            nb_faces: skeleton.get_face_vertices().len(),
+            nb_boundary: result.nb_boundary,
+            nb_infinite: result
+                .erosion_thickness
+                .iter()
+                .filter(|et| et.is_none())
+                .count(),
+            erosion_thickness: ValueStats::new(
+                &finite_values(&result.erosion_thickness),
+                HISTOGRAM_BINS,
+            ),
+            burntime: ValueStats::new(&finite_values(&result.burntime), HISTOGRAM_BINS),
+            radius: ValueStats::new(skeleton.get_radii(), HISTOGRAM_BINS),
+            timings: timings
+                .iter()
+                .map(|&(phase, seconds)| Timing {
+                    phase: phase.to_string(),
+                    seconds,
+                })
+                .collect(),
+        }
+    }
+
+    /// Flat columns: counts, then statistics of each property (`erosion_thickness_p95`,
+    /// `radius_hist_3`, ...), then timings (`time_burn`)
+    ///
+    /// Missing statistics are empty values.
+    pub fn columns(&self) -> Vec<(String, String)> {
+        let mut columns = vec![
+            ("nb_vertices".to_string(), self.nb_vertices.to_string()),
+            ("nb_edges".to_string(), self.nb_edges.to_string()),
+            ("nb_faces".to_string(), self.nb_faces.to_string()),
+            ("nb_boundary".to_string(), self.nb_boundary.to_string()),
+            ("nb_infinite".to_string(), self.nb_infinite.to_string()),
+        ];
+        for (name, stats) in [
+            ("erosion_thickness", &self.erosion_thickness),
+            ("burntime", &self.burntime),
+            ("radius", &self.radius),
+        ] {
+            columns.extend(stats_columns(name, stats.as_ref()));
+        }
+        for timing in self.timings.iter() {
+            columns.push((format!("time_{}", timing.phase), timing.seconds.to_string()));
+        }
+        columns
+    }
+
+    /// Writes the report as csv (a header line and a value line) if the file name ends
+    /// with `.csv`, as json otherwise
+    pub fn save(&self, file_path: &str) -> Result<()> {
+        let path = Path::new(file_path);
+        let mut file = compression::create_writer(path)?;
+        if compression::format_extension(path) == Some("csv") {
+            let columns = self.columns();
+            let names: Vec<&str> = columns.iter().map(|(name, _)| name.as_str()).collect();
+            let values: Vec<&str> = columns.iter().map(|(_, value)| value.as_str()).collect();
+            writeln!(file, "{}", names.join(","))?;
+            writeln!(file, "{}", values.join(","))?;
+        } else {
+            serde_json::to_writer_pretty(&mut file, self)?;
+            writeln!(file)?;
+        }
+        file.finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn percentiles_of_finite_values() {
+        let mut values: Vec<f32> = (0..=100).rev().map(|v| v as f32).collect();
+        values.extend([f32::NAN, f32::INFINITY, f32::NEG_INFINITY]);
+        let stats = ValueStats::new(&values, HISTOGRAM_BINS).unwrap();
+        assert_eq!(stats.count, 101);
+        assert_eq!((stats.min, stats.max), (0.0, 100.0));
+        assert_eq!(stats.mean, 50.0);
+        assert_eq!(stats.median, 50.0);
+        for percentile in stats.percentiles.iter() {
+            assert_eq!(percentile.value, percentile.percentile);
+        }
+
+        // nearest rank
+        let stats = ValueStats::new(&[4.0, 1.0, 3.0, 2.0], 1).unwrap();
+        assert_eq!(stats.median, 3.0);
+        assert_eq!(stats.percentiles[0].value, 1.0);
+        assert_eq!(stats.percentiles[5].value, 4.0);
+
+        assert!(ValueStats::new(&[], 4).is_none());
+        assert!(ValueStats::new(&[f32::NAN, f32::INFINITY], 4).is_none());
+    }
+
+    #[test]
+    fn histogram_bins() {
+        let stats = ValueStats::new(&[0.0, 1.0, 2.0, 3.0, 4.0, 0.5], 4).unwrap();
+        assert_eq!(stats.histogram.edges, [0.0, 1.0, 2.0, 3.0, 4.0]);
+        // the max falls in the last bin
+        assert_eq!(stats.histogram.counts, [2, 1, 1, 2]);
+
+        // zero width, every value in the first bin
+        let stats = ValueStats::new(&[2.0, 2.0, 2.0], 3).unwrap();
+        assert_eq!(stats.histogram.edges, [2.0; 4]);
+        assert_eq!(stats.histogram.counts, [3, 0, 0]);
+
+        // at least one bin
+        let stats = ValueStats::new(&[1.0, 3.0], 0).unwrap();
+        assert_eq!(stats.histogram.edges, [1.0, 3.0]);
+        assert_eq!(stats.histogram.counts, [2]);
+    }
+
+    #[test]
+    fn csv_columns() {
+        let stats = ValueStats::new(&[1.0, 2.0], HISTOGRAM_BINS);
+        let report = Report {
+            nb_vertices: 3,
+            nb_edges: 3,
+            nb_faces: 1,
+            nb_boundary: 3,
+            nb_infinite: 1,
+            erosion_thickness: stats.clone(),
+            burntime: None,
+            radius: stats,
+            timings: vec![Timing {
+                phase: "burn".to_string(),
+                seconds: 0.5,
+            }],
+        };
+        let columns = report.columns();
+        let per_property = 5 + PERCENTILES.len() + 2 * HISTOGRAM_BINS + 1;
+        assert_eq!(columns.len(), 5 + 3 * per_property + 1);
+
+        let names: Vec<&str> = columns.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(
+            names[..5],
+            [
+                "nb_vertices",
+                "nb_edges",
+                "nb_faces",
+                "nb_boundary",
+                "nb_infinite"
+            ]
+        );
+        assert_eq!(
+            names[5..16],
+            [
+                "erosion_thickness_count",
+                "erosion_thickness_min",
+                "erosion_thickness_max",
+                "erosion_thickness_mean",
+                "erosion_thickness_median",
+                "erosion_thickness_p1",
+                "erosion_thickness_p5",
+                "erosion_thickness_p25",
+                "erosion_thickness_p75",
+                "erosion_thickness_p95",
+                "erosion_thickness_p99",
+            ]
+        );
+        assert_eq!(names[16], "erosion_thickness_edge_0");
+        assert_eq!(names[16 + HISTOGRAM_BINS + 1], "erosion_thickness_hist_0");
+        assert_eq!(names[5 + per_property], "burntime_count");
+        assert_eq!(names[5 + 2 * per_property], "radius_count");
+        assert_eq!(names[names.len() - 1], "time_burn");
+
+        let value = |name: &str| {
+            columns
+                .iter()
+                .find(|(column, _)| column == name)
+                .unwrap()
+                .1
+                .as_str()
+        };
+        assert_eq!(value("nb_infinite"), "1");
+        assert_eq!(value("erosion_thickness_max"), "2");
+        assert_eq!(value("erosion_thickness_edge_20"), "2");
+        assert_eq!(value("erosion_thickness_hist_19"), "1");
+        // missing statistics are empty values
+        assert_eq!(value("burntime_count"), "");
+        assert_eq!(value("burntime_hist_0"), "");
+        assert_eq!(value("time_burn"), "0.5");
+
+        let dir = std::env::temp_dir().join(format!("report_csv_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let file_path = dir.join("report.csv");
+        report.save(file_path.to_str().unwrap()).unwrap();
+        let text = std::fs::read_to_string(&file_path).unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0], names.join(","));
+        assert_eq!(lines[1].split(',').count(), columns.len());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,78 +1,163 @@
-use std::collections::HashSet;
+use std::mem::size_of;
 
+use super::bitset::BitSet;
 use super::burntime::BurnTime;
 
-#[derive(Clone)]
-pub struct Sector {
-    arc: Vec<usize>, // contains references to Vertex.neigh
-    burned: bool,
-    prime_arc: Option<usize>,
-    time: BurnTime,
-    sec_neigh_unexposed: [HashSet<usize>; 2],
+/// Sectors of all vertices, in flat storage
+///
+/// Sectors are referenced with a global index. Arcs contain references to
+/// neighbor slots of the vertex, sector extremities contain references to
+/// sectors of the same vertex (local indices). Local indices are stored as u32,
+/// `ETGraph` bounding them by its number of vertices.
+pub struct Sectors {
+    arc_offsets: Vec<usize>,
+    arc: Vec<u32>,
+    // two extremities per sector, each one is the set of neighboring sectors
+    // that have to be exposed before exposing this one
+    end_offsets: Vec<usize>,
+    end_neigh: Vec<u32>,
+    end_neigh_exposed: BitSet,
+    end_unexposed: Vec<u32>,
+
+    burned: BitSet,
+    prime_arc: Vec<Option<u32>>,
+    time: Vec<BurnTime>,
 }
 
-impl Sector {
-    pub fn new(arc: Vec<usize>, beg_sec: HashSet<usize>, end_sec: HashSet<usize>) -> Sector {
-        Sector {
-            arc,
-            burned: false,
-            prime_arc: None,
-            time: BurnTime::Infinity,
-            sec_neigh_unexposed: [beg_sec, end_sec],
+impl Sectors {
+    pub fn new() -> Sectors {
+        Sectors {
+            arc_offsets: vec![0],
+            arc: Vec::new(),
+            end_offsets: vec![0],
+            end_neigh: Vec::new(),
+            end_neigh_exposed: BitSet::new(),
+            end_unexposed: Vec::new(),
+            burned: BitSet::new(),
+            prime_arc: Vec::new(),
+            time: Vec::new(),
         }
     }
 
-    pub fn is_exposed(&self) -> bool {
-        self.sec_neigh_unexposed[0].len() == 0 || self.sec_neigh_unexposed[1].len() == 0
+    pub fn len(&self) -> usize {
+        self.time.len()
     }
 
-    pub fn is_burned(&self) -> bool {
-        self.burned
+    pub fn push(&mut self, arc: &[usize], beg_sec: &[usize], end_sec: &[usize]) -> usize {
+        self.arc.extend(arc.iter().map(|&a| a as u32));
+        self.arc_offsets.push(self.arc.len());
+        for sec in [beg_sec, end_sec] {
+            self.end_neigh.extend(sec.iter().map(|&s| s as u32));
+            self.end_offsets.push(self.end_neigh.len());
+            self.end_unexposed.push(sec.len() as u32);
+        }
+        self.prime_arc.push(None);
+        self.time.push(BurnTime::Infinity);
+        self.time.len() - 1
     }
 
-    pub fn time(&self) -> &BurnTime {
-        &self.time
+    pub fn is_exposed(&self, ind_sec: usize) -> bool {
+        self.end_unexposed[2 * ind_sec] == 0 || self.end_unexposed[2 * ind_sec + 1] == 0
     }
 
-    pub fn arc(&self) -> &Vec<usize> {
-        &self.arc
+    pub fn is_burned(&self, ind_sec: usize) -> bool {
+        self.burned.get(ind_sec)
     }
 
-    pub fn set_prime_arc(&mut self, prime_arc: usize) -> () {
-        self.prime_arc = Some(prime_arc)
+    pub fn time(&self, ind_sec: usize) -> &BurnTime {
+        &self.time[ind_sec]
     }
 
-    pub fn prime_arc(&self) -> &Option<usize> {
-        &self.prime_arc
+    pub fn set_time(&mut self, ind_sec: usize, time: f32) {
+        self.time[ind_sec] = BurnTime::Time(time)
     }
 
-    pub fn get_arc(&self, num_neigh: usize) -> Option<usize> {
-        let mut pos = None;
-        for i in 0..self.arc.len() {
-            if self.arc[i] == num_neigh {
-                pos = Some(i);
-                break;
+    pub fn arc(&self, ind_sec: usize) -> &[u32] {
+        &self.arc[self.arc_offsets[ind_sec]..self.arc_offsets[ind_sec + 1]]
+    }
+
+    pub fn get_arc(&self, ind_sec: usize, num_neigh: usize) -> Option<usize> {
+        self.arc(ind_sec)
+            .iter()
+            .position(|&a| a as usize == num_neigh)
+    }
+
+    pub fn set_prime_arc(&mut self, ind_sec: usize, prime_arc: usize) {
+        self.prime_arc[ind_sec] = Some(prime_arc as u32)
+    }
+
+    pub fn prime_arc(&self, ind_sec: usize) -> Option<usize> {
+        self.prime_arc[ind_sec].map(|a| a as usize)
+    }
+
+    pub fn burn(&mut self, ind_sec: usize) {
+        self.burned.set(ind_sec, true);
+        for end in 2 * ind_sec..2 * ind_sec + 2 {
+            for i in self.end_offsets[end]..self.end_offsets[end + 1] {
+                self.end_neigh_exposed.set(i, true);
             }
+            self.end_unexposed[end] = 0;
         }
-        pos
     }
 
-    pub fn set_time(&mut self, time: f32) -> () {
-        self.time = BurnTime::Time(time)
+    /// Removes a newly exposed sector (local index) from the sectors to expose
+    pub fn expose_neigh(&mut self, ind_sec: usize, ind_exp: usize) {
+        for end in 2 * ind_sec..2 * ind_sec + 2 {
+            for i in self.end_offsets[end]..self.end_offsets[end + 1] {
+                if self.end_neigh[i] as usize == ind_exp && !self.end_neigh_exposed.get(i) {
+                    self.end_neigh_exposed.set(i, true);
+                    self.end_unexposed[end] -= 1;
+                }
+            }
+        }
     }
 
-    pub fn get_sec_unexposed(&self) -> &[HashSet<usize>; 2] {
-        &self.sec_neigh_unexposed
+    pub fn heap_size(&self) -> usize {
+        self.arc_offsets.capacity() * size_of::<usize>()
+            + self.arc.capacity() * size_of::<u32>()
+            + self.end_offsets.capacity() * size_of::<usize>()
+            + self.end_neigh.capacity() * size_of::<u32>()
+            + self.end_neigh_exposed.heap_size()
+            + self.end_unexposed.capacity() * size_of::<u32>()
+            + self.burned.heap_size()
+            + self.prime_arc.capacity() * size_of::<Option<u32>>()
+            + self.time.capacity() * size_of::<BurnTime>()
     }
+}
 
-    pub fn burn(&mut self) -> () {
-        self.burned = true;
-        self.sec_neigh_unexposed[0].clear();
-        self.sec_neigh_unexposed[1].clear();
-    }
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn flat_sectors() {
+        let mut sectors = Sectors::new();
+        // sector 1 is exposed once sector 0 burns, sector 2 at once
+        assert_eq!(sectors.push(&[0, 1], &[], &[]), 0);
+        assert_eq!(sectors.push(&[1, 2, 3], &[0], &[0]), 1);
+        assert_eq!(sectors.push(&[], &[], &[1]), 2);
+        assert_eq!(sectors.len(), 3);
+
+        assert_eq!(sectors.arc(0), &[0, 1]);
+        assert_eq!(sectors.arc(1), &[1, 2, 3]);
+        assert!(sectors.arc(2).is_empty());
+        assert_eq!(sectors.get_arc(1, 3), Some(2));
+        assert_eq!(sectors.get_arc(0, 3), None);
+
+        assert!(sectors.is_exposed(0));
+        assert!(!sectors.is_exposed(1));
+        assert!(sectors.is_exposed(2));
+        sectors.expose_neigh(1, 0);
+        assert!(sectors.is_exposed(1));
+        // already exposed extremities are not counted twice
+        sectors.expose_neigh(1, 0);
+        assert!(sectors.is_exposed(1));
 
-    pub fn expose_neigh(&mut self, ind_exp: usize) -> () {
-        self.sec_neigh_unexposed[0].remove(&ind_exp);
-        self.sec_neigh_unexposed[1].remove(&ind_exp);
+        sectors.set_prime_arc(1, 2);
+        assert_eq!(sectors.prime_arc(1), Some(2));
+        assert_eq!(sectors.prime_arc(0), None);
+        sectors.burn(1);
+        assert!(sectors.is_burned(1));
+        assert!(!sectors.is_burned(0));
     }
 }
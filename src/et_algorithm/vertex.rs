@@ -1,9 +1,6 @@
 use std::collections::HashSet;
 
-use nalgebra::base::*;
-
-use super::burntime::BurnTime;
-use super::sector::Sector;
+use crate::skeleton::topology::LinkGraph;
 
 #[derive(Clone, Copy)]
 pub enum ErosionThickness {
@@ -11,119 +8,72 @@ pub enum ErosionThickness {
     ET(f32),
 }
 
-#[derive(Clone)]
-pub struct Vertex {
-    pos: Vector3<f32>,
-    rad: f32,
+/// Link of a vertex, from which its sectors are computed
+///
+/// Only used while building the `ETGraph`, which stores the result in flat arrays.
+pub struct VertexLink {
     neigh: Vec<usize>,                    // neighbor vertex indices
     neigh_adj: Vec<Vec<usize>>,           // adjacency graph
     edge_sector: Vec<Vec<Option<usize>>>, // sector associated to each edge
-
-    time: BurnTime,
-    burned: bool,
     boundary: bool,
-    prime_sector: Option<usize>,
 
-    sector: Vec<Sector>,
+    sector: Vec<LocalSector>,
 }
 
-impl Vertex {
-    pub fn new(pos: Vector3<f32>, rad: f32) -> Vertex {
-        Vertex {
-            pos,
-            rad,
-            neigh: Vec::new(),
-            neigh_adj: Vec::new(),
-            edge_sector: Vec::new(),
-            time: BurnTime::Infinity,
-            burned: false,
-            boundary: false,
-            prime_sector: None,
-            sector: Vec::new(),
-        }
-    }
-
-    pub fn pos(&self) -> Vector3<f32> {
-        self.pos
-    }
-
-    pub fn rad(&self) -> f32 {
-        self.rad
-    }
-
-    pub fn time(&self) -> &BurnTime {
-        &self.time
-    }
-
-    pub fn erosion_thickness(&self) -> ErosionThickness {
-        match self.time {
-            BurnTime::Infinity => ErosionThickness::Infinity,
-            BurnTime::Time(t) => ErosionThickness::ET(t - self.rad),
-        }
-    }
-
-    pub fn burn(&mut self) -> () {
-        self.burned = true
-    }
+/// Sector of a vertex, arc and sectors to expose at both extremities (local indices)
+pub struct LocalSector {
+    pub arc: Vec<usize>,
+    pub beg_sec: Vec<usize>,
+    pub end_sec: Vec<usize>,
+}
 
-    pub fn prime_sector(&self) -> &Option<usize> {
-        &self.prime_sector
-    }
+fn sorted(set: HashSet<usize>) -> Vec<usize> {
+    let mut vec: Vec<usize> = set.into_iter().collect();
+    vec.sort();
+    vec
+}
 
-    pub fn prime_neighbor(&self) -> Option<usize> {
-        if let Some(sec) = self.prime_sector {
-            if let &Some(arc) = self.sector[sec].prime_arc() {
-                let num_neigh = self.sector[sec].arc()[arc];
-                Some(self.neigh[num_neigh])
-            } else {
-                None
+impl VertexLink {
+    /// Sectors of a vertex from its link graph
+    ///
+    /// Link nodes without link edges (neighbors along edges of no face) are not
+    /// neighbors of the vertex.
+    pub fn new(link: &LinkGraph) -> VertexLink {
+        let mut num_neigh = vec![None; link.nodes().len()];
+        let mut neigh = Vec::new();
+        for (num_node, &node) in link.nodes().iter().enumerate() {
+            if link.degree(num_node) > 0 {
+                num_neigh[num_node] = Some(neigh.len());
+                neigh.push(node);
             }
-        } else {
-            None
         }
-    }
-
-    pub fn set_prime_sector(&mut self, sec: usize) -> () {
-        self.prime_sector = Some(sec);
-    }
-
-    pub fn reset_prime_sector(&mut self) -> () {
-        self.prime_sector = None;
-    }
-
-    pub fn set_time(&mut self, time: f32) -> () {
-        self.time = BurnTime::Time(time);
-    }
-
-    pub fn reset_time(&mut self) -> () {
-        self.time = BurnTime::Infinity;
-    }
-
-    pub fn is_burned(&self) -> bool {
-        self.burned
+        let neigh_adj = link
+            .node_adj()
+            .iter()
+            .filter(|adj| !adj.is_empty())
+            .map(|adj| adj.iter().map(|&n| num_neigh[n].unwrap()).collect())
+            .collect();
+        let mut vertex_link = VertexLink {
+            neigh,
+            neigh_adj,
+            edge_sector: Vec::new(),
+            boundary: false,
+            sector: Vec::new(),
+        };
+        vertex_link.compute_sectors();
+        vertex_link
     }
 
     pub fn is_boundary(&self) -> bool {
         self.boundary
     }
 
-    pub fn sectors(&mut self) -> &mut Vec<Sector> {
-        &mut self.sector
-    }
-
     pub fn neigh(&self) -> &Vec<usize> {
         &self.neigh
     }
 
-    pub fn get_num_neigh(&self, ind_vert: usize) -> Option<usize> {
-        let mut pos = None;
-        for i in 0..self.neigh.len() {
-            if self.neigh[i] == ind_vert {
-                pos = Some(i);
-                break;
-            }
-        }
-        pos
+    pub fn sectors(&self) -> &Vec<LocalSector> {
+        &self.sector
     }
 
     pub fn attached_sectors(&self, num_neigh: usize) -> Vec<usize> {
@@ -136,26 +86,6 @@ impl Vertex {
         sec
     }
 
-    pub fn add_couple_neigh(&mut self, ind_vert1: usize, ind_vert2: usize) -> () {
-        let ind1 = if let Some(ind) = self.neigh.iter().position(|&iv| iv == ind_vert1) {
-            ind
-        } else {
-            self.neigh.push(ind_vert1);
-            self.neigh_adj.push(Vec::new());
-            self.neigh.len() - 1
-        };
-        let ind2 = if let Some(ind) = self.neigh.iter().position(|&iv| iv == ind_vert2) {
-            ind
-        } else {
-            self.neigh.push(ind_vert2);
-            self.neigh_adj.push(Vec::new());
-            self.neigh.len() - 1
-        };
-
-        self.neigh_adj[ind1].push(ind2);
-        self.neigh_adj[ind2].push(ind1);
-    }
-
     fn follow_sector(&self, first_vert: usize, curr_sec: usize) -> (Vec<usize>, bool) {
         let mut arc = Vec::new();
         arc.push(first_vert);
@@ -163,7 +93,8 @@ impl Vertex {
         let mut last_position = first_vert;
         let mut reached_extremity = false;
         let mut closed = false;
-        while !reached_extremity {
+        // an arc cannot be longer than the link, bound the walk on ill-formed links
+        while !reached_extremity && arc.len() <= self.neigh.len() {
             reached_extremity = true;
             for j in 0..self.edge_sector[current_position].len() {
                 if self.edge_sector[current_position][j] == Some(curr_sec)
@@ -217,7 +148,7 @@ impl Vertex {
                                 }
                             }
                         }
-                        num_sector = num_sector + 1;
+                        num_sector += 1;
                     }
                 }
             }
@@ -232,10 +163,35 @@ impl Vertex {
             return (1, true);
         }
 
+        // then, remaining cycles of degree 2 edges (non manifold vertex), one sector each
+        for i in 0..self.edge_sector.len() {
+            if self.edge_sector[i].iter().any(|s| s.is_none()) {
+                let mut prev_ind = i;
+                let mut curr_ind = i;
+                loop {
+                    for k in 0..self.edge_sector[curr_ind].len() {
+                        self.edge_sector[curr_ind][k] = Some(num_sector);
+                    }
+                    let next_ind = self.neigh_adj[curr_ind]
+                        .iter()
+                        .copied()
+                        .find(|&n| n != prev_ind && self.edge_sector[n].contains(&None));
+                    match next_ind {
+                        Some(n) => {
+                            prev_ind = curr_ind;
+                            curr_ind = n;
+                        }
+                        None => break,
+                    }
+                }
+                num_sector += 1;
+            }
+        }
+
         (num_sector, false)
     }
 
-    pub fn compute_sectors(&mut self) -> () {
+    fn compute_sectors(&mut self) {
         log::debug!("Neighbors");
         let mut bound = false;
         let mut sing = false;
@@ -273,12 +229,15 @@ impl Vertex {
                     .position(|v| v.iter().filter(|&&i| i == Some(curr_sec)).count() == 1)
                 {
                     ext
+                } else if let Some(ext) = self.edge_sector.iter().position(|v| {
+                    v.len() != 2 && v.iter().filter(|&&i| i == Some(curr_sec)).count() != 0
+                }) {
+                    ext
                 } else {
+                    // closed sector
                     self.edge_sector
                         .iter()
-                        .position(|v| {
-                            v.len() != 2 && v.iter().filter(|&&i| i == Some(curr_sec)).count() != 0
-                        })
+                        .position(|v| v.contains(&Some(curr_sec)))
                         .unwrap()
                 };
                 let (arc, closed) = self.follow_sector(extremity, curr_sec);
@@ -302,70 +261,123 @@ impl Vertex {
                         }
                     })
                     .collect();
-                self.sector.push(Sector::new(arc, beg_sec, end_sec));
+                self.sector.push(LocalSector {
+                    arc,
+                    beg_sec: sorted(beg_sec),
+                    end_sec: sorted(end_sec),
+                });
             }
         } else {
             let (arc, _) = self.follow_sector(0, 0);
-            self.sector.push(Sector::new(
+            self.sector.push(LocalSector {
                 arc,
-                vec![0].into_iter().collect(),
-                vec![0].into_iter().collect(),
-            ));
+                beg_sec: vec![0],
+                end_sec: vec![0],
+            });
         }
 
         // sectors neighboring
         log::debug!("Sectors neighboring");
         for i in 0..self.sector.len() {
             log::debug!(
-                "{}: {:?} {:?}",
+                "{}: {:?} {:?} {:?}",
                 i,
-                self.sector[i].arc(),
-                self.sector[i].get_sec_unexposed()
+                self.sector[i].arc,
+                self.sector[i].beg_sec,
+                self.sector[i].end_sec
             );
         }
         log::debug!("");
     }
+}
 
-    pub fn burn_sector(&mut self, ind_sec: usize) -> () {
-        self.sector[ind_sec].burn();
-        let mut exposed = vec![ind_sec];
-        while let Some(ind_exp) = exposed.pop() {
-            for j in 0..self.sector.len() {
-                if !self.sector[j].is_exposed() {
-                    self.sector[j].expose_neigh(ind_exp);
-                    if self.sector[j].is_exposed() {
-                        exposed.push(j);
-                    }
-                }
-            }
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::skeleton::io;
+    use crate::skeleton::topology::Topology;
+
+    /// Topology of triangles on vertices along x, with edges of no face
+    fn topology(nb_vertices: usize, faces: &[[usize; 3]], edges: &[[usize; 2]]) -> Topology {
+        let mut ply = format!(
+            "ply\nformat ascii 1.0\nelement vertex {}\nproperty float x\nproperty float y\n\
+             property float z\nproperty float radius\nelement face {}\n\
+             property list uchar uint vertex_indices\nelement edge {}\nproperty int vertex1\n\
+             property int vertex2\nend_header\n",
+            nb_vertices,
+            faces.len(),
+            edges.len()
+        );
+        for i in 0..nb_vertices {
+            ply += &format!("{} {} 0 0.1\n", i, i % 2);
+        }
+        for face in faces {
+            ply += &format!("3 {} {} {}\n", face[0], face[1], face[2]);
         }
+        for edge in edges {
+            ply += &format!("{} {}\n", edge[0], edge[1]);
+        }
+        let skel = io::import_from_ply_reader(&mut ply.as_bytes()).unwrap();
+        Topology::new(&skel).unwrap()
     }
 
-    pub fn get_exposed_sectors(&self) -> Vec<usize> {
-        self.sector
-            .iter()
-            .enumerate()
-            .filter_map(|(ind_sector, sector)| {
-                if sector.is_exposed() && !sector.is_burned() {
-                    Some(ind_sector)
-                } else {
-                    None
-                }
-            })
-            .collect()
+    fn vertex_link(topo: &Topology, ind_vertex: usize) -> VertexLink {
+        VertexLink::new(&topo.link_graph(ind_vertex))
     }
 
-    pub fn get_unburned_sectors(&self) -> Vec<usize> {
-        self.sector
-            .iter()
-            .enumerate()
-            .filter_map(|(ind_sector, sector)| {
-                if !sector.is_burned() {
-                    Some(ind_sector)
-                } else {
-                    None
-                }
-            })
-            .collect()
+    #[test]
+    fn bowtie_vertex() {
+        let topo = topology(6, &[[0, 1, 2], [0, 3, 4]], &[[1, 5]]);
+        let link = vertex_link(&topo, 0);
+        assert_eq!(link.neigh(), &vec![1, 2, 3, 4]);
+        assert!(link.is_boundary());
+        assert_eq!(link.sectors().len(), 2);
+        for (ind_sec, sector) in link.sectors().iter().enumerate() {
+            assert_eq!(sector.arc, vec![2 * ind_sec, 2 * ind_sec + 1]);
+            assert!(sector.beg_sec.is_empty() && sector.end_sec.is_empty());
+        }
+        assert_eq!(link.attached_sectors(2), vec![1]);
+
+        // the edge of no face does not give a neighbor
+        let link = vertex_link(&topo, 1);
+        assert_eq!(link.neigh(), &vec![0, 2]);
+        assert_eq!(link.sectors().len(), 1);
+    }
+
+    #[test]
+    fn fin_vertex() {
+        // three triangles on the edge (0, 1)
+        let topo = topology(5, &[[0, 1, 2], [1, 0, 3], [0, 1, 4]], &[]);
+        let link = vertex_link(&topo, 0);
+        assert_eq!(link.neigh(), &vec![1, 2, 3, 4]);
+        assert!(!link.is_boundary());
+        assert_eq!(link.sectors().len(), 3);
+        // each sector is exposed from its free end, or once the two other ones burn
+        for (ind_sec, sector) in link.sectors().iter().enumerate() {
+            assert_eq!(sector.arc, vec![0, ind_sec + 1]);
+            let others: Vec<usize> = (0..3).filter(|&s| s != ind_sec).collect();
+            assert_eq!(sector.beg_sec, others);
+            assert!(sector.end_sec.is_empty());
+        }
+        assert_eq!(link.attached_sectors(0), vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn closed_and_open_fans_on_a_vertex() {
+        // closed fan around 0 (cycle 1, 2, 3 in the link) and an open one (path 4, 5)
+        let topo = topology(6, &[[0, 1, 2], [0, 2, 3], [0, 3, 1], [0, 4, 5]], &[]);
+        let link = vertex_link(&topo, 0);
+        assert_eq!(link.neigh(), &vec![1, 2, 3, 4, 5]);
+        assert!(link.is_boundary());
+        let sectors = link.sectors();
+        assert_eq!(sectors.len(), 2);
+        assert_eq!(sectors[0].arc, vec![3, 4]);
+        assert!(sectors[0].beg_sec.is_empty() && sectors[0].end_sec.is_empty());
+        // the closed sector is never exposed by its neighbors
+        let mut arc = sectors[1].arc.clone();
+        arc.sort();
+        assert_eq!(arc, vec![0, 1, 2]);
+        assert_eq!(sectors[1].beg_sec, vec![1]);
+        assert_eq!(sectors[1].end_sec, vec![1]);
     }
 }
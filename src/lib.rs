@@ -1,2 +1,4 @@
+#![allow(clippy::needless_range_loop, clippy::module_inception)]
+
 pub mod et_algorithm;
 pub mod skeleton;
@@ -0,0 +1,369 @@
+use anyhow::Result;
+use std::collections::HashMap;
+
+use ply_rs::ply::{Property, PropertyType, ScalarType};
+
+/// Color stops, evenly spaced from 0 to 1
+const RED_BLUE: [[u8; 3]; 2] = [[0, 0, 255], [255, 0, 0]];
+const VIRIDIS: [[u8; 3]; 9] = [
+    [68, 1, 84],
+    [71, 44, 122],
+    [59, 81, 139],
+    [44, 113, 142],
+    [33, 144, 141],
+    [39, 173, 129],
+    [92, 200, 99],
+    [170, 220, 50],
+    [253, 231, 37],
+];
+const MAGMA: [[u8; 3]; 9] = [
+    [0, 0, 4],
+    [28, 16, 68],
+    [79, 18, 123],
+    [129, 37, 129],
+    [181, 54, 122],
+    [229, 80, 100],
+    [251, 135, 97],
+    [254, 194, 135],
+    [252, 253, 191],
+];
+const COOLWARM: [[u8; 3]; 9] = [
+    [59, 76, 192],
+    [98, 130, 234],
+    [141, 176, 254],
+    [184, 208, 249],
+    [221, 221, 221],
+    [245, 196, 173],
+    [244, 154, 123],
+    [222, 96, 77],
+    [180, 4, 38],
+];
+const GRAYSCALE: [[u8; 3]; 2] = [[0, 0, 0], [255, 255, 255]];
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Colormap {
+    /// Blue (low) to red (high)
+    RedBlue,
+    Viridis,
+    Magma,
+    Coolwarm,
+    Grayscale,
+}
+
+impl Colormap {
+    fn stops(&self) -> &'static [[u8; 3]] {
+        match self {
+            Colormap::RedBlue => &RED_BLUE,
+            Colormap::Viridis => &VIRIDIS,
+            Colormap::Magma => &MAGMA,
+            Colormap::Coolwarm => &COOLWARM,
+            Colormap::Grayscale => &GRAYSCALE,
+        }
+    }
+
+    /// Color at `t`, clamped to [0, 1]
+    pub fn color(&self, t: f32) -> [u8; 3] {
+        let stops = self.stops();
+        let pos = t.clamp(0.0, 1.0) * (stops.len() - 1) as f32;
+        let i = (pos.floor() as usize).min(stops.len() - 2);
+        let frac = pos - i as f32;
+        let mut color = [0; 3];
+        for c in 0..3 {
+            // truncated, as colors were before colormaps
+            let beg = stops[i][c] as f32;
+            let end = stops[i + 1][c] as f32;
+            color[c] = (beg * (1.0 - frac) + end * frac) as u8;
+        }
+        color
+    }
+}
+
+/// Values mapped to the ends of the colormap
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum ColorRange {
+    /// Min and max of the values
+    MinMax,
+    /// Given bounds
+    Fixed(f32, f32),
+    /// Given percentiles (0 to 100) of the values
+    Percentile(f32, f32),
+}
+
+/// Mapping from float values to colors
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct ColorScale {
+    pub colormap: Colormap,
+    pub range: ColorRange,
+    /// logarithmic scale, non positive values getting the invalid color
+    pub log: bool,
+    /// color of infinite and NaN values, and of non positive ones on a log scale
+    pub invalid_color: [u8; 3],
+}
+
+impl Default for ColorScale {
+    fn default() -> Self {
+        ColorScale {
+            colormap: Colormap::RedBlue,
+            range: ColorRange::MinMax,
+            log: false,
+            invalid_color: [255, 0, 255],
+        }
+    }
+}
+
+impl ColorScale {
+    /// Bounds of the scale on `values`, None if no value can be colored
+    ///
+    /// Only finite values (and positive ones on a log scale) are taken into account.
+    pub fn bounds(&self, values: &[f32]) -> Result<Option<(f32, f32)>> {
+        let mut valid: Vec<f32> = values
+            .iter()
+            .copied()
+            .filter(|&v| self.is_valid(v))
+            .collect();
+        let bounds = match self.range {
+            ColorRange::Fixed(lo, hi) => {
+                if !lo.is_finite() || !hi.is_finite() || lo > hi || (self.log && lo <= 0.0) {
+                    return Err(anyhow::Error::msg(format!(
+                        "Invalid color range [{}, {}]",
+                        lo, hi
+                    )));
+                }
+                Some((lo, hi))
+            }
+            ColorRange::Percentile(p_lo, p_hi)
+                if !(0.0..=100.0).contains(&p_lo)
+                    || !(0.0..=100.0).contains(&p_hi)
+                    || p_lo > p_hi =>
+            {
+                return Err(anyhow::Error::msg(format!(
+                    "Invalid color percentiles [{}, {}]",
+                    p_lo, p_hi
+                )));
+            }
+            _ if valid.is_empty() => None,
+            ColorRange::MinMax => Some(
+                valid
+                    .iter()
+                    .fold((f32::MAX, f32::MIN), |(lo, hi), &v| (lo.min(v), hi.max(v))),
+            ),
+            ColorRange::Percentile(p_lo, p_hi) => {
+                valid.sort_by(|a, b| a.partial_cmp(b).unwrap());
+                let at = |p: f32| valid[(p / 100.0 * (valid.len() - 1) as f32).round() as usize];
+                Some((at(p_lo), at(p_hi)))
+            }
+        };
+        Ok(bounds)
+    }
+
+    /// Whether a value is colored by the colormap, other ones getting the invalid color
+    pub fn is_valid(&self, value: f32) -> bool {
+        value.is_finite() && (!self.log || value > 0.0)
+    }
+
+    /// Color of each value
+    pub fn colors(&self, values: &[f32]) -> Result<Vec<[u8; 3]>> {
+        let bounds = self.bounds(values)?;
+        let scale = |v: f32| if self.log { v.ln() } else { v };
+        let colors = values
+            .iter()
+            .map(|&v| match bounds {
+                Some((lo, hi)) if self.is_valid(v) => {
+                    let t = if hi > lo {
+                        (scale(v) - scale(lo)) / (scale(hi) - scale(lo))
+                    } else {
+                        // constant values, middle of the colormap
+                        0.5
+                    };
+                    self.colormap.color(t)
+                }
+                _ => self.invalid_color,
+            })
+            .collect();
+        Ok(colors)
+    }
+}
+
+/// Values of the float vertex property `prop_name`
+pub(super) fn property_values_f32(
+    property_types: &HashMap<String, PropertyType>,
+    properties: &[HashMap<String, Property>],
+    prop_name: &str,
+) -> Result<Vec<f32>> {
+    match property_types.get(prop_name) {
+        None => return Err(anyhow::Error::msg("Property does not exist")),
+        Some(ptype) if *ptype != PropertyType::Scalar(ScalarType::Float) => {
+            return Err(anyhow::Error::msg("Property is not a float"))
+        }
+        _ => (),
+    }
+    properties
+        .iter()
+        .map(|prop| match prop.get(prop_name) {
+            Some(Property::Float(val)) => Ok(*val),
+            _ => Err(anyhow::Error::msg("Missing property value")),
+        })
+        .collect()
+}
+
+/// Sets red, green and blue vertex properties, colors of `values`
+pub(super) fn set_vertex_colors(
+    property_types: &mut HashMap<String, PropertyType>,
+    properties: &mut [HashMap<String, Property>],
+    values: &[f32],
+    scale: &ColorScale,
+) -> Result<()> {
+    let colors = scale.colors(values)?;
+    for name in ["red", "green", "blue"] {
+        property_types.insert(name.to_string(), PropertyType::Scalar(ScalarType::UChar));
+    }
+    for (prop, color) in properties.iter_mut().zip(colors) {
+        prop.insert("red".to_string(), Property::UChar(color[0]));
+        prop.insert("green".to_string(), Property::UChar(color[1]));
+        prop.insert("blue".to_string(), Property::UChar(color[2]));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BLUE: [u8; 3] = [0, 0, 255];
+    const PURPLE: [u8; 3] = [127, 0, 127];
+    const RED: [u8; 3] = [255, 0, 0];
+    const INVALID: [u8; 3] = [255, 0, 255];
+
+    fn scale(range: ColorRange, log: bool) -> ColorScale {
+        ColorScale {
+            range,
+            log,
+            ..ColorScale::default()
+        }
+    }
+
+    #[test]
+    fn colormap_stops() {
+        assert_eq!(Colormap::Viridis.color(0.0), VIRIDIS[0]);
+        assert_eq!(Colormap::Viridis.color(0.125), VIRIDIS[1]);
+        assert_eq!(Colormap::Viridis.color(1.0), VIRIDIS[8]);
+        // clamped
+        assert_eq!(Colormap::Magma.color(-1.0), MAGMA[0]);
+        assert_eq!(Colormap::Magma.color(2.0), MAGMA[8]);
+        assert_eq!(Colormap::Grayscale.color(0.5), [127, 127, 127]);
+    }
+
+    #[test]
+    fn min_max_range() {
+        let scale = scale(ColorRange::MinMax, false);
+        let values = [3.0, 1.0, 2.0];
+        assert_eq!(scale.bounds(&values).unwrap(), Some((1.0, 3.0)));
+        assert_eq!(scale.colors(&values).unwrap(), vec![RED, BLUE, PURPLE]);
+    }
+
+    #[test]
+    fn fixed_range() {
+        let scale = scale(ColorRange::Fixed(0.0, 4.0), false);
+        let values = [2.0, -1.0, 8.0, 0.0];
+        assert_eq!(scale.bounds(&values).unwrap(), Some((0.0, 4.0)));
+        // values out of the range are clamped
+        assert_eq!(
+            scale.colors(&values).unwrap(),
+            vec![PURPLE, BLUE, RED, BLUE]
+        );
+        // no valid value needed
+        assert_eq!(scale.bounds(&[f32::NAN]).unwrap(), Some((0.0, 4.0)));
+    }
+
+    #[test]
+    fn percentile_range() {
+        let values: Vec<f32> = (0..=100).rev().map(|v| v as f32).collect();
+        let scale = scale(ColorRange::Percentile(10.0, 90.0), false);
+        assert_eq!(scale.bounds(&values).unwrap(), Some((10.0, 90.0)));
+        // an outlier does not stretch the range
+        let values = [1.0, 2.0, 3.0, 4.0, 1000.0];
+        let scale = ColorScale {
+            range: ColorRange::Percentile(0.0, 50.0),
+            ..scale
+        };
+        assert_eq!(scale.bounds(&values).unwrap(), Some((1.0, 3.0)));
+        assert_eq!(
+            scale.colors(&values).unwrap(),
+            vec![BLUE, PURPLE, RED, RED, RED]
+        );
+        // percentiles of the valid values only
+        let values = [f32::NAN, 1.0, f32::INFINITY, 3.0];
+        let scale = ColorScale {
+            range: ColorRange::Percentile(0.0, 100.0),
+            ..scale
+        };
+        assert_eq!(scale.bounds(&values).unwrap(), Some((1.0, 3.0)));
+    }
+
+    #[test]
+    fn constant_values() {
+        let scale = scale(ColorRange::MinMax, false);
+        assert_eq!(scale.colors(&[5.0, 5.0]).unwrap(), vec![PURPLE, PURPLE]);
+        let scale = ColorScale {
+            range: ColorRange::Fixed(3.0, 3.0),
+            ..scale
+        };
+        assert_eq!(scale.colors(&[3.0, 7.0]).unwrap(), vec![PURPLE, PURPLE]);
+    }
+
+    #[test]
+    fn log_scale() {
+        let scale = scale(ColorRange::MinMax, true);
+        let values = [-1.0, 0.0, 1.0, 100.0, 10.0];
+        assert!(!scale.is_valid(0.0));
+        assert_eq!(scale.bounds(&values).unwrap(), Some((1.0, 100.0)));
+        let colors = scale.colors(&values).unwrap();
+        assert_eq!(colors[..2], [INVALID, INVALID]);
+        assert_eq!(colors[2], BLUE);
+        assert_eq!(colors[3], RED);
+        // middle of the log range
+        assert_eq!(colors[4], Colormap::RedBlue.color(0.5));
+        // all non positive
+        assert_eq!(scale.bounds(&[-2.0, 0.0]).unwrap(), None);
+    }
+
+    #[test]
+    fn invalid_values() {
+        let scale = ColorScale {
+            invalid_color: [1, 2, 3],
+            ..scale(ColorRange::MinMax, false)
+        };
+        let values = [f32::NAN, 1.0, f32::INFINITY, f32::NEG_INFINITY, 2.0];
+        assert!(!scale.is_valid(f32::NEG_INFINITY));
+        assert_eq!(scale.bounds(&values).unwrap(), Some((1.0, 2.0)));
+        assert_eq!(
+            scale.colors(&values).unwrap(),
+            vec![[1, 2, 3], BLUE, [1, 2, 3], [1, 2, 3], RED]
+        );
+        // nothing to color
+        assert_eq!(scale.bounds(&[f32::NAN]).unwrap(), None);
+        assert_eq!(scale.colors(&[f32::NAN]).unwrap(), vec![[1, 2, 3]]);
+        assert!(scale.colors(&[]).unwrap().is_empty());
+    }
+
+    #[test]
+    fn invalid_ranges() {
+        let values = [1.0, 2.0];
+        for (range, log) in [
+            (ColorRange::Fixed(2.0, 1.0), false),
+            (ColorRange::Fixed(f32::NAN, 1.0), false),
+            (ColorRange::Fixed(0.0, f32::INFINITY), false),
+            (ColorRange::Fixed(0.0, 1.0), true),
+            (ColorRange::Fixed(-1.0, 1.0), true),
+            (ColorRange::Percentile(-1.0, 50.0), false),
+            (ColorRange::Percentile(0.0, 101.0), false),
+            (ColorRange::Percentile(60.0, 40.0), false),
+            (ColorRange::Percentile(f32::NAN, 50.0), false),
+        ] {
+            let scale = scale(range, log);
+            assert!(scale.bounds(&values).is_err(), "{:?}", range);
+            // even without any value to color
+            assert!(scale.colors(&[]).is_err(), "{:?}", range);
+        }
+    }
+}
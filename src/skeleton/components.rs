@@ -0,0 +1,264 @@
+use anyhow::Result;
+use std::fmt;
+
+use super::skeleton::Skeleton;
+use super::topology::Topology;
+
+/// Connected components of a skeleton, through edges and faces
+pub struct Components {
+    nb_components: usize,
+    vertex_component: Vec<usize>,
+    face_component: Vec<usize>,
+}
+
+/// Erosion thickness statistics of one connected component
+pub struct ComponentStats {
+    pub component: usize,
+    pub nb_vertices: usize,
+    pub nb_faces: usize,
+    /// total length of edges used by only one face
+    pub boundary_length: f32,
+    /// number of vertices never reached by the burn front
+    pub nb_infinite: usize,
+    /// min, max and mean of finite erosion thickness values, None if all are infinite
+    pub et_min: Option<f32>,
+    pub et_max: Option<f32>,
+    pub et_mean: Option<f32>,
+    /// component without boundary edge
+    pub closed: bool,
+}
+
+fn find_root(parent: &mut [usize], i: usize) -> usize {
+    let mut root = i;
+    while parent[root] != root {
+        root = parent[root];
+    }
+    // path compression
+    let mut cur = i;
+    while parent[cur] != root {
+        let next = parent[cur];
+        parent[cur] = root;
+        cur = next;
+    }
+    root
+}
+
+pub(super) fn format_option(val: Option<f32>) -> String {
+    match val {
+        Some(v) => v.to_string(),
+        None => "inf".to_string(),
+    }
+}
+
+impl Components {
+    /// Components of a skeleton, an error if an edge or a face references a missing
+    /// vertex or a face is empty
+    pub fn new(skel: &Skeleton) -> Result<Components> {
+        let nb_vertices = skel.get_vertices().len();
+        for (i, face) in skel.get_face_vertices().iter().enumerate() {
+            if face.is_empty() {
+                return Err(anyhow::Error::msg(format!("Face {} has no vertex", i)));
+            }
+            if let Some(&v) = face.iter().find(|&&v| v >= nb_vertices) {
+                return Err(anyhow::Error::msg(format!(
+                    "Face {} references vertex {}, out of {} vertices",
+                    i, v, nb_vertices
+                )));
+            }
+        }
+        let mut parent: Vec<usize> = (0..nb_vertices).collect();
+        for (i, &[v1, v2]) in skel.get_edges().iter().enumerate() {
+            if v1.max(v2) >= nb_vertices {
+                return Err(anyhow::Error::msg(format!(
+                    "Edge {} references vertex {}, out of {} vertices",
+                    i,
+                    v1.max(v2),
+                    nb_vertices
+                )));
+            }
+            let r1 = find_root(&mut parent, v1);
+            let r2 = find_root(&mut parent, v2);
+            if r1 != r2 {
+                parent[r1.max(r2)] = r1.min(r2);
+            }
+        }
+
+        // components numbered following their lowest vertex index
+        let mut nb_components = 0;
+        let mut root_component = vec![None; nb_vertices];
+        let mut vertex_component = Vec::with_capacity(nb_vertices);
+        for i in 0..nb_vertices {
+            let root = find_root(&mut parent, i);
+            let comp = *root_component[root].get_or_insert_with(|| {
+                nb_components += 1;
+                nb_components - 1
+            });
+            vertex_component.push(comp);
+        }
+
+        let face_component = skel
+            .get_face_vertices()
+            .iter()
+            .map(|face| vertex_component[face[0]])
+            .collect();
+
+        Ok(Components {
+            nb_components,
+            vertex_component,
+            face_component,
+        })
+    }
+
+    pub fn nb_components(&self) -> usize {
+        self.nb_components
+    }
+
+    pub fn vertex_component(&self) -> &Vec<usize> {
+        &self.vertex_component
+    }
+
+    pub fn face_component(&self) -> &Vec<usize> {
+        &self.face_component
+    }
+
+    /// Statistics of each component, from erosion thickness values (None if infinite)
+    pub fn statistics(
+        &self,
+        skel: &Skeleton,
+        erosion_thickness: &[Option<f32>],
+    ) -> Result<Vec<ComponentStats>> {
+        if erosion_thickness.len() != self.vertex_component.len() {
+            return Err(anyhow::Error::msg(
+                "Number of vertices and erosion thickness values does not match",
+            ));
+        }
+        let topo = Topology::new(skel)?;
+
+        let mut stats: Vec<ComponentStats> = (0..self.nb_components)
+            .map(|component| ComponentStats {
+                component,
+                nb_vertices: 0,
+                nb_faces: 0,
+                boundary_length: 0.0,
+                nb_infinite: 0,
+                et_min: None,
+                et_max: None,
+                et_mean: None,
+                closed: true,
+            })
+            .collect();
+
+        let mut et_sum = vec![0.0; self.nb_components];
+        for (i, &et) in erosion_thickness.iter().enumerate() {
+            let stat = &mut stats[self.vertex_component[i]];
+            stat.nb_vertices += 1;
+            match et {
+                Some(et) => {
+                    stat.et_min = Some(stat.et_min.map_or(et, |m| m.min(et)));
+                    stat.et_max = Some(stat.et_max.map_or(et, |m| m.max(et)));
+                    et_sum[self.vertex_component[i]] += et;
+                }
+                None => stat.nb_infinite += 1,
+            }
+        }
+        for &comp in self.face_component.iter() {
+            stats[comp].nb_faces += 1;
+        }
+        for e in topo.boundary_edges() {
+            let [v1, v2] = topo.edge(e);
+            let stat = &mut stats[self.vertex_component[v1]];
+            stat.boundary_length += (skel.get_vertices()[v1] - skel.get_vertices()[v2]).norm();
+            stat.closed = false;
+        }
+        for stat in stats.iter_mut() {
+            let nb_finite = stat.nb_vertices - stat.nb_infinite;
+            if nb_finite != 0 {
+                stat.et_mean = Some(et_sum[stat.component] / nb_finite as f32);
+            }
+        }
+
+        Ok(stats)
+    }
+}
+
+impl fmt::Display for ComponentStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "component {}: {} vertices, {} faces, boundary length {}, {} infinite, et min {} max {} mean {}{}",
+            self.component,
+            self.nb_vertices,
+            self.nb_faces,
+            self.boundary_length,
+            self.nb_infinite,
+            format_option(self.et_min),
+            format_option(self.et_max),
+            format_option(self.et_mean),
+            if self.closed { ", closed" } else { "" }
+        )
+    }
+}
+
+impl Skeleton {
+    /// Computes connected components, and stores them as `component` vertex and face property
+    pub fn label_components(&mut self) -> Result<Components> {
+        let components = Components::new(self)?;
+        let vertex_labels: Vec<i32> = components
+            .vertex_component()
+            .iter()
+            .map(|&c| c as i32)
+            .collect();
+        let face_labels: Vec<i32> = components
+            .face_component()
+            .iter()
+            .map(|&c| c as i32)
+            .collect();
+        self.set_property_i32("component", &vertex_labels)?;
+        self.set_face_property_i32("component", &face_labels)?;
+        Ok(components)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nalgebra::base::*;
+    use std::collections::HashMap;
+
+    fn skeleton(nb_vertices: usize, faces: &[Vec<usize>]) -> Skeleton {
+        let mut skel = Skeleton::new();
+        for i in 0..nb_vertices {
+            skel.add_vertex(
+                Vector3::new(i as f32, (i % 2) as f32, 0.0),
+                0.1,
+                HashMap::new(),
+            );
+        }
+        for face in faces {
+            skel.add_face(face.clone(), HashMap::new());
+        }
+        skel
+    }
+
+    #[test]
+    fn components_of_disjoint_faces() {
+        let mut skel = skeleton(7, &[vec![0, 1, 2], vec![3, 4, 5], vec![2, 1, 6]]);
+        let components = skel.label_components().unwrap();
+        assert_eq!(components.nb_components(), 2);
+        assert_eq!(components.vertex_component(), &vec![0, 0, 0, 1, 1, 1, 0]);
+        assert_eq!(components.face_component(), &vec![0, 1, 0]);
+    }
+
+    #[test]
+    fn out_of_range_vertex_is_an_error() {
+        let mut skel = skeleton(3, &[vec![0, 1, 5]]);
+        assert!(Components::new(&skel).is_err());
+        assert!(skel.label_components().is_err());
+    }
+
+    #[test]
+    fn empty_face_is_an_error() {
+        let skel = skeleton(3, &[vec![]]);
+        assert!(Components::new(&skel).is_err());
+    }
+}
@@ -0,0 +1,184 @@
+use anyhow::Result;
+use flate2::read::MultiGzDecoder;
+use flate2::write::GzEncoder;
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xb5, 0x2f, 0xfd];
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Compression {
+    None,
+    Gzip,
+    Zstd,
+}
+
+impl Compression {
+    /// Compression given by the file extension (`.gz`, `.zst`)
+    pub fn from_path(file_path: &Path) -> Compression {
+        match file_path.extension().and_then(|ext| ext.to_str()) {
+            Some("gz") => Compression::Gzip,
+            Some("zst") | Some("zstd") => Compression::Zstd,
+            _ => Compression::None,
+        }
+    }
+
+    /// Compression given by the first bytes of a stream
+    pub fn from_magic(bytes: &[u8]) -> Compression {
+        if bytes.starts_with(&GZIP_MAGIC) {
+            Compression::Gzip
+        } else if bytes.starts_with(&ZSTD_MAGIC) {
+            Compression::Zstd
+        } else {
+            Compression::None
+        }
+    }
+}
+
+/// Wraps a reader into a decoder, compression being detected from magic bytes
+pub fn decompress<'a, R: BufRead + 'a>(mut reader: R) -> Result<Box<dyn BufRead + 'a>> {
+    let compression = Compression::from_magic(reader.fill_buf()?);
+    let reader: Box<dyn BufRead + 'a> = match compression {
+        Compression::None => Box::new(reader),
+        Compression::Gzip => Box::new(BufReader::new(MultiGzDecoder::new(reader))),
+        Compression::Zstd => Box::new(BufReader::new(zstd::Decoder::with_buffer(reader)?)),
+    };
+    Ok(reader)
+}
+
+/// Extension giving the file format, before any compression extension (`ply` for
+/// `skeleton.ply.gz`)
+pub fn format_extension(file_path: &Path) -> Option<&str> {
+    let file_path = match Compression::from_path(file_path) {
+        Compression::None => file_path,
+        _ => Path::new(file_path.file_stem()?),
+    };
+    file_path.extension()?.to_str()
+}
+
+/// Path standing for standard input or output
+pub fn is_std_stream(file_path: &Path) -> bool {
+    file_path == Path::new("-")
+}
+
+/// Opens a file (`-` for standard input) for reading, decompressing it if needed
+pub fn open_reader(file_path: &Path) -> Result<Box<dyn BufRead>> {
+    if is_std_stream(file_path) {
+        return decompress(std::io::stdin().lock());
+    }
+    let file = File::open(file_path)?;
+    decompress(BufReader::new(file))
+}
+
+/// Writer compressing its output, which must be finished once everything is written
+pub enum CompressedWriter<W: Write> {
+    Plain(BufWriter<W>),
+    Gzip(GzEncoder<BufWriter<W>>),
+    Zstd(zstd::Encoder<'static, BufWriter<W>>),
+}
+
+impl<W: Write> CompressedWriter<W> {
+    pub fn new(writer: W, compression: Compression) -> Result<CompressedWriter<W>> {
+        let writer = BufWriter::new(writer);
+        Ok(match compression {
+            Compression::None => CompressedWriter::Plain(writer),
+            Compression::Gzip => {
+                CompressedWriter::Gzip(GzEncoder::new(writer, flate2::Compression::default()))
+            }
+            Compression::Zstd => CompressedWriter::Zstd(zstd::Encoder::new(writer, 0)?),
+        })
+    }
+
+    /// Writes the end of the compressed stream and flushes it
+    pub fn finish(self) -> Result<()> {
+        let mut writer = match self {
+            CompressedWriter::Plain(writer) => writer,
+            CompressedWriter::Gzip(encoder) => encoder.finish()?,
+            CompressedWriter::Zstd(encoder) => encoder.finish()?,
+        };
+        writer.flush()?;
+        Ok(())
+    }
+}
+
+impl<W: Write> Write for CompressedWriter<W> {
+    // always writes the whole buffer: the ply writer does not handle partial writes,
+    // which encoders do
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        match self {
+            CompressedWriter::Plain(writer) => writer.write_all(buf)?,
+            CompressedWriter::Gzip(encoder) => encoder.write_all(buf)?,
+            CompressedWriter::Zstd(encoder) => encoder.write_all(buf)?,
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        match self {
+            CompressedWriter::Plain(writer) => writer.flush(),
+            CompressedWriter::Gzip(encoder) => encoder.flush(),
+            CompressedWriter::Zstd(encoder) => encoder.flush(),
+        }
+    }
+}
+
+/// Creates a file (`-` for standard output) for writing, compressed following its extension
+pub fn create_writer(file_path: &Path) -> Result<CompressedWriter<Box<dyn Write>>> {
+    let writer: Box<dyn Write> = if is_std_stream(file_path) {
+        Box::new(std::io::stdout().lock())
+    } else {
+        Box::new(File::create(file_path)?)
+    };
+    CompressedWriter::new(writer, Compression::from_path(file_path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+
+    #[test]
+    fn compression_from_path_and_format() {
+        assert_eq!(
+            Compression::from_path(Path::new("a.ply.gz")),
+            Compression::Gzip
+        );
+        assert_eq!(
+            Compression::from_path(Path::new("a.zstd")),
+            Compression::Zstd
+        );
+        assert_eq!(
+            Compression::from_path(Path::new("a.ply")),
+            Compression::None
+        );
+        assert_eq!(format_extension(Path::new("a.ply.zst")), Some("ply"));
+        assert_eq!(format_extension(Path::new("a.obj")), Some("obj"));
+        assert_eq!(format_extension(Path::new("a.gz")), None);
+    }
+
+    #[test]
+    fn compressed_streams_are_detected() {
+        let data = b"ply\nformat ascii 1.0\n".repeat(100);
+        for compression in [Compression::None, Compression::Gzip, Compression::Zstd] {
+            let mut writer = CompressedWriter::new(Vec::new(), compression).unwrap();
+            writer.write_all(&data).unwrap();
+            let bytes = match writer {
+                CompressedWriter::Plain(writer) => writer.into_inner().unwrap(),
+                CompressedWriter::Gzip(encoder) => encoder.finish().unwrap().into_inner().unwrap(),
+                CompressedWriter::Zstd(encoder) => encoder.finish().unwrap().into_inner().unwrap(),
+            };
+            assert_eq!(Compression::from_magic(&bytes), compression);
+
+            let mut read = Vec::new();
+            decompress(bytes.as_slice())
+                .unwrap()
+                .read_to_end(&mut read)
+                .unwrap();
+            assert_eq!(read, data);
+        }
+        // shorter than the magic bytes
+        assert_eq!(Compression::from_magic(&[0x28, 0xb5]), Compression::None);
+    }
+}
@@ -0,0 +1,357 @@
+use anyhow::Result;
+use nalgebra::base::*;
+use std::fmt;
+
+use super::skeleton::Skeleton;
+
+/// Default number of bins of each descriptor histogram
+pub const DEFAULT_NB_BINS: usize = 32;
+
+/// Upper bound of the erosion thickness histogram, relative to the shape scale
+///
+/// Larger values (and infinite ones) fall in the last bin.
+pub const ET_RANGE: f32 = 0.25;
+
+/// Distance between descriptor histograms
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum HistogramDistance {
+    /// Sum of absolute bin differences
+    L1,
+    /// Symmetric chi-squared, `sum (a - b)^2 / (a + b)`
+    ChiSquared,
+    /// Earth mover's distance, over a histogram range of 1 (bins being `1 / n` apart)
+    Emd,
+}
+
+impl HistogramDistance {
+    pub const ALL: [HistogramDistance; 3] = [
+        HistogramDistance::L1,
+        HistogramDistance::ChiSquared,
+        HistogramDistance::Emd,
+    ];
+
+    /// Distance between two histograms of the same length
+    pub fn distance(&self, a: &[f32], b: &[f32]) -> Result<f32> {
+        if a.len() != b.len() {
+            return Err(anyhow::Error::msg(format!(
+                "Histograms of {} and {} bins cannot be compared",
+                a.len(),
+                b.len()
+            )));
+        }
+        let dist = match self {
+            HistogramDistance::L1 => a.iter().zip(b).map(|(x, y)| (x - y).abs()).sum(),
+            HistogramDistance::ChiSquared => a
+                .iter()
+                .zip(b)
+                .filter(|(x, y)| *x + *y > 0.0)
+                .map(|(x, y)| (x - y) * (x - y) / (x + y))
+                .sum(),
+            HistogramDistance::Emd => {
+                // in one dimension, the area between the cumulative histograms
+                let mut cumul = 0.0;
+                let mut dist = 0.0;
+                for i in 0..a.len() {
+                    cumul += a[i] - b[i];
+                    dist += f32::abs(cumul);
+                }
+                dist / a.len() as f32
+            }
+        };
+        Ok(dist)
+    }
+}
+
+impl fmt::Display for HistogramDistance {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            HistogramDistance::L1 => write!(f, "l1"),
+            HistogramDistance::ChiSquared => write!(f, "chi2"),
+            HistogramDistance::Emd => write!(f, "emd"),
+        }
+    }
+}
+
+/// Shape descriptor made of area-weighted histograms of erosion thickness and shape
+/// tubularity over the skeleton faces
+///
+/// Erosion thickness is divided by the shape scale, the diagonal of the bounding box of
+/// the medial balls, and binned over `[0, ET_RANGE]`. Shape tubularity, erosion
+/// thickness over burn time (`ET / (ET + radius)`), lies in `[0, 1]`. Each histogram
+/// sums to 1.
+#[derive(Clone, Debug)]
+pub struct Descriptor {
+    pub et: Vec<f32>,
+    pub st: Vec<f32>,
+}
+
+/// Bin of a value over `[0, range]`, clamped to the first and last bins
+fn bin(value: f32, range: f32, nb_bins: usize) -> usize {
+    let bin = (value / range * nb_bins as f32).floor();
+    if bin.is_nan() || bin < 0.0 {
+        0
+    } else {
+        (bin as usize).min(nb_bins - 1)
+    }
+}
+
+/// Area of a polygon, as a fan of triangles
+fn face_area(vertices: &[Vector3<f32>], face: &[usize]) -> f32 {
+    let mut area = 0.0;
+    for i in 1..face.len().saturating_sub(1) {
+        let u = vertices[face[i]] - vertices[face[0]];
+        let v = vertices[face[i + 1]] - vertices[face[0]];
+        area += u.cross(&v).norm() / 2.0;
+    }
+    area
+}
+
+impl Descriptor {
+    /// Descriptor of a skeleton holding an `erosion_thickness` vertex property
+    ///
+    /// Each face weighs its area, with the mean value of its vertices. Skeletons without
+    /// face area give the same weight to all vertices.
+    pub fn new(skel: &Skeleton, nb_bins: usize) -> Result<Descriptor> {
+        if nb_bins == 0 {
+            return Err(anyhow::Error::msg("Descriptor histograms need bins"));
+        }
+        let vertices = skel.get_vertices();
+        let radii = skel.get_radii();
+        let et_values = skel.get_property_f32("erosion_thickness").map_err(|_| {
+            anyhow::Error::msg("No erosion_thickness vertex property, compute it first")
+        })?;
+        if vertices.is_empty() {
+            return Err(anyhow::Error::msg("No vertex to describe"));
+        }
+
+        let mut lower = Vector3::repeat(f32::MAX);
+        let mut upper = Vector3::repeat(f32::MIN);
+        for i in 0..vertices.len() {
+            lower = lower.inf(&vertices[i].add_scalar(-radii[i]));
+            upper = upper.sup(&vertices[i].add_scalar(radii[i]));
+        }
+        let scale = (upper - lower).norm();
+        if !scale.is_finite() || scale <= 0.0 {
+            return Err(anyhow::Error::msg(format!("Invalid shape scale {}", scale)));
+        }
+
+        let et: Vec<f32> = et_values.iter().map(|&et| et / scale).collect();
+        let st: Vec<f32> = (0..vertices.len())
+            .map(|i| {
+                let burntime = et_values[i] + radii[i];
+                if et_values[i].is_infinite() {
+                    1.0
+                } else if burntime > 0.0 {
+                    et_values[i] / burntime
+                } else {
+                    0.0
+                }
+            })
+            .collect();
+
+        // (weight, vertices) of each sample
+        let mut samples: Vec<(f32, Vec<usize>)> = skel
+            .get_face_vertices()
+            .iter()
+            .map(|face| (face_area(vertices, face), face.clone()))
+            .filter(|(area, _)| *area > 0.0)
+            .collect();
+        if samples.is_empty() {
+            samples = (0..vertices.len()).map(|i| (1.0, vec![i])).collect();
+        }
+
+        let mut descriptor = Descriptor {
+            et: vec![0.0; nb_bins],
+            st: vec![0.0; nb_bins],
+        };
+        let mut total = 0.0;
+        for (weight, face) in samples.iter() {
+            let mean =
+                |values: &[f32]| face.iter().map(|&v| values[v]).sum::<f32>() / face.len() as f32;
+            let (et_mean, st_mean) = (mean(&et), mean(&st));
+            if et_mean.is_nan() || st_mean.is_nan() {
+                continue;
+            }
+            descriptor.et[bin(et_mean, ET_RANGE, nb_bins)] += weight;
+            descriptor.st[bin(st_mean, 1.0, nb_bins)] += weight;
+            total += weight;
+        }
+        if total <= 0.0 {
+            return Err(anyhow::Error::msg("No valid erosion thickness value"));
+        }
+        for i in 0..nb_bins {
+            descriptor.et[i] /= total;
+            descriptor.st[i] /= total;
+        }
+        Ok(descriptor)
+    }
+
+    /// Fixed-length vector: erosion thickness histogram followed by shape tubularity one
+    pub fn values(&self) -> Vec<f32> {
+        self.et.iter().chain(self.st.iter()).copied().collect()
+    }
+
+    /// Sum of the distances between erosion thickness and shape tubularity histograms
+    pub fn distance(&self, other: &Descriptor, distance: HistogramDistance) -> Result<f32> {
+        Ok(distance.distance(&self.et, &other.et)? + distance.distance(&self.st, &other.st)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::skeleton::io::import_from_ply_reader;
+
+    /// Skeleton of `vertices` (x y z radius erosion_thickness) and triangles
+    fn skeleton(vertices: &[[f32; 5]], triangles: &[[usize; 3]]) -> Skeleton {
+        let mut ply = format!(
+            "ply\nformat ascii 1.0\nelement vertex {}\nproperty float x\nproperty float y\n\
+             property float z\nproperty float radius\nelement face {}\n\
+             property list uchar int vertex_indices\nend_header\n",
+            vertices.len(),
+            triangles.len()
+        );
+        for [x, y, z, radius, _] in vertices.iter() {
+            ply.push_str(&format!("{} {} {} {}\n", x, y, z, radius));
+        }
+        for [a, b, c] in triangles.iter() {
+            ply.push_str(&format!("3 {} {} {}\n", a, b, c));
+        }
+        let mut skel = import_from_ply_reader(&mut ply.as_bytes()).unwrap();
+        let et: Vec<f32> = vertices.iter().map(|vertex| vertex[4]).collect();
+        skel.set_property_f32("erosion_thickness", &et).unwrap();
+        skel
+    }
+
+    fn assert_normalized(descriptor: &Descriptor) {
+        for histogram in [&descriptor.et, &descriptor.st] {
+            assert!((histogram.iter().sum::<f32>() - 1.0).abs() < 1e-6);
+        }
+    }
+
+    fn assert_close(values: &[f32], expected: &[f32]) {
+        assert_eq!(values.len(), expected.len());
+        for (value, expected) in values.iter().zip(expected) {
+            assert!(
+                (value - expected).abs() < 1e-6,
+                "{:?} {:?}",
+                values,
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn histogram_distances() {
+        let a = [0.5, 0.5, 0.0, 0.0];
+        let b = [0.0, 0.5, 0.5, 0.0];
+        assert_eq!(HistogramDistance::L1.distance(&a, &b).unwrap(), 1.0);
+        // 0.25 / 0.5 twice, empty bins skipped
+        assert_eq!(HistogramDistance::ChiSquared.distance(&a, &b).unwrap(), 1.0);
+        // half of the mass moved by two bins of 1/4
+        assert_eq!(HistogramDistance::Emd.distance(&a, &b).unwrap(), 0.25);
+
+        let a = [1.0, 0.0, 0.0, 0.0];
+        let b = [0.0, 0.0, 0.0, 1.0];
+        assert_eq!(HistogramDistance::L1.distance(&a, &b).unwrap(), 2.0);
+        assert_eq!(HistogramDistance::ChiSquared.distance(&a, &b).unwrap(), 2.0);
+        assert_eq!(HistogramDistance::Emd.distance(&a, &b).unwrap(), 0.75);
+        assert_eq!(HistogramDistance::Emd.distance(&b, &a).unwrap(), 0.75);
+
+        for distance in HistogramDistance::ALL {
+            assert_eq!(distance.distance(&a, &a).unwrap(), 0.0);
+            assert!(distance.distance(&a, &a[..3]).is_err());
+        }
+    }
+
+    #[test]
+    fn area_weighted_histograms() {
+        // radius 0.5: balls between (-0.5, -0.5, -0.5) and (2.5, 2.5, 1.5), scale sqrt(22)
+        let skel = skeleton(
+            &[
+                [0.0, 0.0, 0.0, 0.5, 0.0],
+                [1.0, 0.0, 0.0, 0.5, 0.0],
+                [0.0, 1.0, 0.0, 0.5, 0.0],
+                [0.0, 0.0, 1.0, 0.5, 0.75],
+                [2.0, 0.0, 1.0, 0.5, 0.75],
+                [0.0, 2.0, 1.0, 0.5, 0.75],
+            ],
+            &[[0, 1, 2], [3, 4, 5]],
+        );
+        let descriptor = Descriptor::new(&skel, 4).unwrap();
+        assert_normalized(&descriptor);
+        // areas 0.5 and 2, erosion thickness 0 and 0.75 / sqrt(22) = 0.16 (bin 2 of
+        // [0, 0.25]), tubularity 0 and 0.75 / 1.25 = 0.6
+        assert_close(&descriptor.et, &[0.2, 0.0, 0.8, 0.0]);
+        assert_close(&descriptor.st, &[0.2, 0.0, 0.8, 0.0]);
+        assert_eq!(descriptor.values().len(), 8);
+        assert_eq!(
+            descriptor
+                .distance(&descriptor, HistogramDistance::Emd)
+                .unwrap(),
+            0.0
+        );
+    }
+
+    #[test]
+    fn infinite_and_nan_values() {
+        let skel = skeleton(
+            &[
+                [0.0, 0.0, 0.0, 0.5, f32::INFINITY],
+                [1.0, 0.0, 0.0, 0.5, 0.0],
+                [0.0, 1.0, 0.0, 0.5, 0.0],
+                [0.0, 0.0, 1.0, 0.5, f32::NAN],
+                [1.0, 0.0, 1.0, 0.5, 0.0],
+                [0.0, 1.0, 1.0, 0.5, 0.0],
+            ],
+            &[[0, 1, 2], [3, 4, 5]],
+        );
+        let descriptor = Descriptor::new(&skel, 4).unwrap();
+        assert_normalized(&descriptor);
+        // faces with a NaN value left out, infinite erosion thickness in the last bin and
+        // tubularity 1, the face mean being 1 / 3
+        assert_close(&descriptor.et, &[0.0, 0.0, 0.0, 1.0]);
+        assert_close(&descriptor.st, &[0.0, 1.0, 0.0, 0.0]);
+
+        let skel = skeleton(
+            &[
+                [0.0, 0.0, 0.0, 0.5, f32::NAN],
+                [1.0, 0.0, 0.0, 0.5, 0.0],
+                [0.0, 1.0, 0.0, 0.5, 0.0],
+            ],
+            &[[0, 1, 2]],
+        );
+        assert!(Descriptor::new(&skel, 4).is_err());
+    }
+
+    #[test]
+    fn vertex_weights_without_face_area() {
+        // collinear vertices, the face having no area
+        let vertices = [
+            [0.0, 0.0, 0.0, 1.0, 0.0],
+            [1.0, 0.0, 0.0, 1.0, 0.0],
+            [2.0, 0.0, 0.0, 1.0, 3.0],
+        ];
+        let with_face = Descriptor::new(&skeleton(&vertices, &[[0, 1, 2]]), 4).unwrap();
+        let without_face = Descriptor::new(&skeleton(&vertices, &[]), 4).unwrap();
+        for descriptor in [&with_face, &without_face] {
+            assert_normalized(descriptor);
+            // tubularity 0, 0 and 3 / 4
+            assert_close(&descriptor.st, &[2.0 / 3.0, 0.0, 0.0, 1.0 / 3.0]);
+        }
+        assert_eq!(with_face.et, without_face.et);
+    }
+
+    #[test]
+    fn invalid_descriptors() {
+        let skel = skeleton(&[[0.0, 0.0, 0.0, 1.0, 0.0]], &[]);
+        assert!(Descriptor::new(&skel, 0).is_err());
+        // a single ball has a scale
+        assert!(Descriptor::new(&skel, 4).is_ok());
+        let skel = skeleton(&[[0.0, 0.0, 0.0, 0.0, 0.0]], &[]);
+        assert!(Descriptor::new(&skel, 4).is_err());
+        let mut skel = skeleton(&[[0.0, 0.0, 0.0, 1.0, 0.0]], &[]);
+        skel.remove_vertex_property("erosion_thickness");
+        assert!(Descriptor::new(&skel, 4).is_err());
+    }
+}
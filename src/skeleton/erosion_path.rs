@@ -2,6 +2,7 @@ use anyhow::Result;
 use nalgebra::base::*;
 use std::collections::HashMap;
 
+use super::colormap::{self, ColorScale};
 use ply_rs::ply::{
     Addable, DefaultElement, ElementDef, Property, PropertyDef, PropertyType, ScalarType,
 };
@@ -11,6 +12,15 @@ pub struct ErosionPath {
     edge_property_types: HashMap<String, PropertyType>,
     vertex_properties: Vec<HashMap<String, Property>>,
     edge_properties: Vec<HashMap<String, Property>>,
+    comments: Vec<String>,
+    // number of skeleton vertices, coming first
+    nb_original: usize,
+}
+
+impl Default for ErosionPath {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl ErosionPath {
@@ -31,6 +41,8 @@ impl ErosionPath {
             edge_property_types,
             vertex_properties: Vec::new(),
             edge_properties: Vec::new(),
+            comments: Vec::new(),
+            nb_original: 0,
         }
     }
 
@@ -108,57 +120,89 @@ impl ErosionPath {
         self.edge_properties.len() - 1
     }
 
-    pub fn set_vertex_color_from_property_f32(&mut self, prop_name: &str) -> Result<()> {
-        if !self.vertex_property_types.contains_key(prop_name) {
-            return Err(anyhow::Error::msg("Property does not exist"));
-        }
-        if *self.vertex_property_types.get(prop_name).unwrap()
-            != PropertyType::Scalar(ScalarType::Float)
-        {
-            return Err(anyhow::Error::msg("Property is not a float"));
-        }
-        let (min_p, max_p) = self
-            .vertex_properties
+    /// Vertex positions
+    pub fn get_vertices(&self) -> Vec<Vector3<f32>> {
+        let coord = |prop: &HashMap<String, Property>, name: &str| match prop.get(name) {
+            Some(Property::Float(val)) => *val,
+            _ => f32::NAN,
+        };
+        self.vertex_properties
             .iter()
-            .fold(None, |val, vert_prop| {
-                let v_cur = vert_prop.get(prop_name).unwrap();
-                let v_cur = if let Property::Float(v) = v_cur {
-                    *v
-                } else {
-                    panic!()
-                };
-                if let Some((v_min, v_max)) = val {
-                    let v_min = if v_min < v_cur { v_min } else { v_cur };
-                    let v_max = if v_max > v_cur { v_max } else { v_cur };
-                    Some((v_min, v_max))
-                } else {
-                    Some((v_cur, v_cur))
-                }
-            })
-            .unwrap();
-
-        self.vertex_property_types
-            .insert("red".to_string(), PropertyType::Scalar(ScalarType::UChar));
-        self.vertex_property_types
-            .insert("green".to_string(), PropertyType::Scalar(ScalarType::UChar));
-        self.vertex_property_types
-            .insert("blue".to_string(), PropertyType::Scalar(ScalarType::UChar));
-        for prop in self.vertex_properties.iter_mut() {
-            let p_cur = prop.get(prop_name).unwrap();
-            let p_cur = if let Property::Float(p) = p_cur {
-                p
-            } else {
-                panic!()
-            };
-            let factor = (p_cur - min_p) / (max_p - min_p);
-            let r = (255.0 * factor) as u8;
-            let g = 0 as u8;
-            let b = (255.0 * (1.0 - factor)) as u8;
-            prop.insert("red".to_string(), Property::UChar(r));
-            prop.insert("green".to_string(), Property::UChar(g));
-            prop.insert("blue".to_string(), Property::UChar(b));
+            .map(|prop| Vector3::new(coord(prop, "x"), coord(prop, "y"), coord(prop, "z")))
+            .collect()
+    }
+
+    /// Edges, as pairs of vertex indices
+    pub fn get_edges(&self) -> Vec<[usize; 2]> {
+        let index = |prop: &HashMap<String, Property>, name: &str| match prop.get(name) {
+            Some(Property::Int(val)) => *val as usize,
+            _ => usize::MAX,
+        };
+        self.edge_properties
+            .iter()
+            .map(|prop| [index(prop, "vertex1"), index(prop, "vertex2")])
+            .collect()
+    }
+
+    /// Flags the first `nb_original` vertices as skeleton vertices, the others being
+    /// subdivision vertices
+    ///
+    /// The flags are only written to graph files, not as a vertex property.
+    pub fn set_original_vertices(&mut self, nb_original: usize) {
+        self.nb_original = nb_original;
+    }
+
+    /// Whether each vertex is a skeleton vertex, false for subdivision vertices (or if unknown)
+    pub fn get_original_flags(&self) -> Vec<bool> {
+        (0..self.vertex_properties.len())
+            .map(|i| i < self.nb_original)
+            .collect()
+    }
+
+    /// Ply header comments, written on export
+    pub fn get_comments(&self) -> &Vec<String> {
+        &self.comments
+    }
+
+    pub fn add_comment(&mut self, comment: &str) {
+        self.comments.push(comment.to_string());
+    }
+
+    /// Values of a float vertex property
+    pub fn get_property_f32(&self, prop_name: &str) -> Result<Vec<f32>> {
+        colormap::property_values_f32(
+            &self.vertex_property_types,
+            &self.vertex_properties,
+            prop_name,
+        )
+    }
+
+    /// Removes a vertex property, if it exists
+    pub fn remove_vertex_property(&mut self, prop_name: &str) {
+        if self.vertex_property_types.remove(prop_name).is_some() {
+            for prop in self.vertex_properties.iter_mut() {
+                prop.remove(prop_name);
+            }
         }
+    }
+
+    /// Sets vertex colors from a float property, blue to red over its range
+    pub fn set_vertex_color_from_property_f32(&mut self, prop_name: &str) -> Result<()> {
+        self.set_vertex_color_with_scale(prop_name, &ColorScale::default())
+    }
 
-        Ok(())
+    /// Sets vertex colors from a float property
+    pub fn set_vertex_color_with_scale(
+        &mut self,
+        prop_name: &str,
+        scale: &ColorScale,
+    ) -> Result<()> {
+        let values = self.get_property_f32(prop_name)?;
+        colormap::set_vertex_colors(
+            &mut self.vertex_property_types,
+            &mut self.vertex_properties,
+            &values,
+            scale,
+        )
     }
 }
@@ -0,0 +1,468 @@
+use anyhow::Result;
+use serde_json::{json, Map, Value};
+use std::io::Write;
+use std::path::Path;
+
+use nalgebra::base::*;
+use ply_rs::ply::{DefaultElement, ElementDef, Property, PropertyType};
+
+use super::compression;
+use super::erosion_path::ErosionPath;
+use super::skeleton::Skeleton;
+
+const GLB_MAGIC: u32 = 0x4654_6C67;
+const CHUNK_JSON: u32 = 0x4E4F_534A;
+const CHUNK_BIN: u32 = 0x004E_4942;
+
+const MODE_POINTS: u32 = 0;
+const MODE_LINES: u32 = 1;
+const MODE_TRIANGLES: u32 = 4;
+
+const UNSIGNED_BYTE: u32 = 5121;
+const UNSIGNED_INT: u32 = 5125;
+const FLOAT: u32 = 5126;
+
+const ARRAY_BUFFER: u32 = 34962;
+const ELEMENT_ARRAY_BUFFER: u32 = 34963;
+
+/// Binary buffer of a glb file, with its views and accessors
+struct GlbBuffer {
+    data: Vec<u8>,
+    buffer_views: Vec<Value>,
+    accessors: Vec<Value>,
+}
+
+impl GlbBuffer {
+    fn new() -> Self {
+        GlbBuffer {
+            data: Vec::new(),
+            buffer_views: Vec::new(),
+            accessors: Vec::new(),
+        }
+    }
+
+    /// Appends a buffer view (4 bytes aligned) and its accessor, returns the accessor index
+    fn add_accessor(&mut self, bytes: &[u8], target: u32, mut accessor: Value) -> usize {
+        while !self.data.len().is_multiple_of(4) {
+            self.data.push(0);
+        }
+        self.buffer_views.push(json!({
+            "buffer": 0,
+            "byteOffset": self.data.len(),
+            "byteLength": bytes.len(),
+            "target": target,
+        }));
+        self.data.extend_from_slice(bytes);
+        accessor["bufferView"] = json!(self.buffer_views.len() - 1);
+        self.accessors.push(accessor);
+        self.accessors.len() - 1
+    }
+
+    /// Positions, with their bounds (required by glTF)
+    fn add_positions(&mut self, positions: &[Vector3<f32>]) -> usize {
+        let mut min = [f32::MAX; 3];
+        let mut max = [f32::MIN; 3];
+        let mut bytes = Vec::with_capacity(positions.len() * 12);
+        for pos in positions.iter() {
+            for c in 0..3 {
+                min[c] = min[c].min(pos[c]);
+                max[c] = max[c].max(pos[c]);
+                bytes.extend_from_slice(&pos[c].to_le_bytes());
+            }
+        }
+        let accessor = json!({
+            "componentType": FLOAT,
+            "count": positions.len(),
+            "type": "VEC3",
+            "min": min,
+            "max": max,
+        });
+        self.add_accessor(&bytes, ARRAY_BUFFER, accessor)
+    }
+
+    fn add_scalars(&mut self, values: &[f32]) -> usize {
+        let bytes: Vec<u8> = values.iter().flat_map(|val| val.to_le_bytes()).collect();
+        let accessor = json!({
+            "componentType": FLOAT,
+            "count": values.len(),
+            "type": "SCALAR",
+        });
+        self.add_accessor(&bytes, ARRAY_BUFFER, accessor)
+    }
+
+    /// Colors, as normalized unsigned bytes padded to 4 bytes per vertex
+    fn add_colors(&mut self, colors: &[[u8; 3]]) -> usize {
+        let bytes: Vec<u8> = colors
+            .iter()
+            .flat_map(|&[r, g, b]| [r, g, b, 255])
+            .collect();
+        let accessor = json!({
+            "componentType": UNSIGNED_BYTE,
+            "normalized": true,
+            "count": colors.len(),
+            "type": "VEC4",
+        });
+        self.add_accessor(&bytes, ARRAY_BUFFER, accessor)
+    }
+
+    fn add_indices(&mut self, indices: &[u32]) -> usize {
+        let bytes: Vec<u8> = indices.iter().flat_map(|ind| ind.to_le_bytes()).collect();
+        let accessor = json!({
+            "componentType": UNSIGNED_INT,
+            "count": indices.len(),
+            "type": "SCALAR",
+        });
+        self.add_accessor(&bytes, ELEMENT_ARRAY_BUFFER, accessor)
+    }
+}
+
+/// Custom attribute name of a property, e.g. `_EROSION_THICKNESS`
+fn attribute_name(prop_name: &str) -> String {
+    format!("_{}", prop_name.to_uppercase())
+}
+
+fn property_as_f32(prop: &Property) -> Option<f32> {
+    match prop {
+        Property::Char(val) => Some(*val as f32),
+        Property::UChar(val) => Some(*val as f32),
+        Property::Short(val) => Some(*val as f32),
+        Property::UShort(val) => Some(*val as f32),
+        Property::Int(val) => Some(*val as f32),
+        Property::UInt(val) => Some(*val as f32),
+        Property::Float(val) => Some(*val),
+        Property::Double(val) => Some(*val as f32),
+        _ => None,
+    }
+}
+
+/// Vertex attributes: position, colors if any, and other scalar properties as float
+/// custom attributes
+fn vertex_attributes(
+    buffer: &mut GlbBuffer,
+    positions: &[Vector3<f32>],
+    vertex_def: &ElementDef,
+    vertices: &[DefaultElement],
+) -> Map<String, Value> {
+    let mut attributes = Map::new();
+    attributes.insert(
+        "POSITION".to_string(),
+        json!(buffer.add_positions(positions)),
+    );
+
+    let colors: Option<Vec<[u8; 3]>> = vertices
+        .iter()
+        .map(
+            |vertex| match (vertex.get("red"), vertex.get("green"), vertex.get("blue")) {
+                (Some(Property::UChar(r)), Some(Property::UChar(g)), Some(Property::UChar(b))) => {
+                    Some([*r, *g, *b])
+                }
+                _ => None,
+            },
+        )
+        .collect();
+    if let Some(colors) = colors.filter(|colors| !colors.is_empty()) {
+        attributes.insert("COLOR_0".to_string(), json!(buffer.add_colors(&colors)));
+    }
+
+    let mut names: Vec<&String> = vertex_def
+        .properties
+        .iter()
+        .filter(|(name, prop_def)| {
+            matches!(prop_def.data_type, PropertyType::Scalar(_))
+                && !["x", "y", "z", "red", "green", "blue"].contains(&name.as_str())
+        })
+        .map(|(name, _)| name)
+        .collect();
+    names.sort();
+    for name in names {
+        let values: Vec<f32> = vertices
+            .iter()
+            .map(|vertex| {
+                vertex
+                    .get(name)
+                    .and_then(property_as_f32)
+                    .unwrap_or(f32::NAN)
+            })
+            .collect();
+        attributes.insert(attribute_name(name), json!(buffer.add_scalars(&values)));
+    }
+    attributes
+}
+
+/// Mesh of a skeleton: triangulated faces (fans), edges of no face as lines
+fn skeleton_mesh(buffer: &mut GlbBuffer, skel: &Skeleton) -> Value {
+    let attributes = vertex_attributes(
+        buffer,
+        skel.get_vertices(),
+        &skel.vertex_header_element(),
+        &skel.vertex_payload_element(),
+    );
+    let mut triangles = Vec::new();
+    for face in skel.get_face_vertices().iter() {
+        for i in 1..face.len().saturating_sub(1) {
+            triangles.extend([face[0], face[i], face[i + 1]].map(|v| v as u32));
+        }
+    }
+    let mut lines = Vec::new();
+    for edge_index in skel.get_faceless_edges() {
+        lines.extend(skel.get_edges()[edge_index].map(|v| v as u32));
+    }
+
+    let mut primitives = Vec::new();
+    for (indices, mode) in [(triangles, MODE_TRIANGLES), (lines, MODE_LINES)] {
+        if !indices.is_empty() {
+            primitives.push(json!({
+                "attributes": attributes,
+                "indices": buffer.add_indices(&indices),
+                "mode": mode,
+            }));
+        }
+    }
+    if primitives.is_empty() {
+        primitives.push(json!({ "attributes": attributes, "mode": MODE_POINTS }));
+    }
+    json!({ "name": "skeleton", "primitives": primitives })
+}
+
+/// Mesh of an erosion path, as lines
+fn erosion_path_mesh(buffer: &mut GlbBuffer, erosion_path: &ErosionPath) -> Value {
+    let attributes = vertex_attributes(
+        buffer,
+        &erosion_path.get_vertices(),
+        &erosion_path.vertex_header_element(),
+        &erosion_path.vertex_payload_element(),
+    );
+    let lines: Vec<u32> = erosion_path
+        .get_edges()
+        .iter()
+        .flat_map(|edge| edge.map(|v| v as u32))
+        .collect();
+    let primitive = if lines.is_empty() {
+        json!({ "attributes": attributes, "mode": MODE_POINTS })
+    } else {
+        json!({
+            "attributes": attributes,
+            "indices": buffer.add_indices(&lines),
+            "mode": MODE_LINES,
+        })
+    };
+    json!({ "name": "erosion_path", "primitives": [primitive] })
+}
+
+fn write_chunk<W: Write>(out: &mut W, chunk_type: u32, data: &[u8]) -> Result<()> {
+    out.write_all(&(data.len() as u32).to_le_bytes())?;
+    out.write_all(&chunk_type.to_le_bytes())?;
+    out.write_all(data)?;
+    Ok(())
+}
+
+/// Writes meshes as a glb file, one node each
+fn write_glb(
+    file_path: &str,
+    buffer: GlbBuffer,
+    meshes: Vec<Value>,
+    comments: &[String],
+) -> Result<()> {
+    let GlbBuffer {
+        mut data,
+        buffer_views,
+        accessors,
+    } = buffer;
+    while !data.len().is_multiple_of(4) {
+        data.push(0);
+    }
+    let nodes: Vec<Value> = meshes
+        .iter()
+        .enumerate()
+        .map(|(i, mesh)| json!({ "mesh": i, "name": mesh["name"] }))
+        .collect();
+    let gltf = json!({
+        "asset": {
+            "version": "2.0",
+            "generator": format!("erosion_thickness {}", env!("CARGO_PKG_VERSION")),
+            "extras": { "comments": comments },
+        },
+        "scene": 0,
+        "scenes": [{ "nodes": (0..nodes.len()).collect::<Vec<usize>>() }],
+        "nodes": nodes,
+        "meshes": meshes,
+        "accessors": accessors,
+        "bufferViews": buffer_views,
+        "buffers": [{ "byteLength": data.len() }],
+    });
+    let mut json_data = serde_json::to_vec(&gltf)?;
+    while !json_data.len().is_multiple_of(4) {
+        json_data.push(b' ');
+    }
+
+    let length = 12 + 8 + json_data.len() + 8 + data.len();
+    let mut file = compression::create_writer(Path::new(file_path))?;
+    file.write_all(&GLB_MAGIC.to_le_bytes())?;
+    file.write_all(&2u32.to_le_bytes())?;
+    file.write_all(&(length as u32).to_le_bytes())?;
+    write_chunk(&mut file, CHUNK_JSON, &json_data)?;
+    write_chunk(&mut file, CHUNK_BIN, &data)?;
+    file.finish()
+}
+
+/// Writes a skeleton as binary glTF (`.glb`), with its erosion path if given
+///
+/// Faces are triangulated, vertex colors become `COLOR_0`, and other vertex properties
+/// custom float attributes (`_EROSION_THICKNESS`, `_RADIUS`, ...). The erosion path is
+/// a second mesh made of lines, with a `_BURNTIME` attribute. Ply comments are kept in
+/// the asset extras.
+pub fn export_to_glb(
+    skel: &Skeleton,
+    erosion_path: Option<&ErosionPath>,
+    file_path: &str,
+) -> Result<()> {
+    let mut buffer = GlbBuffer::new();
+    let mut meshes = vec![skeleton_mesh(&mut buffer, skel)];
+    if let Some(erosion_path) = erosion_path {
+        meshes.push(erosion_path_mesh(&mut buffer, erosion_path));
+    }
+    write_glb(file_path, buffer, meshes, skel.get_comments())
+}
+
+/// Writes an erosion path alone as binary glTF, see `export_to_glb`
+pub fn export_erosion_path_to_glb(erosion_path: &ErosionPath, file_path: &str) -> Result<()> {
+    let mut buffer = GlbBuffer::new();
+    let meshes = vec![erosion_path_mesh(&mut buffer, erosion_path)];
+    write_glb(file_path, buffer, meshes, erosion_path.get_comments())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::skeleton::io::import_from_ply_reader;
+
+    fn u32_at(bytes: &[u8], offset: usize) -> u32 {
+        u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap())
+    }
+
+    /// Json and binary chunks of a glb file, checking its header and chunk alignment
+    fn glb_chunks(bytes: &[u8]) -> (Value, &[u8]) {
+        assert_eq!(u32_at(bytes, 0), GLB_MAGIC);
+        assert_eq!(u32_at(bytes, 4), 2);
+        assert_eq!(u32_at(bytes, 8) as usize, bytes.len());
+
+        let json_len = u32_at(bytes, 12) as usize;
+        assert_eq!(json_len % 4, 0);
+        assert_eq!(u32_at(bytes, 16), CHUNK_JSON);
+        let gltf: Value = serde_json::from_slice(&bytes[20..20 + json_len]).unwrap();
+
+        let bin_start = 20 + json_len;
+        let bin_len = u32_at(bytes, bin_start) as usize;
+        assert_eq!(bin_len % 4, 0);
+        assert_eq!(u32_at(bytes, bin_start + 4), CHUNK_BIN);
+        assert_eq!(bin_start + 8 + bin_len, bytes.len());
+        (gltf, &bytes[bin_start + 8..])
+    }
+
+    /// Count of an accessor, checking that its buffer view fits it
+    fn accessor_count(gltf: &Value, accessor: &Value) -> usize {
+        let accessor = &gltf["accessors"][accessor.as_u64().unwrap() as usize];
+        let view = &gltf["bufferViews"][accessor["bufferView"].as_u64().unwrap() as usize];
+        let count = accessor["count"].as_u64().unwrap();
+        let element_size = match (
+            accessor["type"].as_str().unwrap(),
+            accessor["componentType"].as_u64().unwrap() as u32,
+        ) {
+            ("SCALAR", FLOAT) | ("SCALAR", UNSIGNED_INT) => 4,
+            ("VEC3", FLOAT) => 12,
+            ("VEC4", UNSIGNED_BYTE) => 4,
+            other => panic!("unexpected accessor {:?}", other),
+        };
+        assert_eq!(view["byteLength"].as_u64().unwrap(), count * element_size);
+        assert_eq!(view["byteOffset"].as_u64().unwrap() % 4, 0);
+        count as usize
+    }
+
+    /// Checks that each primitive has `nb_vertices` values per attribute, returns the mode
+    /// and index count of each one
+    fn primitives(gltf: &Value, mesh: usize, nb_vertices: usize) -> Vec<(u64, Option<usize>)> {
+        let mut primitives = Vec::new();
+        for primitive in gltf["meshes"][mesh]["primitives"].as_array().unwrap() {
+            for (_, accessor) in primitive["attributes"].as_object().unwrap() {
+                assert_eq!(accessor_count(gltf, accessor), nb_vertices);
+            }
+            let indices = primitive.get("indices").map(|a| accessor_count(gltf, a));
+            primitives.push((primitive["mode"].as_u64().unwrap(), indices));
+        }
+        primitives
+    }
+
+    #[test]
+    fn glb_layout() {
+        let ply = "ply\nformat ascii 1.0\ncomment made by hand\nelement vertex 5\n\
+                   property float x\nproperty float y\nproperty float z\nproperty float radius\n\
+                   element face 1\nproperty list uchar int vertex_indices\n\
+                   element edge 1\nproperty int vertex1\nproperty int vertex2\nend_header\n\
+                   0 0 0 0.5\n1 0 0 0.25\n1 1 0 0.125\n0 1 0 1\n2 2 2 0\n4 0 1 2 3\n3 4\n";
+        let mut skel = import_from_ply_reader(&mut ply.as_bytes()).unwrap();
+        skel.set_vertex_color_from_property_f32("radius").unwrap();
+        let mut erosion_path = ErosionPath::new();
+        for i in 0..3 {
+            erosion_path.add_vertex(Vector3::new(i as f32, 0.0, 0.0), i as f32);
+        }
+        erosion_path.add_edge([1, 0]);
+        erosion_path.add_edge([2, 1]);
+
+        let dir = std::env::temp_dir().join(format!("gltf_layout_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let file_path = dir.join("skeleton.glb");
+        export_to_glb(&skel, Some(&erosion_path), file_path.to_str().unwrap()).unwrap();
+        let bytes = std::fs::read(&file_path).unwrap();
+        let (gltf, bin) = glb_chunks(&bytes);
+
+        assert_eq!(
+            gltf["buffers"][0]["byteLength"].as_u64().unwrap() as usize,
+            bin.len()
+        );
+        for view in gltf["bufferViews"].as_array().unwrap() {
+            let end = view["byteOffset"].as_u64().unwrap() + view["byteLength"].as_u64().unwrap();
+            assert!(end as usize <= bin.len());
+        }
+        assert_eq!(gltf["asset"]["extras"]["comments"], json!(["made by hand"]));
+        assert_eq!(gltf["scenes"][0]["nodes"], json!([0, 1]));
+
+        // the quad as two triangles, the edge of no face as a line
+        assert_eq!(
+            primitives(&gltf, 0, 5),
+            [
+                (MODE_TRIANGLES as u64, Some(6)),
+                (MODE_LINES as u64, Some(2))
+            ]
+        );
+        let attributes = &gltf["meshes"][0]["primitives"][0]["attributes"];
+        for name in ["POSITION", "COLOR_0", "_RADIUS"] {
+            assert!(attributes.get(name).is_some(), "{}", name);
+        }
+        let position = &gltf["accessors"][attributes["POSITION"].as_u64().unwrap() as usize];
+        assert_eq!(position["min"], json!([0.0, 0.0, 0.0]));
+        assert_eq!(position["max"], json!([2.0, 2.0, 2.0]));
+        let triangles = &gltf["accessors"][gltf["meshes"][0]["primitives"][0]["indices"]
+            .as_u64()
+            .unwrap() as usize];
+        let view = &gltf["bufferViews"][triangles["bufferView"].as_u64().unwrap() as usize];
+        let offset = view["byteOffset"].as_u64().unwrap() as usize;
+        let indices: Vec<u32> = (0..6).map(|i| u32_at(bin, offset + 4 * i)).collect();
+        assert_eq!(indices, [0, 1, 2, 0, 2, 3]);
+
+        assert_eq!(primitives(&gltf, 1, 3), [(MODE_LINES as u64, Some(4))]);
+        assert!(gltf["meshes"][1]["primitives"][0]["attributes"]
+            .get("_BURNTIME")
+            .is_some());
+
+        // points only, without indices
+        let file_path = dir.join("erosion_path.glb");
+        let mut erosion_path = ErosionPath::new();
+        erosion_path.add_vertex(Vector3::new(1.0, 2.0, 3.0), 0.0);
+        export_erosion_path_to_glb(&erosion_path, file_path.to_str().unwrap()).unwrap();
+        let bytes = std::fs::read(&file_path).unwrap();
+        let (gltf, _) = glb_chunks(&bytes);
+        assert_eq!(primitives(&gltf, 0, 1), [(MODE_POINTS as u64, None)]);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,244 @@
+use anyhow::Result;
+use serde_json::json;
+use std::io::Write;
+use std::path::Path;
+
+use super::compression;
+use super::erosion_path::ErosionPath;
+
+/// Graph file format
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum GraphFormat {
+    /// GraphML `.graphml`
+    GraphMl,
+    /// Graphviz `.dot` or `.gv`
+    Dot,
+    /// Node-link `.json`, as read by networkx `node_link_graph`
+    Json,
+}
+
+impl GraphFormat {
+    /// Format given by the file extension (before `.gz` or `.zst`), None if not a graph file
+    pub fn from_path(file_path: &Path) -> Option<GraphFormat> {
+        match compression::format_extension(file_path)? {
+            "graphml" => Some(GraphFormat::GraphMl),
+            "dot" | "gv" => Some(GraphFormat::Dot),
+            "json" => Some(GraphFormat::Json),
+            _ => None,
+        }
+    }
+}
+
+/// Node attributes of an erosion path
+struct Nodes {
+    positions: Vec<[f32; 3]>,
+    burntimes: Vec<f32>,
+    original: Vec<bool>,
+}
+
+impl Nodes {
+    fn new(erosion_path: &ErosionPath) -> Result<Self> {
+        Ok(Nodes {
+            positions: erosion_path
+                .get_vertices()
+                .iter()
+                .map(|pos| [pos[0], pos[1], pos[2]])
+                .collect(),
+            burntimes: erosion_path.get_property_f32("burntime")?,
+            original: erosion_path.get_original_flags(),
+        })
+    }
+
+    /// Burn time of a node, None if not finite, the attribute being left out
+    fn burntime(&self, i: usize) -> Option<f32> {
+        Some(self.burntimes[i]).filter(|time| time.is_finite())
+    }
+}
+
+fn write_graphml<W: Write>(out: &mut W, nodes: &Nodes, edges: &[[usize; 2]]) -> Result<()> {
+    writeln!(out, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+    writeln!(
+        out,
+        r#"<graphml xmlns="http://graphml.graphdrawing.org/xmlns">"#
+    )?;
+    for key in ["x", "y", "z", "burntime"] {
+        writeln!(
+            out,
+            r#"  <key id="{0}" for="node" attr.name="{0}" attr.type="float"/>"#,
+            key
+        )?;
+    }
+    writeln!(
+        out,
+        r#"  <key id="original" for="node" attr.name="original" attr.type="boolean"/>"#
+    )?;
+    writeln!(out, r#"  <graph id="erosion_path" edgedefault="directed">"#)?;
+    for i in 0..nodes.positions.len() {
+        let [x, y, z] = nodes.positions[i];
+        let burntime = nodes
+            .burntime(i)
+            .map(|time| format!(r#"<data key="burntime">{}</data>"#, time))
+            .unwrap_or_default();
+        writeln!(
+            out,
+            r#"    <node id="n{}"><data key="x">{}</data><data key="y">{}</data><data key="z">{}</data>{}<data key="original">{}</data></node>"#,
+            i, x, y, z, burntime, nodes.original[i]
+        )?;
+    }
+    for &[source, target] in edges.iter() {
+        writeln!(
+            out,
+            r#"    <edge source="n{}" target="n{}"/>"#,
+            source, target
+        )?;
+    }
+    writeln!(out, "  </graph>")?;
+    writeln!(out, "</graphml>")?;
+    Ok(())
+}
+
+fn write_dot<W: Write>(out: &mut W, nodes: &Nodes, edges: &[[usize; 2]]) -> Result<()> {
+    writeln!(out, "digraph erosion_path {{")?;
+    for i in 0..nodes.positions.len() {
+        let [x, y, z] = nodes.positions[i];
+        let burntime = nodes
+            .burntime(i)
+            .map(|time| format!("burntime=\"{}\", ", time))
+            .unwrap_or_default();
+        writeln!(
+            out,
+            "  {} [x=\"{}\", y=\"{}\", z=\"{}\", {}original={}];",
+            i, x, y, z, burntime, nodes.original[i]
+        )?;
+    }
+    for &[source, target] in edges.iter() {
+        writeln!(out, "  {} -> {};", source, target)?;
+    }
+    writeln!(out, "}}")?;
+    Ok(())
+}
+
+fn write_json<W: Write>(out: &mut W, nodes: &Nodes, edges: &[[usize; 2]]) -> Result<()> {
+    let json_nodes: Vec<_> = (0..nodes.positions.len())
+        .map(|i| {
+            let [x, y, z] = nodes.positions[i];
+            let mut node = json!({
+                "id": i,
+                "x": x,
+                "y": y,
+                "z": z,
+                "original": nodes.original[i],
+            });
+            if let Some(time) = nodes.burntime(i) {
+                node["burntime"] = json!(time);
+            }
+            node
+        })
+        .collect();
+    let links: Vec<_> = edges
+        .iter()
+        .map(|&[source, target]| json!({ "source": source, "target": target }))
+        .collect();
+    let graph = json!({
+        "directed": true,
+        "multigraph": false,
+        "graph": { "name": "erosion_path" },
+        "nodes": json_nodes,
+        "links": links,
+    });
+    serde_json::to_writer(&mut *out, &graph)?;
+    writeln!(out)?;
+    Ok(())
+}
+
+fn write_graph<W: Write>(
+    out: &mut W,
+    nodes: &Nodes,
+    edges: &[[usize; 2]],
+    format: GraphFormat,
+) -> Result<()> {
+    match format {
+        GraphFormat::GraphMl => write_graphml(out, nodes, edges),
+        GraphFormat::Dot => write_dot(out, nodes, edges),
+        GraphFormat::Json => write_json(out, nodes, edges),
+    }
+}
+
+/// Writes an erosion path as a directed graph
+///
+/// Nodes carry their position, burn time (left out when not finite) and whether they are
+/// skeleton vertices (`original`) or subdivision vertices. Each edge goes from a vertex to its prime
+/// neighbor, the vertex it was burnt from.
+pub fn export_erosion_path_to_graph(
+    erosion_path: &ErosionPath,
+    file_path: &str,
+    format: GraphFormat,
+) -> Result<()> {
+    let nodes = Nodes::new(erosion_path)?;
+    let edges = erosion_path.get_edges();
+    let mut file = compression::create_writer(Path::new(file_path))?;
+    write_graph(&mut file, &nodes, &edges, format)?;
+    file.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Two nodes, the second one never burnt, and an edge between them
+    fn nodes() -> Nodes {
+        Nodes {
+            positions: vec![[0.0, 1.0, 2.0], [0.5, 0.0, 0.0]],
+            burntimes: vec![0.25, f32::INFINITY],
+            original: vec![true, false],
+        }
+    }
+
+    fn written(nodes: &Nodes, format: GraphFormat) -> String {
+        let mut out = Vec::new();
+        write_graph(&mut out, nodes, &[[1, 0]], format).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn graphml() {
+        let text = written(&nodes(), GraphFormat::GraphMl);
+        assert!(text.contains(
+            r#"<node id="n0"><data key="x">0</data><data key="y">1</data><data key="z">2</data><data key="burntime">0.25</data><data key="original">true</data></node>"#
+        ));
+        assert!(text.contains(
+            r#"<node id="n1"><data key="x">0.5</data><data key="y">0</data><data key="z">0</data><data key="original">false</data></node>"#
+        ));
+        assert!(text.contains(r#"<edge source="n1" target="n0"/>"#));
+        assert!(text.contains(r#"edgedefault="directed""#));
+        assert!(text.trim_end().ends_with("</graphml>"));
+    }
+
+    #[test]
+    fn dot() {
+        let mut nodes = nodes();
+        nodes.burntimes[1] = f32::NAN;
+        assert_eq!(
+            written(&nodes, GraphFormat::Dot),
+            "digraph erosion_path {\n  \
+             0 [x=\"0\", y=\"1\", z=\"2\", burntime=\"0.25\", original=true];\n  \
+             1 [x=\"0.5\", y=\"0\", z=\"0\", original=false];\n  \
+             1 -> 0;\n}\n"
+        );
+    }
+
+    #[test]
+    fn node_link_json() {
+        let graph: serde_json::Value =
+            serde_json::from_str(&written(&nodes(), GraphFormat::Json)).unwrap();
+        assert_eq!(graph["directed"], json!(true));
+        assert_eq!(
+            graph["nodes"],
+            json!([
+                { "id": 0, "x": 0.0, "y": 1.0, "z": 2.0, "burntime": 0.25, "original": true },
+                { "id": 1, "x": 0.5, "y": 0.0, "z": 0.0, "original": false },
+            ])
+        );
+        assert_eq!(graph["links"], json!([{ "source": 1, "target": 0 }]));
+    }
+}
@@ -1,89 +1,209 @@
 use anyhow::Result;
 use std::collections::HashMap;
-use std::fs::File;
+use std::io::{BufRead, Write};
+use std::path::Path;
 
 use nalgebra::base::*;
 
 use ply_rs::parser::Parser;
-use ply_rs::ply::{Addable, DefaultElement, Encoding, Ply, Property};
+use ply_rs::ply::{
+    Addable, DefaultElement, Encoding, Ply, Property, PropertyAccess, PropertyType, ScalarType,
+};
 use ply_rs::writer::Writer;
 
+use super::components::{format_option, ComponentStats};
+use super::compression;
 use super::erosion_path::ErosionPath;
 use super::skeleton::Skeleton;
+use super::subdivided_graph::SubdividedGraph;
 
-pub fn import_from_ply(file_path: &str) -> Result<Skeleton> {
-    let mut f = std::fs::File::open(file_path).unwrap();
+mod ma;
+mod obj_off;
 
-    let p = Parser::<DefaultElement>::new();
-    let ply = p.read_ply(&mut f)?;
+pub use ma::{import_from_ma_reader, write_ma};
+pub use obj_off::{
+    export_to_mesh, import_from_mesh, import_from_obj_reader, import_from_off_reader, read_radii,
+    MeshFormat, RadiusConvention,
+};
 
-    let mut skel = Skeleton::new();
+const GENERATOR_COMMENT: &str =
+    "Erosion thickness generated with https://github.com/Ibujah/erosion-thickness";
 
-    // load vertices
-    if !ply.payload.contains_key("vertex") {
-        return Err(anyhow::Error::msg("No vertex element in file"));
-    }
-    for v in ply.payload["vertex"].iter() {
-        let mut x = None;
-        let mut y = None;
-        let mut z = None;
-        let mut radius = None;
-        let mut properties = HashMap::new();
-
-        for (key, prop) in v.into_iter() {
-            match (key.as_ref(), prop) {
-                ("x", Property::Float(val)) => x = Some(val),
-                ("y", Property::Float(val)) => y = Some(val),
-                ("z", Property::Float(val)) => z = Some(val),
-                ("radius", Property::Float(val)) => radius = Some(val),
-                (k, p) => {
-                    properties.insert(k.to_string(), p.clone());
-                    ()
-                }
-            }
+/// Element record, filled property by property by the ply parser
+struct PlyRecord {
+    properties: HashMap<String, Property>,
+}
+
+impl PropertyAccess for PlyRecord {
+    fn new() -> Self {
+        PlyRecord {
+            properties: HashMap::new(),
         }
-        let x = *x.ok_or(anyhow::Error::msg("No x property in vertex"))?;
-        let y = *y.ok_or(anyhow::Error::msg("No y property in vertex"))?;
-        let z = *z.ok_or(anyhow::Error::msg("No z property in vertex"))?;
-        let radius = *radius.ok_or(anyhow::Error::msg("No radius property in vertex"))?;
-        skel.add_vertex(Vector3::new(x, y, z), radius, properties);
     }
 
-    // load faces
-    if !ply.payload.contains_key("face") {
+    fn set_property(&mut self, property_name: String, property: Property) {
+        self.properties.insert(property_name, property);
+    }
+}
+
+fn property_to_f32(prop: Property) -> Option<f32> {
+    match prop {
+        Property::Float(val) => Some(val),
+        Property::Double(val) => Some(val as f32),
+        _ => None,
+    }
+}
+
+fn property_to_indices(prop: Property) -> Result<Vec<usize>> {
+    let indices: Vec<i64> = match prop {
+        Property::ListChar(val) => val.into_iter().map(i64::from).collect(),
+        Property::ListUChar(val) => val.into_iter().map(i64::from).collect(),
+        Property::ListShort(val) => val.into_iter().map(i64::from).collect(),
+        Property::ListUShort(val) => val.into_iter().map(i64::from).collect(),
+        Property::ListInt(val) => val.into_iter().map(i64::from).collect(),
+        Property::ListUInt(val) => val.into_iter().map(i64::from).collect(),
+        _ => {
+            return Err(anyhow::Error::msg(
+                "Face vertex indices are not an integer list",
+            ))
+        }
+    };
+    indices
+        .into_iter()
+        .map(usize::try_from)
+        .collect::<Result<Vec<usize>, _>>()
+        .map_err(|_| anyhow::Error::msg("Negative vertex index in face"))
+}
+
+fn add_ply_vertex(skel: &mut Skeleton, mut record: PlyRecord) -> Result<()> {
+    let mut coord = |name: &str| {
+        record
+            .properties
+            .remove(name)
+            .and_then(property_to_f32)
+            .ok_or(anyhow::Error::msg(format!(
+                "No {} property in vertex",
+                name
+            )))
+    };
+    let x = coord("x")?;
+    let y = coord("y")?;
+    let z = coord("z")?;
+    let radius = coord("radius")?;
+    skel.add_vertex(Vector3::new(x, y, z), radius, record.properties);
+    Ok(())
+}
+
+fn add_ply_face(skel: &mut Skeleton, mut record: PlyRecord) -> Result<()> {
+    let list_vertices = record
+        .properties
+        .remove("vertex_index")
+        .or_else(|| record.properties.remove("vertex_indices"))
+        .ok_or(anyhow::Error::msg("No vertex_index property in face"))?;
+    skel.add_face(property_to_indices(list_vertices)?, record.properties);
+    Ok(())
+}
+
+fn add_ply_edge(skel: &mut Skeleton, mut record: PlyRecord) -> Result<()> {
+    let mut index = |name: &str| -> Result<usize> {
+        let prop = record
+            .properties
+            .remove(name)
+            .ok_or(anyhow::Error::msg(format!("No {} property in edge", name)))?;
+        let index = match prop {
+            Property::Char(val) => i64::from(val),
+            Property::UChar(val) => i64::from(val),
+            Property::Short(val) => i64::from(val),
+            Property::UShort(val) => i64::from(val),
+            Property::Int(val) => i64::from(val),
+            Property::UInt(val) => i64::from(val),
+            _ => return Err(anyhow::Error::msg("Edge vertex index is not an integer")),
+        };
+        usize::try_from(index).map_err(|_| anyhow::Error::msg("Negative vertex index in edge"))
+    };
+    let v1 = index("vertex1")?;
+    let v2 = index("vertex2")?;
+    skel.add_edge([v1, v2]);
+    Ok(())
+}
+
+/// Reads a ply skeleton from a stream, ascii or binary
+///
+/// Elements are parsed one by one straight into the skeleton: vertices, faces, and edges
+/// (belonging to no face), other elements being skipped.
+pub fn import_from_ply_reader<R: BufRead>(reader: &mut R) -> Result<Skeleton> {
+    let parser = Parser::<PlyRecord>::new();
+    let header = parser.read_header(reader)?;
+
+    if !header.elements.contains_key("vertex") {
+        return Err(anyhow::Error::msg("No vertex element in file"));
+    }
+    if !header.elements.contains_key("face") {
         return Err(anyhow::Error::msg("No face element in file"));
     }
-    for f in ply.payload["face"].iter() {
-        let mut list_vertices = None;
-        let mut properties = HashMap::new();
 
-        for (key, prop) in f.into_iter() {
-            match (key.as_ref(), prop) {
-                ("vertex_index", Property::ListInt(val)) => {
-                    list_vertices = Some(val.iter().map(|&v| usize::try_from(v).unwrap()).collect())
+    let mut skel = Skeleton::new();
+    for comment in header.comments.iter() {
+        if comment != GENERATOR_COMMENT {
+            skel.add_comment(comment);
+        }
+    }
+    let mut line = String::new();
+    for (name, element_def) in header.elements.iter() {
+        for _ in 0..element_def.count {
+            let record = match header.encoding {
+                Encoding::Ascii => {
+                    line.clear();
+                    if reader.read_line(&mut line)? == 0 {
+                        return Err(anyhow::Error::msg(format!(
+                            "Unexpected end of file in {} element",
+                            name
+                        )));
+                    }
+                    parser.read_ascii_element(&line, element_def)?
                 }
-                (k, p) => {
-                    properties.insert(k.to_string(), p.clone());
-                    ()
+                Encoding::BinaryBigEndian => parser.read_big_endian_element(reader, element_def)?,
+                Encoding::BinaryLittleEndian => {
+                    parser.read_little_endian_element(reader, element_def)?
                 }
+            };
+            match name.as_str() {
+                "vertex" => add_ply_vertex(&mut skel, record)?,
+                "face" => add_ply_face(&mut skel, record)?,
+                "edge" => add_ply_edge(&mut skel, record)?,
+                _ => (),
             }
         }
-
-        let list_vertices: Vec<usize> =
-            list_vertices.ok_or(anyhow::Error::msg("No vertex_index property in face"))?;
-
-        skel.add_face(list_vertices, properties);
     }
 
     Ok(skel)
 }
 
+/// Reads a ply skeleton (`-` for standard input), gzip or zstd compressed files being
+/// detected from their content
+pub fn import_from_ply(file_path: &str) -> Result<Skeleton> {
+    let mut reader = compression::open_reader(Path::new(file_path))?;
+    import_from_ply_reader(&mut reader)
+}
+
+/// Writes a skeleton as ascii ply (`-` for standard output), compressed if the file name
+/// ends with `.gz` or `.zst`
 pub fn export_to_ply(skel: &Skeleton, file_path: &str) -> Result<()> {
+    export_to_ply_with_encoding(skel, file_path, Encoding::Ascii)
+}
+
+/// Writes a skeleton as ply with the given encoding, see `export_to_ply`
+pub fn export_to_ply_with_encoding(
+    skel: &Skeleton,
+    file_path: &str,
+    encoding: Encoding,
+) -> Result<()> {
     let mut ply = Ply::<DefaultElement>::new();
-    ply.header.encoding = Encoding::Ascii;
-    ply.header.comments.push(
-        "Erosion thickness generated with https://github.com/Ibujah/erosion-thickness".to_string(),
-    );
+    ply.header.encoding = encoding;
+    ply.header.comments.push(GENERATOR_COMMENT.to_string());
+    ply.header
+        .comments
+        .extend(skel.get_comments().iter().cloned());
 
     ply.header.elements.add(skel.vertex_header_element());
     ply.header.elements.add(skel.face_header_element());
@@ -93,20 +213,122 @@ pub fn export_to_ply(skel: &Skeleton, file_path: &str) -> Result<()> {
     ply.payload
         .insert("face".to_string(), skel.face_payload_element());
 
+    // edges of no face, if any
+    let edges = skel.edge_payload_element();
+    if !edges.is_empty() {
+        ply.header.elements.add(skel.edge_header_element());
+        ply.payload.insert("edge".to_string(), edges);
+    }
+
     ply.make_consistent().unwrap();
 
-    let mut file = File::create(file_path)?;
-    let w = Writer::new();
-    w.write_ply(&mut file, &mut ply).unwrap();
+    let mut file = compression::create_writer(Path::new(file_path))?;
+    write_ply_elements(&mut file, &ply)?;
+    file.finish()
+}
+
+/// Writes a binary property, scalar or list
+///
+/// The ply writer stores the element count instead of the list length in binary lists,
+/// hence this writer.
+fn write_binary_property<W: Write>(
+    out: &mut W,
+    prop: &Property,
+    index_type: Option<&ScalarType>,
+    big_endian: bool,
+) -> Result<()> {
+    macro_rules! write_values {
+        ($values:expr) => {
+            for val in $values {
+                if big_endian {
+                    out.write_all(&val.to_be_bytes())?;
+                } else {
+                    out.write_all(&val.to_le_bytes())?;
+                }
+            }
+        };
+    }
+    macro_rules! write_list {
+        ($list:expr) => {{
+            let len = $list.len();
+            match index_type {
+                Some(ScalarType::Char) | Some(ScalarType::UChar) => write_values!([len as u8]),
+                Some(ScalarType::Short) | Some(ScalarType::UShort) => write_values!([len as u16]),
+                _ => write_values!([len as u32]),
+            }
+            write_values!($list.iter());
+        }};
+    }
+    match prop {
+        Property::Char(val) => write_values!([val]),
+        Property::UChar(val) => write_values!([val]),
+        Property::Short(val) => write_values!([val]),
+        Property::UShort(val) => write_values!([val]),
+        Property::Int(val) => write_values!([val]),
+        Property::UInt(val) => write_values!([val]),
+        Property::Float(val) => write_values!([val]),
+        Property::Double(val) => write_values!([val]),
+        Property::ListChar(list) => write_list!(list),
+        Property::ListUChar(list) => write_list!(list),
+        Property::ListShort(list) => write_list!(list),
+        Property::ListUShort(list) => write_list!(list),
+        Property::ListInt(list) => write_list!(list),
+        Property::ListUInt(list) => write_list!(list),
+        Property::ListFloat(list) => write_list!(list),
+        Property::ListDouble(list) => write_list!(list),
+    }
+    Ok(())
+}
+
+/// Writes header and elements of a consistent ply, in its encoding
+fn write_ply_elements<W: Write>(out: &mut W, ply: &Ply<DefaultElement>) -> Result<()> {
+    let w = Writer::<DefaultElement>::new();
+    w.write_header(out, &ply.header)?;
+    for (name, element_def) in ply.header.elements.iter() {
+        let elements = match ply.payload.get(name) {
+            Some(elements) => elements,
+            None => continue,
+        };
+        for element in elements.iter() {
+            if ply.header.encoding == Encoding::Ascii {
+                w.write_ascii_element(out, element, element_def)?;
+                continue;
+            }
+            let big_endian = ply.header.encoding == Encoding::BinaryBigEndian;
+            for (key, prop_def) in element_def.properties.iter() {
+                let prop = element.get(key).ok_or(anyhow::Error::msg(format!(
+                    "No {} property in {} element",
+                    key, name
+                )))?;
+                let index_type = match &prop_def.data_type {
+                    PropertyType::List(index_type, _) => Some(index_type),
+                    PropertyType::Scalar(_) => None,
+                };
+                write_binary_property(out, prop, index_type, big_endian)?;
+            }
+        }
+    }
     Ok(())
 }
 
+/// Writes an erosion path as ascii ply (`-` for standard output), compressed if the file
+/// name ends with `.gz` or `.zst`
 pub fn export_erosion_path_to_ply(erosion_path: &ErosionPath, file_path: &str) -> Result<()> {
+    export_erosion_path_to_ply_with_encoding(erosion_path, file_path, Encoding::Ascii)
+}
+
+/// Writes an erosion path as ply with the given encoding
+pub fn export_erosion_path_to_ply_with_encoding(
+    erosion_path: &ErosionPath,
+    file_path: &str,
+    encoding: Encoding,
+) -> Result<()> {
     let mut ply = Ply::<DefaultElement>::new();
-    ply.header.encoding = Encoding::Ascii;
-    ply.header.comments.push(
-        "Erosion thickness generated with https://github.com/Ibujah/erosion-thickness".to_string(),
-    );
+    ply.header.encoding = encoding;
+    ply.header.comments.push(GENERATOR_COMMENT.to_string());
+    ply.header
+        .comments
+        .extend(erosion_path.get_comments().iter().cloned());
 
     ply.header
         .elements
@@ -120,8 +342,344 @@ pub fn export_erosion_path_to_ply(erosion_path: &ErosionPath, file_path: &str) -
 
     ply.make_consistent().unwrap();
 
-    let mut file = File::create(file_path)?;
-    let w = Writer::new();
-    w.write_ply(&mut file, &mut ply).unwrap();
-    Ok(())
+    let mut file = compression::create_writer(Path::new(file_path))?;
+    write_ply_elements(&mut file, &ply)?;
+    file.finish()
+}
+
+/// Writes a subdivided graph as ascii ply, see `export_subdivided_graph_to_ply_with_encoding`
+pub fn export_subdivided_graph_to_ply(graph: &SubdividedGraph, file_path: &str) -> Result<()> {
+    export_subdivided_graph_to_ply_with_encoding(graph, file_path, Encoding::Ascii)
+}
+
+/// Writes a subdivided graph as ply: vertices with radius, burn time, erosion thickness,
+/// boundary, original and prime neighbor properties, edges and subdivided faces
+pub fn export_subdivided_graph_to_ply_with_encoding(
+    graph: &SubdividedGraph,
+    file_path: &str,
+    encoding: Encoding,
+) -> Result<()> {
+    let mut ply = Ply::<DefaultElement>::new();
+    ply.header.encoding = encoding;
+    ply.header.comments.push(GENERATOR_COMMENT.to_string());
+    ply.header
+        .comments
+        .extend(graph.get_comments().iter().cloned());
+
+    ply.header.elements.add(graph.vertex_header_element());
+    ply.header.elements.add(graph.edge_header_element());
+    ply.header.elements.add(graph.face_header_element());
+
+    ply.payload
+        .insert("vertex".to_string(), graph.vertex_payload_element());
+    ply.payload
+        .insert("edge".to_string(), graph.edge_payload_element());
+    ply.payload
+        .insert("face".to_string(), graph.face_payload_element());
+
+    ply.make_consistent().unwrap();
+
+    let mut file = compression::create_writer(Path::new(file_path))?;
+    write_ply_elements(&mut file, &ply)?;
+    file.finish()
+}
+
+pub fn export_component_stats_to_csv(stats: &[ComponentStats], file_path: &str) -> Result<()> {
+    let mut file = compression::create_writer(Path::new(file_path))?;
+    writeln!(
+        file,
+        "component,nb_vertices,nb_faces,boundary_length,nb_infinite,et_min,et_max,et_mean,closed"
+    )?;
+    for stat in stats.iter() {
+        writeln!(
+            file,
+            "{},{},{},{},{},{},{},{},{}",
+            stat.component,
+            stat.nb_vertices,
+            stat.nb_faces,
+            stat.boundary_length,
+            stat.nb_infinite,
+            format_option(stat.et_min),
+            format_option(stat.et_max),
+            format_option(stat.et_mean),
+            stat.closed
+        )?;
+    }
+    file.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::skeleton::compression::Compression;
+    use ply_rs::ply::ElementDef;
+
+    /// Ascii ply with comments, properties of every type, a quad, an element which is not
+    /// part of skeletons and an edge of no face
+    const PLY_SKELETON: &str = "ply\nformat ascii 1.0\ncomment made by hand\ncomment units: mm\n\
+        element vertex 5\nproperty float x\nproperty float y\nproperty float z\n\
+        property float radius\nproperty uchar red\nproperty int label\nproperty double weight\n\
+        property char c\nproperty short s\nproperty ushort us\nproperty uint ui\n\
+        property list uchar float normal\nelement face 2\nproperty list uchar int vertex_index\n\
+        property list ushort uint ids\nproperty float area\nelement material 1\n\
+        property uchar kind\nelement edge 1\nproperty int vertex1\nproperty int vertex2\n\
+        end_header\n\
+        0 0 0 0.5 255 -1 0.25 -3 -300 60000 4000000000 3 0 0 1\n\
+        1 0 0 0.25 0 2 1e-10 4 5 6 7 0\n\
+        0 1 0 0.125 10 3 2.5 -1 1 1 1 2 0.5 0.5\n\
+        1 1 1 1 20 4 -2 0 0 0 0 1 1\n\
+        2 2 2 0.1 30 5 0 0 0 0 0 0\n\
+        3 0 1 2 2 7 8 0.5\n\
+        4 1 3 2 0 0 1.5\n\
+        7\n\
+        3 4\n";
+
+    fn property_map(element: &DefaultElement) -> HashMap<String, Property> {
+        element
+            .iter()
+            .map(|(key, prop)| (key.clone(), prop.clone()))
+            .collect()
+    }
+
+    fn property_types(element_def: &ElementDef) -> HashMap<String, PropertyType> {
+        element_def
+            .properties
+            .iter()
+            .map(|(key, prop_def)| (key.clone(), prop_def.data_type.clone()))
+            .collect()
+    }
+
+    fn faceless_edges(skel: &Skeleton) -> Vec<[usize; 2]> {
+        skel.get_faceless_edges()
+            .iter()
+            .map(|&e| skel.get_edges()[e])
+            .collect()
+    }
+
+    /// Checks that two skeletons write the same ply elements
+    fn assert_same_skeleton(skel: &Skeleton, other: &Skeleton) {
+        assert_eq!(skel.get_comments(), other.get_comments());
+        assert_eq!(
+            property_types(&skel.vertex_header_element()),
+            property_types(&other.vertex_header_element())
+        );
+        assert_eq!(
+            property_types(&skel.face_header_element()),
+            property_types(&other.face_header_element())
+        );
+        let maps = |elements: Vec<DefaultElement>| -> Vec<HashMap<String, Property>> {
+            elements.iter().map(property_map).collect()
+        };
+        assert_eq!(
+            maps(skel.vertex_payload_element()),
+            maps(other.vertex_payload_element())
+        );
+        assert_eq!(
+            maps(skel.face_payload_element()),
+            maps(other.face_payload_element())
+        );
+        assert_eq!(faceless_edges(skel), faceless_edges(other));
+    }
+
+    #[test]
+    fn round_trips() {
+        let skel = import_from_ply_reader(&mut PLY_SKELETON.as_bytes()).unwrap();
+        assert_eq!(skel.get_vertices().len(), 5);
+        assert_eq!(
+            skel.get_face_vertices(),
+            &vec![vec![0, 1, 2], vec![1, 3, 2, 0]]
+        );
+        assert_eq!(faceless_edges(&skel), vec![[3, 4]]);
+
+        let dir = std::env::temp_dir().join(format!("io_round_trips_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        for (encoding, format) in [
+            (Encoding::Ascii, "ascii"),
+            (Encoding::BinaryLittleEndian, "binary_little_endian"),
+            (Encoding::BinaryBigEndian, "binary_big_endian"),
+        ] {
+            for (extension, compression) in [
+                ("ply", Compression::None),
+                ("ply.gz", Compression::Gzip),
+                ("ply.zst", Compression::Zstd),
+            ] {
+                let file_path = dir.join(format!("{}.{}", format, extension));
+                let file_path = file_path.to_str().unwrap();
+                export_to_ply_with_encoding(&skel, file_path, encoding).unwrap();
+
+                let bytes = std::fs::read(file_path).unwrap();
+                assert_eq!(
+                    Compression::from_magic(&bytes),
+                    compression,
+                    "{}",
+                    file_path
+                );
+                if compression == Compression::None {
+                    let format_line = format!("\nformat {} 1.0\n", format);
+                    assert!(bytes
+                        .windows(format_line.len())
+                        .any(|w| w == format_line.as_bytes()));
+                }
+                // compression detected from the content, not the extension
+                let renamed = dir.join(format!("{}_{}", format, extension.replace('.', "_")));
+                std::fs::rename(file_path, &renamed).unwrap();
+                let read = import_from_ply(renamed.to_str().unwrap()).unwrap();
+                assert_same_skeleton(&skel, &read);
+            }
+        }
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn binary_lists_store_their_length() {
+        let list = Property::ListShort(vec![1, -2]);
+        let mut out = Vec::new();
+        write_binary_property(&mut out, &list, Some(&ScalarType::UShort), true).unwrap();
+        assert_eq!(out, [0, 2, 0, 1, 0xff, 0xfe]);
+        let mut out = Vec::new();
+        write_binary_property(&mut out, &list, Some(&ScalarType::UChar), false).unwrap();
+        assert_eq!(out, [2, 1, 0, 0xfe, 0xff]);
+        let mut out = Vec::new();
+        write_binary_property(&mut out, &list, Some(&ScalarType::Int), false).unwrap();
+        assert_eq!(out, [2, 0, 0, 0, 1, 0, 0xfe, 0xff]);
+        let mut out = Vec::new();
+        write_binary_property(&mut out, &Property::Double(1.0), None, true).unwrap();
+        assert_eq!(out, 1.0f64.to_be_bytes());
+    }
+
+    /// Ascii ply of a triangle and an edge of no face, edge indices being of type `ptype`
+    fn ply_with_edge(ptype: &str, edge: &str) -> String {
+        format!(
+            "ply\nformat ascii 1.0\nelement vertex 4\nproperty float x\nproperty float y\n\
+             property float z\nproperty float radius\nelement face 1\n\
+             property list uchar int vertex_indices\nelement edge 1\nproperty {t} vertex1\n\
+             property {t} vertex2\nend_header\n\
+             0 0 0 0.1\n1 0 0 0.1\n0 1 0 0.1\n1 1 0 0.1\n3 0 1 2\n{}\n",
+            edge,
+            t = ptype
+        )
+    }
+
+    #[test]
+    fn edge_indices_of_any_integer_type() {
+        for ptype in ["char", "uchar", "short", "ushort", "int", "uint"] {
+            let ply = ply_with_edge(ptype, "2 3");
+            let skel = import_from_ply_reader(&mut ply.as_bytes()).unwrap();
+            let faceless: Vec<[usize; 2]> = skel
+                .get_faceless_edges()
+                .iter()
+                .map(|&e| skel.get_edges()[e])
+                .collect();
+            assert_eq!(faceless, vec![[2, 3]], "{}", ptype);
+        }
+        let ply = ply_with_edge("char", "2 -3");
+        assert!(import_from_ply_reader(&mut ply.as_bytes()).is_err());
+        let ply = ply_with_edge("float", "2 3");
+        assert!(import_from_ply_reader(&mut ply.as_bytes()).is_err());
+    }
+
+    /// Checks that streaming `bytes` into a skeleton keeps what the ply-rs parser reads
+    fn assert_same_as_ply_rs(bytes: &[u8]) {
+        let ply = Parser::<DefaultElement>::new()
+            .read_ply(&mut &bytes[..])
+            .unwrap();
+        let skel = import_from_ply_reader(&mut &bytes[..]).unwrap();
+
+        let comments: Vec<String> = ply
+            .header
+            .comments
+            .iter()
+            .filter(|&comment| comment != GENERATOR_COMMENT)
+            .cloned()
+            .collect();
+        assert_eq!(skel.get_comments(), &comments);
+
+        assert_eq!(
+            property_types(&skel.vertex_header_element()),
+            property_types(&ply.header.elements["vertex"])
+        );
+        let vertices: Vec<HashMap<String, Property>> =
+            ply.payload["vertex"].iter().map(property_map).collect();
+        let skel_vertices: Vec<HashMap<String, Property>> = skel
+            .vertex_payload_element()
+            .iter()
+            .map(property_map)
+            .collect();
+        assert_eq!(skel_vertices, vertices);
+
+        // list properties of faces keep their value type, lengths being written as uchar
+        let face_types = |element_def: &ElementDef| -> HashMap<String, PropertyType> {
+            property_types(element_def)
+                .into_iter()
+                .filter(|(key, _)| key != "vertex_index" && key != "vertex_indices")
+                .map(|(key, ptype)| match ptype {
+                    PropertyType::List(_, value_type) => {
+                        (key, PropertyType::List(ScalarType::UChar, value_type))
+                    }
+                    ptype => (key, ptype),
+                })
+                .collect()
+        };
+        assert_eq!(
+            face_types(&skel.face_header_element()),
+            face_types(&ply.header.elements["face"])
+        );
+        let skel_faces = skel.face_payload_element();
+        assert_eq!(skel_faces.len(), ply.payload["face"].len());
+        for (i, face) in ply.payload["face"].iter().enumerate() {
+            let mut props = property_map(face);
+            let indices = props
+                .remove("vertex_index")
+                .or_else(|| props.remove("vertex_indices"))
+                .unwrap();
+            assert_eq!(
+                &property_to_indices(indices).unwrap(),
+                &skel.get_face_vertices()[i]
+            );
+            let mut skel_props = property_map(&skel_faces[i]);
+            skel_props.remove("vertex_indices");
+            assert_eq!(skel_props, props);
+        }
+
+        let edges: Vec<[usize; 2]> = ply
+            .payload
+            .get("edge")
+            .map(|edges| {
+                edges
+                    .iter()
+                    .map(|edge| match (&edge["vertex1"], &edge["vertex2"]) {
+                        (Property::Int(v1), Property::Int(v2)) => [*v1 as usize, *v2 as usize],
+                        _ => panic!("edge indices are not int"),
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        assert_eq!(faceless_edges(&skel), edges);
+    }
+
+    #[test]
+    fn streaming_reader_matches_ply_rs() {
+        assert_same_as_ply_rs(PLY_SKELETON.as_bytes());
+
+        let skel = import_from_ply_reader(&mut PLY_SKELETON.as_bytes()).unwrap();
+        let dir = std::env::temp_dir().join(format!("io_streaming_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        for encoding in [Encoding::BinaryLittleEndian, Encoding::BinaryBigEndian] {
+            let file_path = dir.join(format!("{:?}.ply", encoding));
+            let file_path = file_path.to_str().unwrap();
+            export_to_ply_with_encoding(&skel, file_path, encoding).unwrap();
+            assert_same_as_ply_rs(&std::fs::read(file_path).unwrap());
+        }
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_same_as_ply_rs(
+            &std::fs::read(concat!(
+                env!("CARGO_MANIFEST_DIR"),
+                "/resources/skeleton.ply"
+            ))
+            .unwrap(),
+        );
+    }
 }
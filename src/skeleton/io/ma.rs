@@ -0,0 +1,201 @@
+use anyhow::Result;
+use std::collections::HashMap;
+use std::io::{BufRead, Write};
+
+use nalgebra::base::*;
+
+use crate::skeleton::skeleton::Skeleton;
+
+fn parse_values<T: std::str::FromStr>(tokens: &[&str], nb: usize, kind: &str) -> Result<Vec<T>> {
+    if tokens.len() < nb {
+        return Err(anyhow::Error::msg(format!(
+            "Invalid ma {} line, {} values expected",
+            kind, nb
+        )));
+    }
+    tokens[..nb]
+        .iter()
+        .map(|token| {
+            token
+                .parse::<T>()
+                .map_err(|_| anyhow::Error::msg(format!("Invalid value {} in ma {}", token, kind)))
+        })
+        .collect()
+}
+
+fn check_indices(indices: &[usize], nb_vertices: usize) -> Result<()> {
+    match indices.iter().find(|&&i| i >= nb_vertices) {
+        Some(index) => Err(anyhow::Error::msg(format!(
+            "Vertex {} out of range in ma",
+            index
+        ))),
+        None => Ok(()),
+    }
+}
+
+/// Reads a medial mesh (`.ma`) from a stream
+///
+/// The file starts with vertex, edge and face counts, followed by `v x y z r`, `e i j` and
+/// `f i j k` lines (0-based indices). Edges may belong to no face, faces with more than
+/// three vertices are accepted, `#` lines are comments.
+pub fn import_from_ma_reader<R: BufRead>(reader: &mut R) -> Result<Skeleton> {
+    let mut skel = Skeleton::new();
+    let mut counts: Option<[usize; 3]> = None;
+    let mut nb_edge_lines = 0;
+    let mut faces = Vec::new();
+    for line in reader.lines() {
+        let line = line?;
+        let line = line.trim();
+        if let Some(comment) = line.strip_prefix('#') {
+            skel.add_comment(comment.trim());
+            continue;
+        }
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+        if tokens.is_empty() {
+            continue;
+        }
+        if counts.is_none() {
+            let values = parse_values::<usize>(&tokens, 3, "header")?;
+            counts = Some([values[0], values[1], values[2]]);
+            continue;
+        }
+        let nb_vertices = skel.get_vertices().len();
+        match tokens[0] {
+            "v" => {
+                let values = parse_values::<f32>(&tokens[1..], 4, "vertex")?;
+                skel.add_vertex(
+                    Vector3::new(values[0], values[1], values[2]),
+                    values[3],
+                    HashMap::new(),
+                );
+            }
+            "e" => {
+                let indices = parse_values::<usize>(&tokens[1..], 2, "edge")?;
+                check_indices(&indices, nb_vertices)?;
+                skel.add_edge([indices[0], indices[1]]);
+                nb_edge_lines += 1;
+            }
+            "f" => {
+                let indices = parse_values::<usize>(&tokens[1..], tokens.len() - 1, "face")?;
+                check_indices(&indices, nb_vertices)?;
+                // faces are added last, so that edges keep the order of the file
+                faces.push(indices);
+            }
+            _ => {
+                return Err(anyhow::Error::msg(format!(
+                    "Unknown ma line type {}",
+                    tokens[0]
+                )))
+            }
+        }
+    }
+    let counts = counts.ok_or(anyhow::Error::msg("No ma vertex, edge and face counts"))?;
+    if counts != [skel.get_vertices().len(), nb_edge_lines, faces.len()] {
+        return Err(anyhow::Error::msg(format!(
+            "Ma counts {:?} do not match the {} vertices, {} edges and {} faces of the file",
+            counts,
+            skel.get_vertices().len(),
+            nb_edge_lines,
+            faces.len()
+        )));
+    }
+    for face in faces {
+        skel.add_face(face, HashMap::new());
+    }
+    Ok(skel)
+}
+
+/// Writes a skeleton as medial mesh, all edges (of faces or not) being listed
+///
+/// Only positions, radii, edges and faces are written, the format having no comment.
+pub fn write_ma<W: Write>(out: &mut W, skel: &Skeleton) -> Result<()> {
+    let vertices = skel.get_vertices();
+    let radii = skel.get_radii();
+    let edges = skel.get_edges();
+    let faces = skel.get_face_vertices();
+    writeln!(out, "{} {} {}", vertices.len(), edges.len(), faces.len())?;
+    for i in 0..vertices.len() {
+        writeln!(
+            out,
+            "v {} {} {} {}",
+            vertices[i][0], vertices[i][1], vertices[i][2], radii[i]
+        )?;
+    }
+    for &[v1, v2] in edges.iter() {
+        writeln!(out, "e {} {}", v1, v2)?;
+    }
+    for face in faces.iter() {
+        let indices: Vec<String> = face.iter().map(|i| i.to_string()).collect();
+        writeln!(out, "f {}", indices.join(" "))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MA: &str = "# medial mesh\n4 5 1\nv 0 0 0 0.5\nv 1 0 0 0.25\nv 0 1 0 1\n\
+                      v 2 2 2 0.125\ne 0 1\ne 1 2\ne 2 0\ne 2 3\ne 3 3\nf 0 1 2\n";
+
+    #[test]
+    fn import_vertices_edges_and_faces() {
+        let skel = import_from_ma_reader(&mut MA.as_bytes()).unwrap();
+        assert_eq!(skel.get_comments(), &vec!["medial mesh".to_string()]);
+        assert_eq!(skel.get_vertices().len(), 4);
+        assert_eq!(skel.get_vertices()[2], Vector3::new(0.0, 1.0, 0.0));
+        assert_eq!(skel.get_radii(), &vec![0.5, 0.25, 1.0, 0.125]);
+        // edges keep the order of the file, the face reusing the first three
+        assert_eq!(
+            skel.get_edges(),
+            &vec![[0, 1], [1, 2], [2, 0], [2, 3], [3, 3]]
+        );
+        assert_eq!(skel.get_face_vertices(), &vec![vec![0, 1, 2]]);
+        assert_eq!(skel.get_faceless_edges(), vec![3, 4]);
+    }
+
+    #[test]
+    fn write_then_import() {
+        let skel = import_from_ma_reader(&mut MA.as_bytes()).unwrap();
+        let mut out = Vec::new();
+        write_ma(&mut out, &skel).unwrap();
+        assert_eq!(
+            String::from_utf8(out.clone()).unwrap(),
+            MA.trim_start_matches("# medial mesh\n")
+        );
+        let reread = import_from_ma_reader(&mut out.as_slice()).unwrap();
+        assert_eq!(reread.get_vertices(), skel.get_vertices());
+        assert_eq!(reread.get_radii(), skel.get_radii());
+        assert_eq!(reread.get_edges(), skel.get_edges());
+        assert_eq!(reread.get_face_vertices(), skel.get_face_vertices());
+    }
+
+    #[test]
+    fn quads_and_faces_without_edge_lines() {
+        let ma = "4 0 1\nv 0 0 0 1\nv 1 0 0 1\nv 1 1 0 1\nv 0 1 0 1\nf 0 1 2 3\n";
+        let skel = import_from_ma_reader(&mut ma.as_bytes()).unwrap();
+        assert_eq!(skel.get_face_vertices(), &vec![vec![0, 1, 2, 3]]);
+        assert_eq!(skel.get_edges().len(), 4);
+        assert!(skel.get_faceless_edges().is_empty());
+    }
+
+    #[test]
+    fn invalid_files() {
+        for ma in [
+            // no counts
+            "",
+            // counts not matching
+            "2 0 0\nv 0 0 0 1\n",
+            "1 1 0\nv 0 0 0 1\n",
+            // index out of range, even when the vertex comes later
+            "2 1 0\nv 0 0 0 1\ne 0 1\nv 1 0 0 1\n",
+            "1 0 1\nv 0 0 0 1\nf 0 0 3\n",
+            // missing radius, negative index, unknown line
+            "1 0 0\nv 0 0 0\n",
+            "2 1 0\nv 0 0 0 1\nv 1 0 0 1\ne 0 -1\n",
+            "1 0 0\nv 0 0 0 1\nl 0 0\n",
+        ] {
+            assert!(import_from_ma_reader(&mut ma.as_bytes()).is_err(), "{}", ma);
+        }
+    }
+}
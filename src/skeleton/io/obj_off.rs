@@ -0,0 +1,568 @@
+use anyhow::Result;
+use std::collections::HashMap;
+use std::io::{BufRead, Write};
+use std::path::{Path, PathBuf};
+
+use nalgebra::base::*;
+
+use super::ma;
+use super::GENERATOR_COMMENT;
+use crate::skeleton::compression;
+use crate::skeleton::skeleton::Skeleton;
+
+/// Text mesh formats of medial axis tools
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum MeshFormat {
+    /// Wavefront `.obj`
+    Obj,
+    /// Object file format `.off`
+    Off,
+    /// Medial mesh `.ma`, with radii and edges of no face
+    Ma,
+}
+
+impl MeshFormat {
+    /// Format given by the file extension (before `.gz` or `.zst`), None if not obj or off
+    pub fn from_path(file_path: &Path) -> Option<MeshFormat> {
+        match compression::format_extension(file_path)? {
+            "obj" => Some(MeshFormat::Obj),
+            "off" => Some(MeshFormat::Off),
+            "ma" => Some(MeshFormat::Ma),
+            _ => None,
+        }
+    }
+}
+
+/// Where vertex radii are stored, obj and off having no radius of their own (ignored for ma)
+#[derive(Clone, PartialEq, Debug)]
+pub enum RadiusConvention {
+    /// Fourth vertex coordinate: `v x y z r` in obj, `x y z r` in off
+    FourthCoordinate,
+    /// Companion text file, one radius per vertex, in vertex order
+    Companion(PathBuf),
+    /// No radius in the files, every vertex getting this radius on import
+    Constant(f32),
+}
+
+/// Vertices, faces and comments read from a text mesh
+struct TextMesh {
+    positions: Vec<Vector3<f32>>,
+    /// values after the three coordinates of each vertex
+    extra: Vec<Vec<f32>>,
+    faces: Vec<Vec<usize>>,
+    edges: Vec<[usize; 2]>,
+    comments: Vec<String>,
+}
+
+impl TextMesh {
+    fn new() -> Self {
+        TextMesh {
+            positions: Vec::new(),
+            extra: Vec::new(),
+            faces: Vec::new(),
+            edges: Vec::new(),
+            comments: Vec::new(),
+        }
+    }
+
+    fn add_vertex(&mut self, values: &[f32]) -> Result<()> {
+        if values.len() < 3 {
+            return Err(anyhow::Error::msg(format!(
+                "Vertex {} has less than three coordinates",
+                self.positions.len()
+            )));
+        }
+        self.positions
+            .push(Vector3::new(values[0], values[1], values[2]));
+        self.extra.push(values[3..].to_vec());
+        Ok(())
+    }
+
+    fn add_face(&mut self, vertex_indices: Vec<usize>) -> Result<()> {
+        if let Some(&index) = vertex_indices.iter().find(|&&i| i >= self.positions.len()) {
+            return Err(anyhow::Error::msg(format!(
+                "Face vertex {} out of range",
+                index
+            )));
+        }
+        self.faces.push(vertex_indices);
+        Ok(())
+    }
+
+    /// Edges between consecutive vertices of a polyline
+    fn add_polyline(&mut self, vertex_indices: Vec<usize>) -> Result<()> {
+        if let Some(&index) = vertex_indices.iter().find(|&&i| i >= self.positions.len()) {
+            return Err(anyhow::Error::msg(format!(
+                "Line vertex {} out of range",
+                index
+            )));
+        }
+        for pair in vertex_indices.windows(2) {
+            self.edges.push([pair[0], pair[1]]);
+        }
+        Ok(())
+    }
+
+    fn into_skeleton(self, radius: &RadiusConvention) -> Result<Skeleton> {
+        let radii = match radius {
+            RadiusConvention::FourthCoordinate => self
+                .extra
+                .iter()
+                .enumerate()
+                .map(|(i, extra)| {
+                    extra.first().copied().ok_or(anyhow::Error::msg(format!(
+                        "Vertex {} has no fourth coordinate",
+                        i
+                    )))
+                })
+                .collect::<Result<Vec<f32>>>()?,
+            RadiusConvention::Companion(file_path) => read_radii(file_path)?,
+            RadiusConvention::Constant(radius) => vec![*radius; self.positions.len()],
+        };
+        if radii.len() != self.positions.len() {
+            return Err(anyhow::Error::msg(format!(
+                "{} radii for {} vertices",
+                radii.len(),
+                self.positions.len()
+            )));
+        }
+
+        let mut skel = Skeleton::new();
+        for comment in self.comments.iter() {
+            if comment != GENERATOR_COMMENT {
+                skel.add_comment(comment);
+            }
+        }
+        for i in 0..self.positions.len() {
+            skel.add_vertex(self.positions[i], radii[i], HashMap::new());
+        }
+        for face in self.faces {
+            skel.add_face(face, HashMap::new());
+        }
+        for edge in self.edges {
+            skel.add_edge(edge);
+        }
+        Ok(skel)
+    }
+}
+
+fn parse_f32(token: &str) -> Result<f32> {
+    token
+        .parse::<f32>()
+        .map_err(|_| anyhow::Error::msg(format!("Invalid number {}", token)))
+}
+
+fn parse_usize(token: &str) -> Result<usize> {
+    token
+        .parse::<usize>()
+        .map_err(|_| anyhow::Error::msg(format!("Invalid index {}", token)))
+}
+
+/// Radii of a companion file, whitespace separated, `#` starting comments
+pub fn read_radii(file_path: &Path) -> Result<Vec<f32>> {
+    let reader = compression::open_reader(file_path)?;
+    let mut radii = Vec::new();
+    for line in reader.lines() {
+        let line = line?;
+        let data = line.split('#').next().unwrap_or("");
+        for token in data.split_whitespace() {
+            radii.push(parse_f32(token)?);
+        }
+    }
+    Ok(radii)
+}
+
+/// Obj face vertex (`v`, `v/vt`, `v//vn`, `v/vt/vn`), 1-based or negative (relative)
+fn obj_vertex_index(token: &str, nb_vertices: usize) -> Result<usize> {
+    let index_str = token.split('/').next().unwrap_or("");
+    let index = index_str
+        .parse::<i64>()
+        .map_err(|_| anyhow::Error::msg(format!("Invalid face vertex {}", token)))?;
+    let index = if index < 0 {
+        nb_vertices as i64 + index
+    } else {
+        index - 1
+    };
+    usize::try_from(index).map_err(|_| anyhow::Error::msg(format!("Invalid face vertex {}", token)))
+}
+
+/// Reads an obj skeleton from a stream
+///
+/// Vertices (`v`), faces (`f`) and lines (`l`, as edges of no face) are read, other
+/// statements (texture coordinates, normals, groups, materials) are skipped.
+pub fn import_from_obj_reader<R: BufRead>(
+    reader: &mut R,
+    radius: &RadiusConvention,
+) -> Result<Skeleton> {
+    let mut mesh = TextMesh::new();
+    for line in reader.lines() {
+        let line = line?;
+        let line = line.trim();
+        if let Some(comment) = line.strip_prefix('#') {
+            mesh.comments.push(comment.trim().to_string());
+            continue;
+        }
+        let mut tokens = line.split_whitespace();
+        match tokens.next() {
+            Some("v") => {
+                let values = tokens.map(parse_f32).collect::<Result<Vec<f32>>>()?;
+                mesh.add_vertex(&values)?;
+            }
+            Some("f") => {
+                let nb_vertices = mesh.positions.len();
+                let face = tokens
+                    .map(|token| obj_vertex_index(token, nb_vertices))
+                    .collect::<Result<Vec<usize>>>()?;
+                mesh.add_face(face)?;
+            }
+            Some("l") => {
+                let nb_vertices = mesh.positions.len();
+                let polyline = tokens
+                    .map(|token| obj_vertex_index(token, nb_vertices))
+                    .collect::<Result<Vec<usize>>>()?;
+                mesh.add_polyline(polyline)?;
+            }
+            _ => (),
+        }
+    }
+    mesh.into_skeleton(radius)
+}
+
+/// Reads an off skeleton from a stream
+///
+/// Any `OFF` header variant is accepted (`COFF`, `4OFF`, ...), values after the three
+/// vertex coordinates and the face vertices are left for the radius convention or skipped.
+pub fn import_from_off_reader<R: BufRead>(
+    reader: &mut R,
+    radius: &RadiusConvention,
+) -> Result<Skeleton> {
+    let mut mesh = TextMesh::new();
+    let mut header = false;
+    let mut counts: Option<(usize, usize)> = None;
+    for line in reader.lines() {
+        let line = line?;
+        let (data, comment) = match line.split_once('#') {
+            Some((data, comment)) => (data, Some(comment)),
+            None => (line.as_str(), None),
+        };
+        if let Some(comment) = comment {
+            mesh.comments.push(comment.trim().to_string());
+        }
+        let mut tokens: Vec<&str> = data.split_whitespace().collect();
+        if tokens.is_empty() {
+            continue;
+        }
+        if !header {
+            if !tokens[0].ends_with("OFF") {
+                return Err(anyhow::Error::msg("No OFF header"));
+            }
+            header = true;
+            tokens.remove(0);
+            if tokens.is_empty() {
+                continue;
+            }
+        }
+        match counts {
+            None => {
+                if tokens.len() < 2 {
+                    return Err(anyhow::Error::msg("Invalid OFF vertex and face counts"));
+                }
+                counts = Some((parse_usize(tokens[0])?, parse_usize(tokens[1])?));
+            }
+            Some((nb_vertices, _)) if mesh.positions.len() < nb_vertices => {
+                let values = tokens
+                    .into_iter()
+                    .map(parse_f32)
+                    .collect::<Result<Vec<f32>>>()?;
+                mesh.add_vertex(&values)?;
+            }
+            Some((_, nb_faces)) if mesh.faces.len() < nb_faces => {
+                let nb_face_vertices = parse_usize(tokens[0])?;
+                if tokens.len() <= nb_face_vertices {
+                    return Err(anyhow::Error::msg(format!(
+                        "Face {} has less than {} vertices",
+                        mesh.faces.len(),
+                        nb_face_vertices
+                    )));
+                }
+                let face = tokens[1..=nb_face_vertices]
+                    .iter()
+                    .map(|token| parse_usize(token))
+                    .collect::<Result<Vec<usize>>>()?;
+                mesh.add_face(face)?;
+            }
+            _ => (),
+        }
+    }
+    match counts {
+        Some((nb_vertices, nb_faces))
+            if mesh.positions.len() == nb_vertices && mesh.faces.len() == nb_faces =>
+        {
+            mesh.into_skeleton(radius)
+        }
+        Some(_) => Err(anyhow::Error::msg("Unexpected end of file in OFF data")),
+        None => Err(anyhow::Error::msg("No OFF vertex and face counts")),
+    }
+}
+
+/// Reads an obj, off or ma skeleton (`-` for standard input), gzip or zstd compressed
+/// files being detected from their content
+pub fn import_from_mesh(
+    file_path: &str,
+    format: MeshFormat,
+    radius: &RadiusConvention,
+) -> Result<Skeleton> {
+    let mut reader = compression::open_reader(Path::new(file_path))?;
+    match format {
+        MeshFormat::Obj => import_from_obj_reader(&mut reader, radius),
+        MeshFormat::Off => import_from_off_reader(&mut reader, radius),
+        MeshFormat::Ma => ma::import_from_ma_reader(&mut reader),
+    }
+}
+
+/// Vertex line values, with the radius as fourth coordinate if required
+fn vertex_values(skel: &Skeleton, i: usize, radius: &RadiusConvention) -> String {
+    let vertex = skel.get_vertices()[i];
+    match radius {
+        RadiusConvention::FourthCoordinate => format!(
+            "{} {} {} {}",
+            vertex[0],
+            vertex[1],
+            vertex[2],
+            skel.get_radii()[i]
+        ),
+        _ => format!("{} {} {}", vertex[0], vertex[1], vertex[2]),
+    }
+}
+
+fn write_radii(skel: &Skeleton, radius: &RadiusConvention) -> Result<()> {
+    if let RadiusConvention::Companion(file_path) = radius {
+        let mut file = compression::create_writer(file_path)?;
+        for r in skel.get_radii().iter() {
+            writeln!(file, "{}", r)?;
+        }
+        file.finish()?;
+    }
+    Ok(())
+}
+
+/// Writes a skeleton as obj, off or ma (`-` for standard output), compressed if the file
+/// name ends with `.gz` or `.zst`
+///
+/// Only positions, radii (following `radius` for obj and off, not written with `Constant`),
+/// faces and edges of no face (obj lines, not written in off) are written, ply comments
+/// becoming `#` comments in obj and off.
+pub fn export_to_mesh(
+    skel: &Skeleton,
+    file_path: &str,
+    format: MeshFormat,
+    radius: &RadiusConvention,
+) -> Result<()> {
+    let mut file = compression::create_writer(Path::new(file_path))?;
+    if format == MeshFormat::Ma {
+        ma::write_ma(&mut file, skel)?;
+        return file.finish();
+    }
+    let faces = skel.get_face_vertices();
+    if format == MeshFormat::Off {
+        writeln!(file, "OFF")?;
+    }
+    writeln!(file, "# {}", GENERATOR_COMMENT)?;
+    for comment in skel.get_comments().iter() {
+        writeln!(file, "# {}", comment)?;
+    }
+    match format {
+        MeshFormat::Obj => {
+            for i in 0..skel.get_vertices().len() {
+                writeln!(file, "v {}", vertex_values(skel, i, radius))?;
+            }
+            for face in faces.iter() {
+                let indices: Vec<String> = face.iter().map(|i| (i + 1).to_string()).collect();
+                writeln!(file, "f {}", indices.join(" "))?;
+            }
+            for edge_index in skel.get_faceless_edges() {
+                let [v1, v2] = skel.get_edges()[edge_index];
+                writeln!(file, "l {} {}", v1 + 1, v2 + 1)?;
+            }
+        }
+        MeshFormat::Off => {
+            writeln!(file, "{} {} 0", skel.get_vertices().len(), faces.len())?;
+            for i in 0..skel.get_vertices().len() {
+                writeln!(file, "{}", vertex_values(skel, i, radius))?;
+            }
+            for face in faces.iter() {
+                let indices: Vec<String> = face.iter().map(|i| i.to_string()).collect();
+                writeln!(file, "{} {}", face.len(), indices.join(" "))?;
+            }
+        }
+        MeshFormat::Ma => (),
+    }
+    file.finish()?;
+    write_radii(skel, radius)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn faceless_edges(skel: &Skeleton) -> Vec<[usize; 2]> {
+        skel.get_faceless_edges()
+            .iter()
+            .map(|&e| skel.get_edges()[e])
+            .collect()
+    }
+
+    fn off_error(off: &str) -> String {
+        import_from_off_reader(&mut off.as_bytes(), &RadiusConvention::Constant(1.0))
+            .err()
+            .unwrap()
+            .to_string()
+    }
+
+    #[test]
+    fn obj_vertex_indices() {
+        assert_eq!(obj_vertex_index("1", 3).unwrap(), 0);
+        assert_eq!(obj_vertex_index("3/1/2", 3).unwrap(), 2);
+        assert_eq!(obj_vertex_index("2//5", 3).unwrap(), 1);
+        assert_eq!(obj_vertex_index("2/7", 3).unwrap(), 1);
+        // relative to the vertices read so far
+        assert_eq!(obj_vertex_index("-1", 3).unwrap(), 2);
+        assert_eq!(obj_vertex_index("-3/4/4", 3).unwrap(), 0);
+        for token in ["-4", "0", "a", "/1", ""] {
+            assert!(obj_vertex_index(token, 3).is_err(), "{}", token);
+        }
+    }
+
+    #[test]
+    fn obj_import() {
+        let obj = "# made by hand\nv 0 0 0 0.5\nv 1 0 0 0.25\nvt 0 1\nvn 0 0 1\n\
+                   v 0 1 0 0.125\ng part\nf 1/1/1 2/1/1 -1/1/1\nv 2 2 2 1\nl 3 4\n";
+        let skel = import_from_obj_reader(&mut obj.as_bytes(), &RadiusConvention::FourthCoordinate)
+            .unwrap();
+        assert_eq!(skel.get_radii(), &vec![0.5, 0.25, 0.125, 1.0]);
+        assert_eq!(skel.get_face_vertices(), &vec![vec![0, 1, 2]]);
+        assert_eq!(faceless_edges(&skel), vec![[2, 3]]);
+        assert_eq!(skel.get_comments(), &vec!["made by hand".to_string()]);
+
+        let obj = "v 0 0 0\nv 1 0 0\nf 1 2 3\n";
+        let err = import_from_obj_reader(&mut obj.as_bytes(), &RadiusConvention::Constant(1.0))
+            .err()
+            .unwrap();
+        assert_eq!(err.to_string(), "Face vertex 2 out of range");
+    }
+
+    #[test]
+    fn off_header_and_counts() {
+        // header, counts and values split over lines, with comments and blank lines
+        let off = "COFF # colored\n\n# counts\n4 2 0\n0 0 0 255 0 0\n1 0 0 0 255 0\n\
+                   0 1 0\n1 1 0\n3 0 1 2 255 0 0\n3 1 3 2\n";
+        let skel =
+            import_from_off_reader(&mut off.as_bytes(), &RadiusConvention::Constant(0.5)).unwrap();
+        assert_eq!(skel.get_vertices().len(), 4);
+        assert_eq!(skel.get_radii(), &vec![0.5; 4]);
+        // values after the face vertices are skipped
+        assert_eq!(
+            skel.get_face_vertices(),
+            &vec![vec![0, 1, 2], vec![1, 3, 2]]
+        );
+        assert_eq!(
+            skel.get_comments(),
+            &vec!["colored".to_string(), "counts".to_string()]
+        );
+
+        // counts on the header line, values of a vertex left for the radius
+        let off = "4OFF 3 1 0\n0 0 0 0.1\n1 0 0 0.2\n0 1 0 0.3\n3 0 1 2\n";
+        let skel = import_from_off_reader(&mut off.as_bytes(), &RadiusConvention::FourthCoordinate)
+            .unwrap();
+        assert_eq!(skel.get_radii(), &vec![0.1, 0.2, 0.3]);
+
+        // no face
+        let skel = import_from_off_reader(
+            &mut "OFF\n1 0 0\n0 0 0\n".as_bytes(),
+            &RadiusConvention::Constant(1.0),
+        )
+        .unwrap();
+        assert_eq!(skel.get_vertices().len(), 1);
+
+        assert_eq!(off_error("3 1 0\n"), "No OFF header");
+        assert_eq!(off_error("OFF\n"), "No OFF vertex and face counts");
+        assert_eq!(off_error("OFF 3\n"), "Invalid OFF vertex and face counts");
+        assert_eq!(
+            off_error("OFF\n3 1 0\n0 0 0\n1 0 0\n0 1 0\n"),
+            "Unexpected end of file in OFF data"
+        );
+        assert_eq!(
+            off_error("OFF\n3 1 0\n0 0 0\n1 0 0\n0 1 0\n3 0 1\n"),
+            "Face 0 has less than 3 vertices"
+        );
+        assert_eq!(
+            off_error("OFF\n3 1 0\n0 0 0\n1 0 0\n0 1\n3 0 1 2\n"),
+            "Vertex 2 has less than three coordinates"
+        );
+        assert_eq!(
+            off_error("OFF\n3 1 0\n0 0 0\n1 0 0\n0 1 0\n3 0 1 3\n"),
+            "Face vertex 3 out of range"
+        );
+    }
+
+    #[test]
+    fn radius_conventions() {
+        let dir = std::env::temp_dir().join(format!("obj_off_radius_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let obj = "v 0 0 0 0.5\nv 1 0 0\nv 0 1 0 0.25\nf 1 2 3\n";
+        let err = import_from_obj_reader(&mut obj.as_bytes(), &RadiusConvention::FourthCoordinate)
+            .err()
+            .unwrap();
+        assert_eq!(err.to_string(), "Vertex 1 has no fourth coordinate");
+
+        let companion = dir.join("skeleton.rad");
+        std::fs::write(&companion, "0.1 # first\n\n0.2 0.3\n").unwrap();
+        let radius = RadiusConvention::Companion(companion.clone());
+        let skel = import_from_obj_reader(&mut obj.as_bytes(), &radius).unwrap();
+        assert_eq!(skel.get_radii(), &vec![0.1, 0.2, 0.3]);
+        std::fs::write(&companion, "0.1 0.2\n").unwrap();
+        let err = import_from_obj_reader(&mut obj.as_bytes(), &radius)
+            .err()
+            .unwrap();
+        assert_eq!(err.to_string(), "2 radii for 3 vertices");
+
+        let skel =
+            import_from_obj_reader(&mut obj.as_bytes(), &RadiusConvention::Constant(2.0)).unwrap();
+        assert_eq!(skel.get_radii(), &vec![2.0; 3]);
+
+        // written and read back with each convention
+        let skel = import_from_obj_reader(
+            &mut "v 0 0 0 0.5\nv 1 0 0 0.25\nv 0 1 0 0.125\nf 1 2 3\n".as_bytes(),
+            &RadiusConvention::FourthCoordinate,
+        )
+        .unwrap();
+        for (format, extension) in [(MeshFormat::Obj, "obj"), (MeshFormat::Off, "off")] {
+            let file_path = dir.join(format!("skeleton.{}", extension));
+            let file_path = file_path.to_str().unwrap();
+            for (radius, radii) in [
+                (RadiusConvention::FourthCoordinate, vec![0.5, 0.25, 0.125]),
+                (
+                    RadiusConvention::Companion(dir.join("written.rad")),
+                    vec![0.5, 0.25, 0.125],
+                ),
+                (RadiusConvention::Constant(1.0), vec![1.0; 3]),
+            ] {
+                export_to_mesh(&skel, file_path, format, &radius).unwrap();
+                let text = std::fs::read_to_string(file_path).unwrap();
+                let has_fourth = text.contains("0 0 0 0.5\n");
+                assert_eq!(
+                    has_fourth,
+                    radius == RadiusConvention::FourthCoordinate,
+                    "{}",
+                    text
+                );
+                let read = import_from_mesh(file_path, format, &radius).unwrap();
+                assert_eq!(read.get_radii(), &radii, "{:?} {:?}", format, radius);
+                assert_eq!(read.get_face_vertices(), skel.get_face_vertices());
+            }
+        }
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,564 @@
+use anyhow::Result;
+use std::io::Write;
+use std::path::Path;
+
+use super::colormap::ColorScale;
+use super::compression;
+
+// layout, in pixels
+const WIDTH: usize = 480;
+const HEIGHT: usize = 100;
+const MARGIN: usize = 20;
+const TITLE_Y: usize = 20;
+const BAR_Y: usize = 32;
+const BAR_HEIGHT: usize = 24;
+const TICK_LENGTH: usize = 5;
+const LABEL_Y: usize = BAR_Y + BAR_HEIGHT + TICK_LENGTH + 16;
+const SWATCH_WIDTH: usize = 24;
+/// most ticks, fewer being drawn when their labels would overlap
+const MAX_TICKS: usize = 5;
+/// least space between two tick labels
+const LABEL_GAP: usize = 8;
+/// samples of the gradient, a multiple of the colormap intervals so that stops are exact
+const NB_GRADIENT_STOPS: usize = 64;
+/// glyph pixel size in png legends
+const GLYPH_SCALE: usize = 2;
+/// horizontal space taken by a glyph in png legends
+const GLYPH_ADVANCE: usize = 6 * GLYPH_SCALE;
+/// label of the invalid color swatch
+const INVALID_LABEL: &str = "invalid";
+
+/// Legend of the colors set from a property
+pub struct Legend {
+    title: String,
+    scale: ColorScale,
+    /// range of the scale, None if no value is colored by the colormap
+    bounds: Option<(f32, f32)>,
+    /// whether some values get the invalid color (infinite, NaN, non positive on a log
+    /// scale)
+    has_invalid: bool,
+}
+
+/// Short label of a value
+fn format_value(value: f32) -> String {
+    let abs = value.abs();
+    if value == 0.0 {
+        "0".to_string()
+    } else if !(1e-2..1e4).contains(&abs) {
+        format!("{:.2e}", value)
+    } else {
+        let label = format!("{:.3}", value);
+        label
+            .trim_end_matches('0')
+            .trim_end_matches('.')
+            .to_string()
+    }
+}
+
+/// Text anchor of a tick label, so that labels at the ends stay in the legend
+fn anchor(t: f32) -> &'static str {
+    if t <= 0.0 {
+        "start"
+    } else if t >= 1.0 {
+        "end"
+    } else {
+        "middle"
+    }
+}
+
+/// Width of a text in png legends, larger than in svg ones
+fn text_width(text: &str) -> usize {
+    text.chars().count() * GLYPH_ADVANCE
+}
+
+/// Horizontal extent of a text of width `width` anchored at `x`
+fn text_extent(x: f32, width: usize, anchor: &str) -> (f32, f32) {
+    let width = width as f32;
+    match anchor {
+        "start" => (x, x + width),
+        "end" => (x - width, x),
+        _ => (x - width / 2.0, x + width / 2.0),
+    }
+}
+
+/// Width of the invalid color column, swatch and label
+fn invalid_width() -> usize {
+    SWATCH_WIDTH.max(text_width(INVALID_LABEL))
+}
+
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+/// 5x7 glyph, one byte per row, upper case only
+fn glyph(c: char) -> [u8; 7] {
+    match c.to_ascii_uppercase() {
+        '0' => [0x0E, 0x11, 0x13, 0x15, 0x19, 0x11, 0x0E],
+        '1' => [0x04, 0x0C, 0x04, 0x04, 0x04, 0x04, 0x0E],
+        '2' => [0x0E, 0x11, 0x01, 0x02, 0x04, 0x08, 0x1F],
+        '3' => [0x1F, 0x02, 0x04, 0x02, 0x01, 0x11, 0x0E],
+        '4' => [0x02, 0x06, 0x0A, 0x12, 0x1F, 0x02, 0x02],
+        '5' => [0x1F, 0x10, 0x1E, 0x01, 0x01, 0x11, 0x0E],
+        '6' => [0x06, 0x08, 0x10, 0x1E, 0x11, 0x11, 0x0E],
+        '7' => [0x1F, 0x01, 0x02, 0x04, 0x08, 0x08, 0x08],
+        '8' => [0x0E, 0x11, 0x11, 0x0E, 0x11, 0x11, 0x0E],
+        '9' => [0x0E, 0x11, 0x11, 0x0F, 0x01, 0x02, 0x0C],
+        'A' => [0x0E, 0x11, 0x11, 0x11, 0x1F, 0x11, 0x11],
+        'B' => [0x1E, 0x11, 0x11, 0x1E, 0x11, 0x11, 0x1E],
+        'C' => [0x0E, 0x11, 0x10, 0x10, 0x10, 0x11, 0x0E],
+        'D' => [0x1C, 0x12, 0x11, 0x11, 0x11, 0x12, 0x1C],
+        'E' => [0x1F, 0x10, 0x10, 0x1E, 0x10, 0x10, 0x1F],
+        'F' => [0x1F, 0x10, 0x10, 0x1E, 0x10, 0x10, 0x10],
+        'G' => [0x0E, 0x11, 0x10, 0x17, 0x11, 0x11, 0x0F],
+        'H' => [0x11, 0x11, 0x11, 0x1F, 0x11, 0x11, 0x11],
+        'I' => [0x0E, 0x04, 0x04, 0x04, 0x04, 0x04, 0x0E],
+        'J' => [0x07, 0x02, 0x02, 0x02, 0x02, 0x12, 0x0C],
+        'K' => [0x11, 0x12, 0x14, 0x18, 0x14, 0x12, 0x11],
+        'L' => [0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x1F],
+        'M' => [0x11, 0x1B, 0x15, 0x15, 0x11, 0x11, 0x11],
+        'N' => [0x11, 0x11, 0x19, 0x15, 0x13, 0x11, 0x11],
+        'O' => [0x0E, 0x11, 0x11, 0x11, 0x11, 0x11, 0x0E],
+        'P' => [0x1E, 0x11, 0x11, 0x1E, 0x10, 0x10, 0x10],
+        'Q' => [0x0E, 0x11, 0x11, 0x11, 0x15, 0x12, 0x0D],
+        'R' => [0x1E, 0x11, 0x11, 0x1E, 0x14, 0x12, 0x11],
+        'S' => [0x0F, 0x10, 0x10, 0x0E, 0x01, 0x01, 0x1E],
+        'T' => [0x1F, 0x04, 0x04, 0x04, 0x04, 0x04, 0x04],
+        'U' => [0x11, 0x11, 0x11, 0x11, 0x11, 0x11, 0x0E],
+        'V' => [0x11, 0x11, 0x11, 0x11, 0x11, 0x0A, 0x04],
+        'W' => [0x11, 0x11, 0x11, 0x15, 0x15, 0x15, 0x0A],
+        'X' => [0x11, 0x11, 0x0A, 0x04, 0x0A, 0x11, 0x11],
+        'Y' => [0x11, 0x11, 0x11, 0x0A, 0x04, 0x04, 0x04],
+        'Z' => [0x1F, 0x01, 0x02, 0x04, 0x08, 0x10, 0x1F],
+        '.' => [0x00, 0x00, 0x00, 0x00, 0x00, 0x0C, 0x0C],
+        '-' => [0x00, 0x00, 0x00, 0x1F, 0x00, 0x00, 0x00],
+        '+' => [0x00, 0x04, 0x04, 0x1F, 0x04, 0x04, 0x00],
+        '_' => [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x1F],
+        '/' => [0x00, 0x01, 0x02, 0x04, 0x08, 0x10, 0x00],
+        '(' => [0x02, 0x04, 0x08, 0x08, 0x08, 0x04, 0x02],
+        ')' => [0x08, 0x04, 0x02, 0x02, 0x02, 0x04, 0x08],
+        ' ' => [0x00; 7],
+        _ => [0x0E, 0x11, 0x01, 0x02, 0x04, 0x00, 0x04],
+    }
+}
+
+/// RGB image, drawn with the legend layout
+struct Image {
+    pixels: Vec<[u8; 3]>,
+}
+
+impl Image {
+    fn new() -> Image {
+        Image {
+            pixels: vec![[255; 3]; WIDTH * HEIGHT],
+        }
+    }
+
+    fn fill(&mut self, x: usize, y: usize, width: usize, height: usize, color: [u8; 3]) {
+        for j in y..(y + height).min(HEIGHT) {
+            for i in x..(x + width).min(WIDTH) {
+                self.pixels[j * WIDTH + i] = color;
+            }
+        }
+    }
+
+    fn frame(&mut self, x: usize, y: usize, width: usize, height: usize) {
+        self.fill(x, y, width, 1, [0; 3]);
+        self.fill(x, y + height - 1, width, 1, [0; 3]);
+        self.fill(x, y, 1, height, [0; 3]);
+        self.fill(x + width - 1, y, 1, height, [0; 3]);
+    }
+
+    /// Text anchored at `x` (see `anchor`), with its baseline at `y`
+    fn text(&mut self, x: usize, y: usize, text: &str, anchor: &str) {
+        let (x0, _) = text_extent(x as f32, text_width(text), anchor);
+        let x0 = x0.max(0.0) as usize;
+        let y0 = y.saturating_sub(7 * GLYPH_SCALE);
+        for (k, c) in text.chars().enumerate() {
+            let rows = glyph(c);
+            for (j, row) in rows.iter().enumerate() {
+                for i in 0..5 {
+                    if row & (0x10 >> i) != 0 {
+                        self.fill(
+                            x0 + k * GLYPH_ADVANCE + i * GLYPH_SCALE,
+                            y0 + j * GLYPH_SCALE,
+                            GLYPH_SCALE,
+                            GLYPH_SCALE,
+                            [0; 3],
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    /// PNG encoding: 8 bits RGB, no filter, zlib compressed
+    fn write_png<W: Write>(&self, out: &mut W) -> Result<()> {
+        fn chunk<W: Write>(out: &mut W, kind: &[u8; 4], data: &[u8]) -> Result<()> {
+            let mut crc = flate2::Crc::new();
+            crc.update(kind);
+            crc.update(data);
+            out.write_all(&(data.len() as u32).to_be_bytes())?;
+            out.write_all(kind)?;
+            out.write_all(data)?;
+            out.write_all(&crc.sum().to_be_bytes())?;
+            Ok(())
+        }
+
+        let mut header = Vec::new();
+        header.extend((WIDTH as u32).to_be_bytes());
+        header.extend((HEIGHT as u32).to_be_bytes());
+        header.extend([8, 2, 0, 0, 0]);
+
+        let mut encoder =
+            flate2::write::ZlibEncoder::new(Vec::new(), flate2::Compression::default());
+        for j in 0..HEIGHT {
+            encoder.write_all(&[0])?;
+            for i in 0..WIDTH {
+                encoder.write_all(&self.pixels[j * WIDTH + i])?;
+            }
+        }
+
+        out.write_all(&[0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A])?;
+        chunk(out, b"IHDR", &header)?;
+        chunk(out, b"IDAT", &encoder.finish()?)?;
+        chunk(out, b"IEND", &[])?;
+        Ok(())
+    }
+}
+
+impl Legend {
+    /// Legend of the colors given by `scale` to `values`
+    pub fn new(title: &str, scale: &ColorScale, values: &[f32]) -> Result<Legend> {
+        let title = if scale.log {
+            format!("{} (log)", title)
+        } else {
+            title.to_string()
+        };
+        Ok(Legend {
+            title,
+            scale: *scale,
+            bounds: scale.bounds(values)?,
+            has_invalid: values.iter().any(|&v| !scale.is_valid(v)),
+        })
+    }
+
+    /// Horizontal extent of the color bar
+    fn bar_width(&self) -> usize {
+        if self.has_invalid {
+            WIDTH - 3 * MARGIN - invalid_width()
+        } else {
+            WIDTH - 2 * MARGIN
+        }
+    }
+
+    /// Position on the bar (0 to 1) and value of each tick, evenly spaced on the scale
+    ///
+    /// As many ticks as possible (up to `MAX_TICKS`, at least the two ends) are drawn without
+    /// their labels overlapping.
+    fn ticks(&self) -> Vec<(f32, f32)> {
+        match self.bounds {
+            None => Vec::new(),
+            Some((lo, hi)) if hi <= lo => vec![(0.5, lo)],
+            Some((lo, hi)) => (2..=MAX_TICKS)
+                .rev()
+                .map(|nb_ticks| self.evenly_spaced_ticks(lo, hi, nb_ticks))
+                .find(|ticks| self.labels_fit(ticks))
+                .unwrap_or_else(|| self.evenly_spaced_ticks(lo, hi, 2)),
+        }
+    }
+
+    fn evenly_spaced_ticks(&self, lo: f32, hi: f32, nb_ticks: usize) -> Vec<(f32, f32)> {
+        (0..nb_ticks)
+            .map(|k| {
+                let t = k as f32 / (nb_ticks - 1) as f32;
+                let value = if self.scale.log {
+                    (lo.ln() + t * (hi.ln() - lo.ln())).exp()
+                } else {
+                    lo + t * (hi - lo)
+                };
+                (t, value)
+            })
+            .collect()
+    }
+
+    /// Horizontal extent of each tick label
+    fn label_extents(&self, ticks: &[(f32, f32)]) -> Vec<(f32, f32)> {
+        let bar_width = self.bar_width() as f32;
+        ticks
+            .iter()
+            .map(|&(t, value)| {
+                let x = MARGIN as f32 + t * bar_width;
+                text_extent(x, text_width(&format_value(value)), anchor(t))
+            })
+            .collect()
+    }
+
+    /// Whether consecutive tick labels are at least `LABEL_GAP` apart
+    fn labels_fit(&self, ticks: &[(f32, f32)]) -> bool {
+        self.label_extents(ticks)
+            .windows(2)
+            .all(|pair| pair[0].1 + LABEL_GAP as f32 <= pair[1].0)
+    }
+
+    /// Color of the bar at position `t` (0 to 1), None without colored values
+    fn bar_color(&self, t: f32) -> Option<[u8; 3]> {
+        match self.bounds {
+            None => None,
+            Some((lo, hi)) if hi <= lo => Some(self.scale.colormap.color(0.5)),
+            Some(_) => Some(self.scale.colormap.color(t)),
+        }
+    }
+
+    pub fn to_svg(&self) -> String {
+        let rgb = |c: [u8; 3]| format!("rgb({},{},{})", c[0], c[1], c[2]);
+        let bar_width = self.bar_width();
+        let mut svg = format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{w}\" height=\"{h}\" viewBox=\"0 0 {w} {h}\" font-family=\"sans-serif\" font-size=\"12\">\n",
+            w = WIDTH,
+            h = HEIGHT
+        );
+        svg += &format!(
+            "<rect width=\"{}\" height=\"{}\" fill=\"white\"/>\n",
+            WIDTH, HEIGHT
+        );
+        svg += "<defs><linearGradient id=\"colormap\" x1=\"0\" x2=\"1\" y1=\"0\" y2=\"0\">\n";
+        for k in 0..=NB_GRADIENT_STOPS {
+            let t = k as f32 / NB_GRADIENT_STOPS as f32;
+            let color = self.bar_color(t).unwrap_or([255; 3]);
+            svg += &format!(
+                "<stop offset=\"{:.6}\" stop-color=\"{}\"/>\n",
+                t,
+                rgb(color)
+            );
+        }
+        svg += "</linearGradient></defs>\n";
+        svg += &format!(
+            "<text x=\"{}\" y=\"{}\" text-anchor=\"middle\">{}</text>\n",
+            WIDTH / 2,
+            TITLE_Y,
+            escape_xml(&self.title)
+        );
+        svg += &format!(
+            "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"url(#colormap)\" stroke=\"black\"/>\n",
+            MARGIN, BAR_Y, bar_width, BAR_HEIGHT
+        );
+        if self.bounds.is_none() {
+            svg += &format!(
+                "<text x=\"{}\" y=\"{}\" text-anchor=\"middle\">no value</text>\n",
+                MARGIN + bar_width / 2,
+                LABEL_Y
+            );
+        }
+        for (t, value) in self.ticks() {
+            let x = MARGIN as f32 + t * bar_width as f32;
+            svg += &format!(
+                "<line x1=\"{x:.2}\" x2=\"{x:.2}\" y1=\"{}\" y2=\"{}\" stroke=\"black\"/>\n",
+                BAR_Y + BAR_HEIGHT,
+                BAR_Y + BAR_HEIGHT + TICK_LENGTH,
+                x = x
+            );
+            svg += &format!(
+                "<text x=\"{:.2}\" y=\"{}\" text-anchor=\"{}\">{}</text>\n",
+                x,
+                LABEL_Y,
+                anchor(t),
+                format_value(value)
+            );
+        }
+        if self.has_invalid {
+            let center = WIDTH - MARGIN - invalid_width() / 2;
+            let x = center - SWATCH_WIDTH / 2;
+            svg += &format!(
+                "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"{}\" stroke=\"black\"/>\n",
+                x,
+                BAR_Y,
+                SWATCH_WIDTH,
+                BAR_HEIGHT,
+                rgb(self.scale.invalid_color)
+            );
+            svg += &format!(
+                "<text x=\"{}\" y=\"{}\" text-anchor=\"middle\">{}</text>\n",
+                center, LABEL_Y, INVALID_LABEL
+            );
+        }
+        svg += "</svg>\n";
+        svg
+    }
+
+    fn to_image(&self) -> Image {
+        let bar_width = self.bar_width();
+        let mut image = Image::new();
+        image.text(WIDTH / 2, TITLE_Y, &self.title, "middle");
+        for i in 0..bar_width {
+            let t = i as f32 / (bar_width - 1) as f32;
+            if let Some(color) = self.bar_color(t) {
+                image.fill(MARGIN + i, BAR_Y, 1, BAR_HEIGHT, color);
+            }
+        }
+        image.frame(MARGIN, BAR_Y, bar_width, BAR_HEIGHT);
+        if self.bounds.is_none() {
+            image.text(MARGIN + bar_width / 2, LABEL_Y, "no value", "middle");
+        }
+        for (t, value) in self.ticks() {
+            let x = MARGIN + (t * (bar_width - 1) as f32).round() as usize;
+            image.fill(x, BAR_Y + BAR_HEIGHT, 1, TICK_LENGTH, [0; 3]);
+            image.text(x, LABEL_Y, &format_value(value), anchor(t));
+        }
+        if self.has_invalid {
+            let center = WIDTH - MARGIN - invalid_width() / 2;
+            let x = center - SWATCH_WIDTH / 2;
+            image.fill(x, BAR_Y, SWATCH_WIDTH, BAR_HEIGHT, self.scale.invalid_color);
+            image.frame(x, BAR_Y, SWATCH_WIDTH, BAR_HEIGHT);
+            image.text(center, LABEL_Y, INVALID_LABEL, "middle");
+        }
+        image
+    }
+
+    /// Writes the legend as svg (`-` for standard output)
+    pub fn write_svg(&self, file_path: &Path) -> Result<()> {
+        let mut file = compression::create_writer(file_path)?;
+        file.write_all(self.to_svg().as_bytes())?;
+        file.finish()
+    }
+
+    /// Writes the legend as png (`-` for standard output)
+    pub fn write_png(&self, file_path: &Path) -> Result<()> {
+        let mut file = compression::create_writer(file_path)?;
+        self.to_image().write_png(&mut file)?;
+        file.finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::skeleton::colormap::ColorRange;
+    use std::io::Read;
+
+    fn legend(values: &[f32], log: bool) -> Legend {
+        let scale = ColorScale {
+            log,
+            ..ColorScale::default()
+        };
+        Legend::new("erosion_thickness", &scale, values).unwrap()
+    }
+
+    #[test]
+    fn ticks_span_the_bounds() {
+        let ticks = legend(&[0.0, 1.0], false).ticks();
+        assert_eq!(ticks.len(), MAX_TICKS);
+        assert_eq!(ticks[0], (0.0, 0.0));
+        assert_eq!(ticks[MAX_TICKS - 1], (1.0, 1.0));
+        assert_eq!(ticks[2], (0.5, 0.5));
+
+        let ticks = legend(&[1.0, 100.0], true).ticks();
+        assert_eq!(ticks.first().unwrap().1, 1.0);
+        assert!((ticks[ticks.len() / 2].1 - 10.0).abs() < 1e-4);
+        assert!((ticks.last().unwrap().1 - 100.0).abs() < 1e-3);
+
+        assert_eq!(legend(&[2.0, 2.0], false).ticks(), vec![(0.5, 2.0)]);
+        assert!(legend(&[f32::NAN], false).ticks().is_empty());
+    }
+
+    #[test]
+    fn tick_labels_do_not_overlap() {
+        // range of the erosion thickness of resources/skeleton.ply
+        for values in [
+            vec![-3.6e-4, 15.7227],
+            vec![-3.6e-4, 15.7227, f32::INFINITY],
+            vec![-123456.7, 1.0e-7, f32::NAN],
+        ] {
+            let legend = legend(&values, false);
+            let ticks = legend.ticks();
+            assert!(ticks.len() >= 2);
+            let extents = legend.label_extents(&ticks);
+            for pair in extents.windows(2) {
+                assert!(pair[0].1 < pair[1].0, "{:?}", extents);
+            }
+            let (first, last) = (extents[0], extents[extents.len() - 1]);
+            assert!(first.0 >= 0.0);
+            assert!(last.1 <= (MARGIN + legend.bar_width()) as f32);
+        }
+        // labels of the skeleton range only fit with 3 ticks next to the invalid swatch
+        assert_eq!(
+            legend(&[-3.6e-4, 15.7227, f32::INFINITY], false)
+                .ticks()
+                .len(),
+            3
+        );
+    }
+
+    #[test]
+    fn svg_labels_and_swatch() {
+        let svg = legend(&[0.0, 1.0, f32::INFINITY], false).to_svg();
+        assert!(svg.starts_with("<svg") && svg.ends_with("</svg>\n"));
+        assert_eq!(svg.matches("<stop ").count(), NB_GRADIENT_STOPS + 1);
+        assert!(svg.contains(">erosion_thickness</text>"));
+        assert!(svg.contains(">0</text>") && svg.contains(">1</text>"));
+        assert_eq!(svg.matches(INVALID_LABEL).count(), 1);
+        assert!(svg.contains("fill=\"rgb(255,0,255)\""));
+
+        let svg = legend(&[0.0, 1.0], false).to_svg();
+        assert!(!svg.contains(INVALID_LABEL));
+
+        let svg = legend(&[f32::NAN], false).to_svg();
+        assert!(svg.contains(">no value</text>"));
+
+        let svg = legend(&[1.0, 10.0], true).to_svg();
+        assert!(svg.contains(">erosion_thickness (log)</text>"));
+        let scale = ColorScale {
+            range: ColorRange::Fixed(1.0, 0.0),
+            ..ColorScale::default()
+        };
+        assert!(Legend::new("x", &scale, &[0.5]).is_err());
+    }
+
+    #[test]
+    fn png_layout() {
+        let legend = legend(&[0.0, 1.0, f32::INFINITY], false);
+        let mut png = Vec::new();
+        legend.to_image().write_png(&mut png).unwrap();
+        assert_eq!(png[..8], [0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A]);
+
+        // chunks, with their crc
+        let mut chunks = Vec::new();
+        let mut pos = 8;
+        while pos < png.len() {
+            let len = u32::from_be_bytes(png[pos..pos + 4].try_into().unwrap()) as usize;
+            let kind = &png[pos + 4..pos + 8];
+            let data = &png[pos + 8..pos + 8 + len];
+            let mut crc = flate2::Crc::new();
+            crc.update(kind);
+            crc.update(data);
+            let sum = u32::from_be_bytes(png[pos + 8 + len..pos + 12 + len].try_into().unwrap());
+            assert_eq!(crc.sum(), sum);
+            chunks.push((kind.to_vec(), data.to_vec()));
+            pos += 12 + len;
+        }
+        assert_eq!(pos, png.len());
+        let kinds: Vec<&[u8]> = chunks.iter().map(|(kind, _)| kind.as_slice()).collect();
+        assert_eq!(kinds, [b"IHDR", b"IDAT", b"IEND"]);
+        assert_eq!(chunks[0].1[..4], (WIDTH as u32).to_be_bytes());
+        assert_eq!(chunks[0].1[4..8], (HEIGHT as u32).to_be_bytes());
+
+        let mut raw = Vec::new();
+        flate2::read::ZlibDecoder::new(chunks[1].1.as_slice())
+            .read_to_end(&mut raw)
+            .unwrap();
+        assert_eq!(raw.len(), HEIGHT * (1 + 3 * WIDTH));
+        let pixel = |x: usize, y: usize| {
+            let at = y * (1 + 3 * WIDTH) + 1 + 3 * x;
+            [raw[at], raw[at + 1], raw[at + 2]]
+        };
+        let swatch_center = WIDTH - MARGIN - invalid_width() / 2;
+        assert_eq!(pixel(swatch_center, BAR_Y + BAR_HEIGHT / 2), [255, 0, 255]);
+        let bar_start = legend.bar_color(1.0 / (legend.bar_width() - 1) as f32);
+        assert_eq!(
+            pixel(MARGIN + 1, BAR_Y + BAR_HEIGHT / 2),
+            bar_start.unwrap()
+        );
+        assert_eq!(pixel(0, 0), [255; 3]);
+    }
+}
@@ -1,3 +1,17 @@
+pub mod colormap;
+pub mod components;
+pub mod compression;
+pub mod descriptor;
 pub mod erosion_path;
+pub mod gltf;
+pub mod graph;
 pub mod io;
+pub mod legend;
+pub mod npy;
+pub mod prune;
+pub mod repair;
 pub mod skeleton;
+pub mod subdivided_graph;
+pub mod topology;
+pub mod validation;
+pub mod vtk;
@@ -0,0 +1,389 @@
+use anyhow::Result;
+use flate2::Crc;
+use ndarray::{Array1, Array2, ArrayBase, Data, Dimension};
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+
+use super::compression;
+use super::subdivided_graph::SubdividedGraph;
+
+const NPY_MAGIC: &[u8] = b"\x93NUMPY";
+
+const ZIP_LOCAL_HEADER: u32 = 0x0403_4b50;
+const ZIP_CENTRAL_HEADER: u32 = 0x0201_4b50;
+const ZIP_END_OF_CENTRAL_DIRECTORY: u32 = 0x0605_4b50;
+
+/// Element type of a npy array
+pub trait NpyElement {
+    /// Numpy type string, e.g. `<f4`
+    const DESCR: &'static str;
+    fn write_le(&self, out: &mut Vec<u8>);
+}
+
+impl NpyElement for f32 {
+    const DESCR: &'static str = "<f4";
+    fn write_le(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&self.to_le_bytes());
+    }
+}
+
+impl NpyElement for i64 {
+    const DESCR: &'static str = "<i8";
+    fn write_le(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&self.to_le_bytes());
+    }
+}
+
+impl NpyElement for bool {
+    const DESCR: &'static str = "|b1";
+    fn write_le(&self, out: &mut Vec<u8>) {
+        out.push(u8::from(*self));
+    }
+}
+
+/// Content of a `.npy` file (format 1.0, C order) holding an array
+pub fn npy_bytes<A, S, D>(array: &ArrayBase<S, D>) -> Vec<u8>
+where
+    A: NpyElement,
+    S: Data<Elem = A>,
+    D: Dimension,
+{
+    let shape: Vec<String> = array.shape().iter().map(|n| n.to_string()).collect();
+    let shape = match shape.len() {
+        1 => format!("({},)", shape[0]),
+        _ => format!("({})", shape.join(", ")),
+    };
+    let mut header = format!(
+        "{{'descr': '{}', 'fortran_order': False, 'shape': {}, }}",
+        A::DESCR,
+        shape
+    );
+    // magic, version and header length take 10 bytes, the total being aligned to 64
+    while !(10 + header.len() + 1).is_multiple_of(64) {
+        header.push(' ');
+    }
+    header.push('\n');
+
+    let mut bytes = Vec::with_capacity(10 + header.len() + array.len() * 8);
+    bytes.extend_from_slice(NPY_MAGIC);
+    bytes.extend_from_slice(&[1, 0]);
+    bytes.extend_from_slice(&(header.len() as u16).to_le_bytes());
+    bytes.extend_from_slice(header.as_bytes());
+    for val in array.iter() {
+        val.write_le(&mut bytes);
+    }
+    bytes
+}
+
+fn option_values(values: &[Option<f32>]) -> Array1<f32> {
+    values
+        .iter()
+        .map(|val| val.unwrap_or(f32::INFINITY))
+        .collect()
+}
+
+/// Arrays of a subdivided graph, as named `.npy` contents
+///
+/// Vertex arrays have one row per graph vertex, skeleton vertices first (see `original`):
+/// `positions` (n, 3), `radii`, `burntime` and `erosion_thickness` (infinite if never
+/// burnt), `boundary`, `original`, and `prime_neighbor` (-1 if none). `faces` (f, k) holds
+/// the subdivided faces, padded with -1 up to the largest one.
+pub fn graph_arrays(graph: &SubdividedGraph) -> Result<Vec<(String, Vec<u8>)>> {
+    let vertices = graph.get_vertices();
+    let nb_vertices = vertices.len();
+    let positions = Array2::from_shape_vec(
+        (nb_vertices, 3),
+        vertices
+            .iter()
+            .flat_map(|pos| [pos.x, pos.y, pos.z])
+            .collect(),
+    )?;
+    let original: Array1<bool> = (0..nb_vertices).map(|i| i < graph.nb_original()).collect();
+    let prime_neighbor: Array1<i64> = graph
+        .get_prime_neighbor()
+        .iter()
+        .map(|prime| prime.map_or(-1, |v| v as i64))
+        .collect();
+
+    let faces = graph.get_faces();
+    let face_len = faces.iter().map(|face| face.len()).max().unwrap_or(0);
+    let mut face_indices = Array2::from_elem((faces.len(), face_len), -1i64);
+    for i in 0..faces.len() {
+        for j in 0..faces[i].len() {
+            face_indices[[i, j]] = faces[i][j] as i64;
+        }
+    }
+
+    Ok(vec![
+        ("positions".to_string(), npy_bytes(&positions)),
+        (
+            "radii".to_string(),
+            npy_bytes(&Array1::from_vec(graph.get_radii().clone())),
+        ),
+        (
+            "burntime".to_string(),
+            npy_bytes(&option_values(graph.get_burntime())),
+        ),
+        (
+            "erosion_thickness".to_string(),
+            npy_bytes(&option_values(graph.get_erosion_thickness())),
+        ),
+        (
+            "boundary".to_string(),
+            npy_bytes(&Array1::from_vec(graph.get_boundary().clone())),
+        ),
+        ("original".to_string(), npy_bytes(&original)),
+        ("prime_neighbor".to_string(), npy_bytes(&prime_neighbor)),
+        ("faces".to_string(), npy_bytes(&face_indices)),
+    ])
+}
+
+/// Writes files as an uncompressed zip archive, as `numpy.savez` does
+fn write_zip<W: Write>(out: &mut W, files: &[(String, Vec<u8>)]) -> Result<()> {
+    let mut central_directory = Vec::new();
+    let mut offset = 0usize;
+    for (name, data) in files.iter() {
+        if data.len() > u32::MAX as usize || offset > u32::MAX as usize {
+            return Err(anyhow::Error::msg(format!(
+                "Array {} too large for a npz archive, write npy files instead",
+                name
+            )));
+        }
+        let mut crc = Crc::new();
+        crc.update(data);
+
+        // fields shared by the local and central headers: version needed, flags,
+        // method (stored), time, date, crc, sizes, name length
+        let mut common = Vec::new();
+        common.extend_from_slice(&20u16.to_le_bytes());
+        common.extend_from_slice(&0u16.to_le_bytes());
+        common.extend_from_slice(&0u16.to_le_bytes());
+        common.extend_from_slice(&0u16.to_le_bytes());
+        common.extend_from_slice(&0x21u16.to_le_bytes());
+        common.extend_from_slice(&crc.sum().to_le_bytes());
+        common.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        common.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        common.extend_from_slice(&(name.len() as u16).to_le_bytes());
+
+        out.write_all(&ZIP_LOCAL_HEADER.to_le_bytes())?;
+        out.write_all(&common)?;
+        out.write_all(&0u16.to_le_bytes())?;
+        out.write_all(name.as_bytes())?;
+        out.write_all(data)?;
+
+        central_directory.extend_from_slice(&ZIP_CENTRAL_HEADER.to_le_bytes());
+        central_directory.extend_from_slice(&20u16.to_le_bytes());
+        central_directory.extend_from_slice(&common);
+        // extra and comment lengths, disk, internal and external attributes
+        central_directory.extend_from_slice(&[0; 12]);
+        central_directory.extend_from_slice(&(offset as u32).to_le_bytes());
+        central_directory.extend_from_slice(name.as_bytes());
+
+        offset += 30 + name.len() + data.len();
+    }
+    if offset > u32::MAX as usize {
+        return Err(anyhow::Error::msg(
+            "Arrays too large for a npz archive, write npy files instead",
+        ));
+    }
+    out.write_all(&central_directory)?;
+    out.write_all(&ZIP_END_OF_CENTRAL_DIRECTORY.to_le_bytes())?;
+    out.write_all(&[0; 4])?;
+    out.write_all(&(files.len() as u16).to_le_bytes())?;
+    out.write_all(&(files.len() as u16).to_le_bytes())?;
+    out.write_all(&(central_directory.len() as u32).to_le_bytes())?;
+    out.write_all(&(offset as u32).to_le_bytes())?;
+    out.write_all(&0u16.to_le_bytes())?;
+    Ok(())
+}
+
+/// Writes the arrays of a subdivided graph as a `.npz` archive, see `graph_arrays`
+pub fn export_graph_to_npz(graph: &SubdividedGraph, file_path: &str) -> Result<()> {
+    let files: Vec<(String, Vec<u8>)> = graph_arrays(graph)?
+        .into_iter()
+        .map(|(name, data)| (format!("{}.npy", name), data))
+        .collect();
+    let mut file = compression::create_writer(Path::new(file_path))?;
+    write_zip(&mut file, &files)?;
+    file.finish()
+}
+
+/// Writes the arrays of a subdivided graph as `.npy` files in a directory, see
+/// `graph_arrays`
+pub fn export_graph_to_npy_dir(graph: &SubdividedGraph, dir_path: &str) -> Result<()> {
+    let dir_path = Path::new(dir_path);
+    fs::create_dir_all(dir_path)?;
+    for (name, data) in graph_arrays(graph)? {
+        fs::write(dir_path.join(format!("{}.npy", name)), data)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nalgebra::Vector3;
+    use ndarray::array;
+
+    /// Header dictionary and data of a npy content, checking its magic and alignment
+    fn npy_parts(bytes: &[u8]) -> (&str, &[u8]) {
+        assert_eq!(&bytes[..6], NPY_MAGIC);
+        assert_eq!(&bytes[6..8], &[1, 0]);
+        let header_len = u16::from_le_bytes([bytes[8], bytes[9]]) as usize;
+        assert_eq!((10 + header_len) % 64, 0);
+        let header = std::str::from_utf8(&bytes[10..10 + header_len]).unwrap();
+        assert!(header.ends_with('\n'));
+        (header.trim_end(), &bytes[10 + header_len..])
+    }
+
+    fn u16_at(bytes: &[u8], offset: usize) -> u16 {
+        u16::from_le_bytes(bytes[offset..offset + 2].try_into().unwrap())
+    }
+
+    fn u32_at(bytes: &[u8], offset: usize) -> u32 {
+        u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap())
+    }
+
+    #[test]
+    fn npy_headers() {
+        let bytes = npy_bytes(&array![1.0f32, -2.0]);
+        let (header, data) = npy_parts(&bytes);
+        assert_eq!(
+            header,
+            "{'descr': '<f4', 'fortran_order': False, 'shape': (2,), }"
+        );
+        assert_eq!(data, [0, 0, 0x80, 0x3f, 0, 0, 0, 0xc0]);
+
+        let bytes = npy_bytes(&array![[1i64, 2, 3], [4, 5, -1]]);
+        let (header, data) = npy_parts(&bytes);
+        assert_eq!(
+            header,
+            "{'descr': '<i8', 'fortran_order': False, 'shape': (2, 3), }"
+        );
+        // C order
+        assert_eq!(data.len(), 48);
+        assert_eq!(&data[8..16], &2i64.to_le_bytes());
+        assert_eq!(&data[40..], &(-1i64).to_le_bytes());
+
+        let bytes = npy_bytes(&Array1::<bool>::from_vec(vec![true, false]));
+        let (header, data) = npy_parts(&bytes);
+        assert!(header.contains("'descr': '|b1'"));
+        assert!(header.contains("'shape': (2,)"));
+        assert_eq!(data, [1, 0]);
+
+        let bytes = npy_bytes(&Array1::<f32>::from_vec(Vec::new()));
+        let (header, data) = npy_parts(&bytes);
+        assert!(header.contains("'shape': (0,)"));
+        assert!(data.is_empty());
+    }
+
+    #[test]
+    fn stored_zip_layout() {
+        let files = vec![
+            ("a.npy".to_string(), b"123456789".to_vec()),
+            ("bc.npy".to_string(), Vec::new()),
+        ];
+        let mut zip = Vec::new();
+        write_zip(&mut zip, &files).unwrap();
+
+        // local headers, then data
+        assert_eq!(u32_at(&zip, 0), ZIP_LOCAL_HEADER);
+        assert_eq!(u16_at(&zip, 8), 0);
+        // CRC-32 check value
+        assert_eq!(u32_at(&zip, 14), 0xcbf4_3926);
+        assert_eq!(u32_at(&zip, 18), 9);
+        assert_eq!(u32_at(&zip, 22), 9);
+        assert_eq!(u16_at(&zip, 26), 5);
+        assert_eq!(&zip[30..35], b"a.npy");
+        assert_eq!(&zip[35..44], b"123456789");
+        let second = 44;
+        assert_eq!(u32_at(&zip, second), ZIP_LOCAL_HEADER);
+        assert_eq!(u32_at(&zip, second + 14), 0);
+        assert_eq!(&zip[second + 30..second + 36], b"bc.npy");
+
+        // central directory, pointing to the local headers
+        let directory = second + 36;
+        assert_eq!(u32_at(&zip, directory), ZIP_CENTRAL_HEADER);
+        assert_eq!(u32_at(&zip, directory + 16), 0xcbf4_3926);
+        assert_eq!(u32_at(&zip, directory + 42), 0);
+        assert_eq!(&zip[directory + 46..directory + 51], b"a.npy");
+        let entry = directory + 51;
+        assert_eq!(u32_at(&zip, entry), ZIP_CENTRAL_HEADER);
+        assert_eq!(u32_at(&zip, entry + 42), second as u32);
+
+        let end = entry + 52;
+        assert_eq!(u32_at(&zip, end), ZIP_END_OF_CENTRAL_DIRECTORY);
+        assert_eq!(u16_at(&zip, end + 8), 2);
+        assert_eq!(u16_at(&zip, end + 10), 2);
+        assert_eq!(u32_at(&zip, end + 12), (end - directory) as u32);
+        assert_eq!(u32_at(&zip, end + 16), directory as u32);
+        assert_eq!(zip.len(), end + 22);
+    }
+
+    #[test]
+    fn arrays_of_the_subdivided_graph() {
+        let mut graph = SubdividedGraph::new(3);
+        for (i, boundary) in [true, false, false, false].into_iter().enumerate() {
+            let burnt = (i < 3).then_some(i as f32);
+            let prime = (i > 0 && i < 3).then_some(0);
+            graph.add_vertex(
+                Vector3::new(i as f32, 0.0, 1.0),
+                0.5,
+                burnt,
+                burnt,
+                boundary,
+                prime,
+            );
+        }
+        graph.add_face(vec![0, 1, 3]);
+        graph.add_face(vec![1, 2, 3, 0]);
+        let arrays = graph_arrays(&graph).unwrap();
+        let names: Vec<&str> = arrays.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(
+            names,
+            [
+                "positions",
+                "radii",
+                "burntime",
+                "erosion_thickness",
+                "boundary",
+                "original",
+                "prime_neighbor",
+                "faces"
+            ]
+        );
+        let header = |name: &str| {
+            npy_parts(&arrays.iter().find(|a| a.0 == name).unwrap().1)
+                .0
+                .to_string()
+        };
+        let data = |name: &str| {
+            npy_parts(&arrays.iter().find(|a| a.0 == name).unwrap().1)
+                .1
+                .to_vec()
+        };
+
+        assert!(header("positions").contains("'shape': (4, 3)"));
+        let burntime: Vec<f32> = data("burntime")
+            .chunks(4)
+            .map(|b| f32::from_le_bytes(b.try_into().unwrap()))
+            .collect();
+        assert_eq!(burntime, [0.0, 1.0, 2.0, f32::INFINITY]);
+        assert_eq!(data("original"), [1, 1, 1, 0]);
+        assert_eq!(data("boundary"), [1, 0, 0, 0]);
+        let prime: Vec<i64> = data("prime_neighbor")
+            .chunks(8)
+            .map(|b| i64::from_le_bytes(b.try_into().unwrap()))
+            .collect();
+        assert_eq!(prime, [-1, 0, 0, -1]);
+
+        // faces padded with -1 up to the largest one
+        assert!(header("faces").contains("'shape': (2, 4)"));
+        let faces: Vec<i64> = data("faces")
+            .chunks(8)
+            .map(|b| i64::from_le_bytes(b.try_into().unwrap()))
+            .collect();
+        assert_eq!(faces, [0, 1, 3, -1, 1, 2, 3, 0]);
+    }
+}
@@ -0,0 +1,50 @@
+use anyhow::Result;
+use std::fmt;
+
+use super::skeleton::Skeleton;
+
+/// Number of elements removed by pruning
+pub struct PruneSummary {
+    pub removed_faces: usize,
+    pub removed_vertices: usize,
+}
+
+impl fmt::Display for PruneSummary {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} removed faces, {} removed vertices",
+            self.removed_faces, self.removed_vertices
+        )
+    }
+}
+
+impl Skeleton {
+    /// Removes faces with a vertex whose float property `prop_name` is below `threshold`,
+    /// then vertices used by no remaining face
+    pub fn prune_below(&mut self, prop_name: &str, threshold: f32) -> Result<PruneSummary> {
+        let values = self.get_property_f32(prop_name)?;
+        let nb_faces = self.get_face_vertices().len();
+        let faces: Vec<(usize, Vec<usize>)> = self
+            .get_face_vertices()
+            .iter()
+            .enumerate()
+            .filter(|(_, face)| {
+                face.iter()
+                    .all(|&v| values.get(v).is_some_and(|&val| val >= threshold))
+            })
+            .map(|(i, face)| (i, face.clone()))
+            .collect();
+
+        let removed_faces = nb_faces - faces.len();
+        if removed_faces != 0 {
+            let vertex_sources: Vec<usize> = (0..values.len()).collect();
+            self.rebuild(&vertex_sources, faces)?;
+        }
+        let removed_vertices = self.remove_unreferenced_vertices()?;
+        Ok(PruneSummary {
+            removed_faces,
+            removed_vertices,
+        })
+    }
+}
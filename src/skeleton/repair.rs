@@ -0,0 +1,453 @@
+use anyhow::Result;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+use super::skeleton::Skeleton;
+use super::topology::Topology;
+
+/// Number of elements modified by each repair operation
+#[derive(Default)]
+pub struct RepairSummary {
+    pub welded_vertices: usize,
+    pub degenerate_faces: usize,
+    pub duplicate_faces: usize,
+    pub unreferenced_vertices: usize,
+    pub flipped_faces: usize,
+}
+
+impl fmt::Display for RepairSummary {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} welded vertices, {} degenerate faces, {} duplicate faces, {} unreferenced vertices, {} flipped faces",
+            self.welded_vertices,
+            self.degenerate_faces,
+            self.duplicate_faces,
+            self.unreferenced_vertices,
+            self.flipped_faces
+        )
+    }
+}
+
+/// Smallest rotation of a face boundary, over both orientations
+fn cyclic_key(face: &[usize]) -> Vec<usize> {
+    let n = face.len();
+    let mut key = face.to_vec();
+    for start in 0..n {
+        let forward: Vec<usize> = (0..n).map(|k| face[(start + k) % n]).collect();
+        let backward: Vec<usize> = (0..n).map(|k| face[(start + n - k) % n]).collect();
+        key = key.min(forward).min(backward);
+    }
+    key
+}
+
+impl Skeleton {
+    fn indexed_faces(&self) -> Vec<(usize, Vec<usize>)> {
+        self.get_face_vertices()
+            .iter()
+            .cloned()
+            .enumerate()
+            .collect()
+    }
+
+    /// Merges vertices closer than `tolerance` into the first of them (lowest index)
+    ///
+    /// Faces must refer to existing vertices, see `remove_degenerate_faces`.
+    /// Returns the number of removed vertices.
+    pub fn weld_vertices(&mut self, tolerance: f32) -> Result<usize> {
+        self.check_faceless_edges()?;
+        let vertices = self.get_vertices();
+        let cell_size = if tolerance > 0.0 { tolerance } else { 1.0 };
+        let cell = |i: usize| -> [i64; 3] {
+            let p = vertices[i] / cell_size;
+            [p.x.floor() as i64, p.y.floor() as i64, p.z.floor() as i64]
+        };
+
+        // representatives, stored in a grid
+        let mut grid: HashMap<[i64; 3], Vec<usize>> = HashMap::new();
+        let mut representative = Vec::with_capacity(vertices.len());
+        let mut vertex_sources = Vec::new();
+        for i in 0..vertices.len() {
+            let mut rep = None;
+            if vertices[i].iter().all(|c| c.is_finite()) {
+                let [cx, cy, cz] = cell(i);
+                'search: for dx in -1..=1 {
+                    for dy in -1..=1 {
+                        for dz in -1..=1 {
+                            if let Some(cands) = grid.get(&[cx + dx, cy + dy, cz + dz]) {
+                                if let Some((_, new_ind)) = cands
+                                    .iter()
+                                    .map(|&c| (c, representative[c]))
+                                    .find(|&(c, _)| (vertices[c] - vertices[i]).norm() <= tolerance)
+                                {
+                                    rep = Some(new_ind);
+                                    break 'search;
+                                }
+                            }
+                        }
+                    }
+                }
+                if rep.is_none() {
+                    grid.entry([cx, cy, cz]).or_default().push(i);
+                }
+            }
+            representative.push(rep.unwrap_or_else(|| {
+                vertex_sources.push(i);
+                vertex_sources.len() - 1
+            }));
+        }
+
+        let nb_welded = vertices.len() - vertex_sources.len();
+        if nb_welded != 0 {
+            let faces = self
+                .indexed_faces()
+                .into_iter()
+                .map(|(i, face)| (i, face.iter().map(|&v| representative[v]).collect()))
+                .collect();
+            // rebuilding only keeps edges between copied vertices
+            let edges: Vec<[usize; 2]> = self
+                .get_faceless_edges()
+                .into_iter()
+                .map(|e| self.get_edges()[e].map(|v| representative[v]))
+                .filter(|[v1, v2]| v1 != v2)
+                .collect();
+            self.rebuild(&vertex_sources, faces)?;
+            for edge in edges {
+                self.add_edge(edge);
+            }
+        }
+        Ok(nb_welded)
+    }
+
+    /// Removes repeated consecutive vertices in faces, then faces with less than
+    /// three distinct vertices, with a repeated vertex or referring to a missing vertex
+    ///
+    /// Returns the number of removed faces.
+    pub fn remove_degenerate_faces(&mut self) -> Result<usize> {
+        let nb_vertices = self.get_vertices().len();
+        let nb_faces = self.get_face_vertices().len();
+        let mut changed = false;
+        let faces: Vec<(usize, Vec<usize>)> = self
+            .indexed_faces()
+            .into_iter()
+            .filter_map(|(i, mut face)| {
+                let len_init = face.len();
+                face.dedup();
+                while face.len() > 1 && face.first() == face.last() {
+                    face.pop();
+                }
+                changed = changed || face.len() != len_init;
+                let distinct: HashSet<usize> = face.iter().copied().collect();
+                if distinct.len() >= 3
+                    && distinct.len() == face.len()
+                    && face.iter().all(|&v| v < nb_vertices)
+                {
+                    Some((i, face))
+                } else {
+                    None
+                }
+            })
+            .collect();
+
+        let nb_removed = nb_faces - faces.len();
+        if changed || nb_removed != 0 {
+            let vertex_sources: Vec<usize> = (0..nb_vertices).collect();
+            self.rebuild(&vertex_sources, faces)?;
+        }
+        Ok(nb_removed)
+    }
+
+    /// Removes faces with the same boundary as a previous one, whatever their starting
+    /// vertex and orientation
+    ///
+    /// Faces using the same vertices in another cyclic order are distinct polygons and
+    /// are kept. Returns the number of removed faces.
+    pub fn remove_duplicate_faces(&mut self) -> Result<usize> {
+        let nb_faces = self.get_face_vertices().len();
+        let mut seen = HashSet::new();
+        let faces: Vec<(usize, Vec<usize>)> = self
+            .indexed_faces()
+            .into_iter()
+            .filter(|(_, face)| seen.insert(cyclic_key(face)))
+            .collect();
+
+        let nb_removed = nb_faces - faces.len();
+        if nb_removed != 0 {
+            let vertex_sources: Vec<usize> = (0..self.get_vertices().len()).collect();
+            self.rebuild(&vertex_sources, faces)?;
+        }
+        Ok(nb_removed)
+    }
+
+    /// Removes vertices used by no face nor edge, and compacts vertex indices
+    ///
+    /// Faces must refer to existing vertices, see `remove_degenerate_faces`.
+    /// Returns the number of removed vertices.
+    pub fn remove_unreferenced_vertices(&mut self) -> Result<usize> {
+        self.check_faceless_edges()?;
+        let nb_vertices = self.get_vertices().len();
+        let mut referenced = vec![false; nb_vertices];
+        for face in self.get_face_vertices().iter() {
+            for &v in face.iter() {
+                referenced[v] = true;
+            }
+        }
+        for &[v1, v2] in self.get_edges().iter() {
+            referenced[v1] = true;
+            referenced[v2] = true;
+        }
+        let mut new_index = vec![0; nb_vertices];
+        let mut vertex_sources = Vec::new();
+        for i in 0..nb_vertices {
+            if referenced[i] {
+                new_index[i] = vertex_sources.len();
+                vertex_sources.push(i);
+            }
+        }
+
+        let nb_removed = nb_vertices - vertex_sources.len();
+        if nb_removed != 0 {
+            let faces = self
+                .indexed_faces()
+                .into_iter()
+                .map(|(i, face)| (i, face.iter().map(|&v| new_index[v]).collect()))
+                .collect();
+            self.rebuild(&vertex_sources, faces)?;
+        }
+        Ok(nb_removed)
+    }
+
+    /// Makes face orientations consistent across manifold edges (shared by two faces)
+    ///
+    /// Orientation is propagated from the first face of each connected set of faces,
+    /// non manifold edges do not propagate it. Returns the number of flipped faces.
+    pub fn orient_faces(&mut self) -> Result<usize> {
+        let topo = Topology::new(self)?;
+        let nb_faces = topo.nb_faces();
+        let mut flip: Vec<Option<bool>> = vec![None; nb_faces];
+        for first_face in 0..nb_faces {
+            if flip[first_face].is_some() {
+                continue;
+            }
+            flip[first_face] = Some(false);
+            let mut to_visit = vec![first_face];
+            while let Some(f) = to_visit.pop() {
+                for ind_he in topo.face_half_edges(f) {
+                    let he = topo.half_edge(ind_he);
+                    if topo.edge_valence(he.edge) != 2 {
+                        continue;
+                    }
+                    let he_opp = topo.half_edge(he.radial_next);
+                    // consistent faces go through their common edge in opposite directions
+                    let same_direction = he.origin == he_opp.origin;
+                    if flip[he_opp.face].is_none() {
+                        flip[he_opp.face] = Some(flip[f].unwrap() ^ same_direction);
+                        to_visit.push(he_opp.face);
+                    }
+                }
+            }
+        }
+
+        let nb_flipped = flip.iter().filter(|&&fl| fl == Some(true)).count();
+        if nb_flipped != 0 {
+            let faces = self
+                .indexed_faces()
+                .into_iter()
+                .map(|(i, mut face)| {
+                    if flip[i] == Some(true) {
+                        face.reverse();
+                    }
+                    (i, face)
+                })
+                .collect();
+            let vertex_sources: Vec<usize> = (0..self.get_vertices().len()).collect();
+            self.rebuild(&vertex_sources, faces)?;
+        }
+        Ok(nb_flipped)
+    }
+
+    /// Applies all repair operations, welding vertices closer than `tolerance`
+    pub fn repair(&mut self, tolerance: f32) -> Result<RepairSummary> {
+        // welding may create new degenerate faces
+        let mut degenerate_faces = self.remove_degenerate_faces()?;
+        let welded_vertices = self.weld_vertices(tolerance)?;
+        degenerate_faces += self.remove_degenerate_faces()?;
+        let duplicate_faces = self.remove_duplicate_faces()?;
+        let unreferenced_vertices = self.remove_unreferenced_vertices()?;
+        let flipped_faces = self.orient_faces()?;
+        Ok(RepairSummary {
+            welded_vertices,
+            degenerate_faces,
+            duplicate_faces,
+            unreferenced_vertices,
+            flipped_faces,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nalgebra::base::*;
+    use ply_rs::ply::Property;
+
+    /// Skeleton whose vertices and faces carry their initial index as `id` property, and
+    /// whose vertex radii are a tenth of it
+    fn skeleton(positions: &[[f32; 3]], faces: &[Vec<usize>]) -> Skeleton {
+        let mut skel = Skeleton::new();
+        for (i, &pos) in positions.iter().enumerate() {
+            let properties = HashMap::from([("id".to_string(), Property::Float(i as f32))]);
+            skel.add_vertex(Vector3::from(pos), i as f32 / 10.0, properties);
+        }
+        for (i, face) in faces.iter().enumerate() {
+            let properties = HashMap::from([("id".to_string(), Property::Int(i as i32))]);
+            skel.add_face(face.clone(), properties);
+        }
+        skel
+    }
+
+    fn vertex_ids(skel: &Skeleton) -> Vec<f32> {
+        skel.get_property_f32("id").unwrap()
+    }
+
+    fn face_ids(skel: &Skeleton) -> Vec<i32> {
+        skel.face_payload_element()
+            .iter()
+            .map(|face| match face["id"] {
+                Property::Int(id) => id,
+                _ => panic!("face id is not an int"),
+            })
+            .collect()
+    }
+
+    fn assert_consistent(skel: &Skeleton) {
+        let ids = vertex_ids(skel);
+        for i in 0..ids.len() {
+            assert_eq!(skel.get_radii()[i], ids[i] / 10.0);
+        }
+    }
+
+    #[test]
+    fn weld_two_triangles() {
+        // the second triangle repeats vertices 1 and 2
+        let mut skel = skeleton(
+            &[
+                [0.0, 0.0, 0.0],
+                [1.0, 0.0, 0.0],
+                [0.0, 1.0, 0.0],
+                [1.0, 0.0, 0.001],
+                [1.0, 1.0, 0.0],
+                [0.0, 1.0, 0.001],
+            ],
+            &[vec![0, 1, 2], vec![3, 4, 5]],
+        );
+        assert_eq!(skel.weld_vertices(0.01).unwrap(), 2);
+        assert_eq!(vertex_ids(&skel), vec![0.0, 1.0, 2.0, 4.0]);
+        assert_consistent(&skel);
+        assert_eq!(
+            skel.get_face_vertices(),
+            &vec![vec![0, 1, 2], vec![1, 3, 2]]
+        );
+        assert_eq!(face_ids(&skel), vec![0, 1]);
+        assert_eq!(skel.get_edges().len(), 5);
+    }
+
+    #[test]
+    fn remove_degenerate_faces_keeps_properties() {
+        let positions = [
+            [0.0, 0.0, 0.0],
+            [1.0, 0.0, 0.0],
+            [0.0, 1.0, 0.0],
+            [1.0, 1.0, 0.0],
+        ];
+        let mut skel = skeleton(
+            &positions,
+            &[
+                vec![0, 1, 2],
+                vec![0, 0, 1],
+                vec![2, 1, 1, 3],
+                vec![1, 3, 7],
+            ],
+        );
+        assert_eq!(skel.remove_degenerate_faces().unwrap(), 2);
+        assert_eq!(vertex_ids(&skel), vec![0.0, 1.0, 2.0, 3.0]);
+        assert_consistent(&skel);
+        assert_eq!(
+            skel.get_face_vertices(),
+            &vec![vec![0, 1, 2], vec![2, 1, 3]]
+        );
+        assert_eq!(face_ids(&skel), vec![0, 2]);
+    }
+
+    #[test]
+    fn remove_unreferenced_vertices_compacts_indices() {
+        let positions = [
+            [0.0, 0.0, 0.0],
+            [1.0, 0.0, 0.0],
+            [0.0, 1.0, 0.0],
+            [5.0, 5.0, 5.0],
+            [1.0, 1.0, 0.0],
+        ];
+        let mut skel = skeleton(&positions, &[vec![1, 4, 2]]);
+        assert_eq!(skel.remove_unreferenced_vertices().unwrap(), 2);
+        assert_eq!(vertex_ids(&skel), vec![1.0, 2.0, 4.0]);
+        assert_consistent(&skel);
+        assert_eq!(skel.get_vertices()[2], Vector3::new(1.0, 1.0, 0.0));
+        assert_eq!(skel.get_face_vertices(), &vec![vec![0, 2, 1]]);
+        assert_eq!(face_ids(&skel), vec![0]);
+    }
+
+    #[test]
+    fn orient_flipped_face() {
+        let positions = [
+            [0.0, 0.0, 0.0],
+            [1.0, 0.0, 0.0],
+            [0.0, 1.0, 0.0],
+            [1.0, 1.0, 0.0],
+        ];
+        // both faces go from 1 to 2
+        let mut skel = skeleton(&positions, &[vec![0, 1, 2], vec![1, 2, 3]]);
+        assert_eq!(skel.orient_faces().unwrap(), 1);
+        assert_eq!(vertex_ids(&skel), vec![0.0, 1.0, 2.0, 3.0]);
+        assert_consistent(&skel);
+        assert_eq!(
+            skel.get_face_vertices(),
+            &vec![vec![0, 1, 2], vec![3, 2, 1]]
+        );
+        assert_eq!(face_ids(&skel), vec![0, 1]);
+        assert_eq!(skel.orient_faces().unwrap(), 0);
+    }
+
+    #[test]
+    fn remove_duplicate_faces_compares_cycles() {
+        let positions = [
+            [0.0, 0.0, 0.0],
+            [1.0, 0.0, 0.0],
+            [1.0, 1.0, 0.0],
+            [0.0, 1.0, 0.0],
+        ];
+        let mut skel = skeleton(
+            &positions,
+            &[
+                vec![0, 1, 2, 3],
+                // same vertices, another polygon
+                vec![0, 2, 1, 3],
+                // rotation and reversals of the first face
+                vec![2, 3, 0, 1],
+                vec![3, 2, 1, 0],
+                vec![1, 0, 3, 2],
+                // reversal of the second face
+                vec![3, 1, 2, 0],
+            ],
+        );
+        assert_eq!(skel.remove_duplicate_faces().unwrap(), 4);
+        assert_eq!(vertex_ids(&skel), vec![0.0, 1.0, 2.0, 3.0]);
+        assert_consistent(&skel);
+        assert_eq!(
+            skel.get_face_vertices(),
+            &vec![vec![0, 1, 2, 3], vec![0, 2, 1, 3]]
+        );
+        assert_eq!(face_ids(&skel), vec![0, 1]);
+        assert_eq!(skel.remove_duplicate_faces().unwrap(), 0);
+    }
+}
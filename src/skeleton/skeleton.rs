@@ -2,6 +2,7 @@ use anyhow::Result;
 use nalgebra::base::*;
 use std::collections::HashMap;
 
+use super::colormap::{self, ColorScale};
 use ply_rs::ply::{
     Addable, DefaultElement, ElementDef, Property, PropertyDef, PropertyType, ScalarType,
 };
@@ -18,6 +19,7 @@ pub struct Skeleton {
     faces_properties: Vec<HashMap<String, Property>>,
     vertex_to_edges: HashMap<usize, Vec<usize>>,
     edge_to_faces: HashMap<usize, Vec<usize>>,
+    comments: Vec<String>,
 }
 
 pub(super) fn property_to_propertytype(prop: &Property) -> PropertyType {
@@ -41,6 +43,12 @@ pub(super) fn property_to_propertytype(prop: &Property) -> PropertyType {
     }
 }
 
+impl Default for Skeleton {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Skeleton {
     pub fn new() -> Self {
         Skeleton {
@@ -55,6 +63,7 @@ impl Skeleton {
             faces_properties: Vec::new(),
             vertex_to_edges: HashMap::new(),
             edge_to_faces: HashMap::new(),
+            comments: Vec::new(),
         }
     }
 
@@ -127,7 +136,6 @@ impl Skeleton {
     ) -> usize {
         self.vertex_coords.push(position);
         self.vertex_radius.push(radius);
-        self.vertex_properties.push(properties.clone());
         self.vertex_property_types
             .insert("x".to_string(), PropertyType::Scalar(ScalarType::Float));
         self.vertex_property_types
@@ -138,14 +146,43 @@ impl Skeleton {
             "radius".to_string(),
             PropertyType::Scalar(ScalarType::Float),
         );
-        for (key, prop) in properties {
-            let ptype = property_to_propertytype(&prop);
-            self.vertex_property_types.insert(key.clone(), ptype);
+        for (key, prop) in properties.iter() {
+            if !self.vertex_property_types.contains_key(key) {
+                let ptype = property_to_propertytype(prop);
+                self.vertex_property_types.insert(key.clone(), ptype);
+            }
         }
+        self.vertex_properties.push(properties);
 
         self.vertex_coords.len() - 1
     }
 
+    /// Ply element of the edges of no face
+    pub(super) fn edge_header_element(&self) -> ElementDef {
+        let mut edge_element = ElementDef::new("edge".to_string());
+        for key in ["vertex1", "vertex2"] {
+            edge_element.properties.add(PropertyDef::new(
+                key.to_string(),
+                PropertyType::Scalar(ScalarType::Int),
+            ));
+        }
+        edge_element
+    }
+
+    pub(super) fn edge_payload_element(&self) -> Vec<DefaultElement> {
+        let mut edges = Vec::new();
+
+        for edge_index in self.get_faceless_edges() {
+            let mut edge = DefaultElement::new();
+            let [v1, v2] = self.edges[edge_index];
+            edge.insert("vertex1".to_string(), Property::Int(v1 as i32));
+            edge.insert("vertex2".to_string(), Property::Int(v2 as i32));
+            edges.push(edge);
+        }
+
+        edges
+    }
+
     pub(super) fn add_edge(&mut self, vertex_indices: [usize; 2]) -> usize {
         if let Some(edge_indices) = self.get_edges_from_vertex(vertex_indices[0]) {
             for &edge_index in edge_indices.iter() {
@@ -165,7 +202,7 @@ impl Skeleton {
         for vertex_index in &vertex_indices {
             self.vertex_to_edges
                 .entry(*vertex_index)
-                .or_insert(Vec::new())
+                .or_default()
                 .push(edge_index);
         }
 
@@ -191,21 +228,74 @@ impl Skeleton {
             PropertyType::List(ScalarType::UChar, ScalarType::UInt),
         );
         for (key, prop) in properties.iter() {
-            let ptype = property_to_propertytype(&prop);
+            let ptype = property_to_propertytype(prop);
             self.face_property_types.insert(key.clone(), ptype);
         }
 
         for edge_index in &edge_indices {
             self.edge_to_faces
                 .entry(*edge_index)
-                .or_insert(Vec::new())
-                .push(self.face_edges.len() - 1);
+                .or_default()
+                .push(self.face_edges.len());
         }
         self.face_edges.push(edge_indices);
         self.faces_properties.push(properties);
     }
 
-    pub fn set_property_f32(&mut self, prop_name: &str, prop_value: &Vec<f32>) -> Result<()> {
+    /// Rebuilds the skeleton from a selection of its elements, keeping their properties
+    ///
+    /// `vertex_sources` gives, for each new vertex, the index of the vertex it is copied from.
+    /// `faces` gives, for each new face, the index of the face its properties are copied from,
+    /// and its vertices (new indices). Edges of no face are kept if both their vertices are
+    /// copied, and must refer to existing vertices.
+    pub(super) fn rebuild(
+        &mut self,
+        vertex_sources: &[usize],
+        faces: Vec<(usize, Vec<usize>)>,
+    ) -> Result<()> {
+        self.check_faceless_edges()?;
+        let mut skel = Skeleton::new();
+        let mut new_index = vec![None; self.vertex_coords.len()];
+        for (new_ind, &i) in vertex_sources.iter().enumerate() {
+            skel.add_vertex(
+                self.vertex_coords[i],
+                self.vertex_radius[i],
+                self.vertex_properties[i].clone(),
+            );
+            new_index[i].get_or_insert(new_ind);
+        }
+        for (i, vertex_indices) in faces {
+            skel.add_face(vertex_indices, self.faces_properties[i].clone());
+        }
+        for edge_index in self.get_faceless_edges() {
+            let [v1, v2] = self.edges[edge_index];
+            if let (Some(n1), Some(n2)) = (new_index[v1], new_index[v2]) {
+                skel.add_edge([n1, n2]);
+            }
+        }
+        skel.vertex_property_types = self.vertex_property_types.clone();
+        skel.face_property_types = self.face_property_types.clone();
+        skel.comments = std::mem::take(&mut self.comments);
+        *self = skel;
+        Ok(())
+    }
+
+    /// Checks that edges of no face refer to existing vertices
+    pub(super) fn check_faceless_edges(&self) -> Result<()> {
+        let nb_vertices = self.vertex_coords.len();
+        for edge_index in self.get_faceless_edges() {
+            let [v1, v2] = self.edges[edge_index];
+            if v1 >= nb_vertices || v2 >= nb_vertices {
+                return Err(anyhow::Error::msg(format!(
+                    "Edge ({}, {}) out of range, {} vertices",
+                    v1, v2, nb_vertices
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn set_property_f32(&mut self, prop_name: &str, prop_value: &[f32]) -> Result<()> {
         if prop_value.len() != self.vertex_properties.len() {
             Err(anyhow::Error::msg(
                 "Number of vertices and properties does not match",
@@ -223,58 +313,105 @@ impl Skeleton {
         }
     }
 
-    pub fn set_vertex_color_from_property_f32(&mut self, prop_name: &str) -> Result<()> {
-        if !self.vertex_property_types.contains_key(prop_name) {
-            return Err(anyhow::Error::msg("Property does not exist"));
+    pub fn set_property_i32(&mut self, prop_name: &str, prop_value: &[i32]) -> Result<()> {
+        if prop_value.len() != self.vertex_properties.len() {
+            Err(anyhow::Error::msg(
+                "Number of vertices and properties does not match",
+            ))
+        } else {
+            self.vertex_property_types
+                .insert(prop_name.to_string(), PropertyType::Scalar(ScalarType::Int));
+            for i in 0..self.vertex_properties.len() {
+                self.vertex_properties[i]
+                    .insert(prop_name.to_string(), Property::Int(prop_value[i]));
+            }
+            Ok(())
         }
-        if *self.vertex_property_types.get(prop_name).unwrap()
-            != PropertyType::Scalar(ScalarType::Float)
-        {
-            return Err(anyhow::Error::msg("Property is not a float"));
+    }
+
+    pub fn set_face_property_i32(&mut self, prop_name: &str, prop_value: &[i32]) -> Result<()> {
+        if prop_value.len() != self.faces_properties.len() {
+            Err(anyhow::Error::msg(
+                "Number of faces and properties does not match",
+            ))
+        } else {
+            self.face_property_types
+                .insert(prop_name.to_string(), PropertyType::Scalar(ScalarType::Int));
+            for i in 0..self.faces_properties.len() {
+                self.faces_properties[i]
+                    .insert(prop_name.to_string(), Property::Int(prop_value[i]));
+            }
+            Ok(())
         }
-        let (min_p, max_p) = self
-            .vertex_properties
-            .iter()
-            .fold(None, |val, vert_prop| {
-                let v_cur = vert_prop.get(prop_name).unwrap();
-                let v_cur = if let Property::Float(v) = v_cur {
-                    *v
-                } else {
-                    panic!()
-                };
-                if let Some((v_min, v_max)) = val {
-                    let v_min = if v_min < v_cur { v_min } else { v_cur };
-                    let v_max = if v_max > v_cur { v_max } else { v_cur };
-                    Some((v_min, v_max))
-                } else {
-                    Some((v_cur, v_cur))
-                }
-            })
-            .unwrap();
+    }
 
-        self.vertex_property_types
-            .insert("red".to_string(), PropertyType::Scalar(ScalarType::UChar));
-        self.vertex_property_types
-            .insert("green".to_string(), PropertyType::Scalar(ScalarType::UChar));
-        self.vertex_property_types
-            .insert("blue".to_string(), PropertyType::Scalar(ScalarType::UChar));
-        for prop in self.vertex_properties.iter_mut() {
-            let p_cur = prop.get(prop_name).unwrap();
-            let p_cur = if let Property::Float(p) = p_cur {
-                p
-            } else {
-                panic!()
-            };
-            let factor = (p_cur - min_p) / (max_p - min_p);
-            let r = (255.0 * factor) as u8;
-            let g = 0 as u8;
-            let b = (255.0 * (1.0 - factor)) as u8;
-            prop.insert("red".to_string(), Property::UChar(r));
-            prop.insert("green".to_string(), Property::UChar(g));
-            prop.insert("blue".to_string(), Property::UChar(b));
+    /// Values of a float vertex property, `radius` included
+    pub fn get_property_f32(&self, prop_name: &str) -> Result<Vec<f32>> {
+        if prop_name == "radius" {
+            return Ok(self.vertex_radius.clone());
         }
+        colormap::property_values_f32(
+            &self.vertex_property_types,
+            &self.vertex_properties,
+            prop_name,
+        )
+    }
 
-        Ok(())
+    /// Removes a vertex property, if it exists
+    pub fn remove_vertex_property(&mut self, prop_name: &str) {
+        if self.vertex_property_types.remove(prop_name).is_some() {
+            for prop in self.vertex_properties.iter_mut() {
+                prop.remove(prop_name);
+            }
+        }
+    }
+
+    /// Ply header comments, written on export
+    pub fn get_comments(&self) -> &Vec<String> {
+        &self.comments
+    }
+
+    pub fn add_comment(&mut self, comment: &str) {
+        self.comments.push(comment.to_string());
+    }
+
+    /// Keeps only the comments for which `keep` returns true
+    pub fn retain_comments<F: FnMut(&String) -> bool>(&mut self, keep: F) {
+        self.comments.retain(keep);
+    }
+
+    /// Sets vertex colors from a float property, blue to red over its range
+    pub fn set_vertex_color_from_property_f32(&mut self, prop_name: &str) -> Result<()> {
+        self.set_vertex_color_with_scale(prop_name, &ColorScale::default())
+    }
+
+    /// Sets vertex colors from a float property
+    pub fn set_vertex_color_with_scale(
+        &mut self,
+        prop_name: &str,
+        scale: &ColorScale,
+    ) -> Result<()> {
+        let values = self.get_property_f32(prop_name)?;
+        self.set_vertex_color_from_values(&values, scale)
+    }
+
+    /// Sets vertex colors from one value per vertex
+    pub fn set_vertex_color_from_values(
+        &mut self,
+        values: &[f32],
+        scale: &ColorScale,
+    ) -> Result<()> {
+        if values.len() != self.vertex_properties.len() {
+            return Err(anyhow::Error::msg(
+                "Number of vertices and values does not match",
+            ));
+        }
+        colormap::set_vertex_colors(
+            &mut self.vertex_property_types,
+            &mut self.vertex_properties,
+            values,
+            scale,
+        )
     }
 
     pub fn get_vertices(&self) -> &Vec<Vector3<f32>> {
@@ -289,10 +426,21 @@ impl Skeleton {
         &self.edges
     }
 
+    /// Edges belonging to no face, 1D parts of the skeleton
+    pub fn get_faceless_edges(&self) -> Vec<usize> {
+        (0..self.edges.len())
+            .filter(|i| !self.edge_to_faces.contains_key(i))
+            .collect()
+    }
+
     pub fn get_faces(&self) -> &Vec<Vec<usize>> {
         &self.face_edges
     }
 
+    pub fn get_face_vertices(&self) -> &Vec<Vec<usize>> {
+        &self.face_vertices
+    }
+
     pub fn get_edges_from_vertex(&self, vertex_index: usize) -> Option<&Vec<usize>> {
         self.vertex_to_edges.get(&vertex_index)
     }
@@ -301,3 +449,63 @@ impl Skeleton {
         self.edge_to_faces.get(&edge_index)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn faces_from_edge_are_the_faces_holding_the_edge() {
+        let mut skel = Skeleton::new();
+        for pos in [
+            [0.0, 0.0, 0.0],
+            [1.0, 0.0, 0.0],
+            [0.0, 1.0, 0.0],
+            [1.0, 1.0, 0.0],
+        ] {
+            skel.add_vertex(Vector3::from(pos), 0.1, HashMap::new());
+        }
+        skel.add_face(vec![0, 1, 2], HashMap::new());
+        skel.add_face(vec![1, 3, 2], HashMap::new());
+
+        let edges = skel.get_edges();
+        let edge = |v1: usize, v2: usize| {
+            edges
+                .iter()
+                .position(|&[a, b]| (a, b) == (v1, v2) || (a, b) == (v2, v1))
+                .unwrap()
+        };
+        assert_eq!(skel.get_faces_from_edge(edge(0, 1)), Some(&vec![0]));
+        assert_eq!(skel.get_faces_from_edge(edge(1, 2)), Some(&vec![0, 1]));
+        assert_eq!(skel.get_faces_from_edge(edge(1, 3)), Some(&vec![1]));
+    }
+
+    #[test]
+    fn rebuild_rejects_out_of_range_edges() {
+        let mut skel = Skeleton::new();
+        for i in 0..3 {
+            skel.add_vertex(Vector3::new(i as f32, 0.0, 0.0), 0.1, HashMap::new());
+        }
+        skel.add_face(vec![0, 1, 2], HashMap::new());
+        skel.add_face(vec![0, 1, 2], HashMap::new());
+        skel.add_edge([2, 7]);
+
+        assert!(skel.check_faceless_edges().is_err());
+        assert!(skel.rebuild(&[0, 1, 2], vec![(0, vec![0, 1, 2])]).is_err());
+        assert!(skel.remove_duplicate_faces().is_err());
+        assert!(skel.weld_vertices(0.5).is_err());
+        assert!(skel.remove_unreferenced_vertices().is_err());
+        assert_eq!(skel.get_face_vertices().len(), 2);
+
+        // edges of copied vertices are kept
+        let mut skel = Skeleton::new();
+        for i in 0..4 {
+            skel.add_vertex(Vector3::new(i as f32, 0.0, 0.0), 0.1, HashMap::new());
+        }
+        skel.add_face(vec![0, 1, 2], HashMap::new());
+        skel.add_edge([2, 3]);
+        skel.rebuild(&[0, 1, 2, 3], vec![(0, vec![0, 1, 2])])
+            .unwrap();
+        assert_eq!(skel.get_faceless_edges().len(), 1);
+    }
+}
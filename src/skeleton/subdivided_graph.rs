@@ -0,0 +1,228 @@
+use nalgebra::base::*;
+use ply_rs::ply::{
+    Addable, DefaultElement, ElementDef, Property, PropertyDef, PropertyType, ScalarType,
+};
+
+/// Graph on which the burning is computed, kept for inspection: skeleton vertices
+/// followed by subdivision vertices, their adjacency and the subdivided faces
+pub struct SubdividedGraph {
+    vertices: Vec<Vector3<f32>>,
+    radii: Vec<f32>,
+    burntime: Vec<Option<f32>>,
+    erosion_thickness: Vec<Option<f32>>,
+    boundary: Vec<bool>,
+    prime_neighbor: Vec<Option<usize>>,
+    nb_original: usize,
+    edges: Vec<[usize; 2]>,
+    faces: Vec<Vec<usize>>,
+    comments: Vec<String>,
+}
+
+impl SubdividedGraph {
+    /// Empty graph, whose first `nb_original` vertices will be skeleton vertices
+    pub fn new(nb_original: usize) -> Self {
+        SubdividedGraph {
+            vertices: Vec::new(),
+            radii: Vec::new(),
+            burntime: Vec::new(),
+            erosion_thickness: Vec::new(),
+            boundary: Vec::new(),
+            prime_neighbor: Vec::new(),
+            nb_original,
+            edges: Vec::new(),
+            faces: Vec::new(),
+            comments: Vec::new(),
+        }
+    }
+
+    /// Adds a vertex with its burning state, None values for vertices never burnt
+    pub fn add_vertex(
+        &mut self,
+        position: Vector3<f32>,
+        radius: f32,
+        burntime: Option<f32>,
+        erosion_thickness: Option<f32>,
+        boundary: bool,
+        prime_neighbor: Option<usize>,
+    ) -> usize {
+        self.vertices.push(position);
+        self.radii.push(radius);
+        self.burntime.push(burntime);
+        self.erosion_thickness.push(erosion_thickness);
+        self.boundary.push(boundary);
+        self.prime_neighbor.push(prime_neighbor);
+        self.vertices.len() - 1
+    }
+
+    pub fn add_edge(&mut self, vertex_indices: [usize; 2]) -> usize {
+        self.edges.push(vertex_indices);
+        self.edges.len() - 1
+    }
+
+    pub fn add_face(&mut self, vertex_indices: Vec<usize>) -> usize {
+        self.faces.push(vertex_indices);
+        self.faces.len() - 1
+    }
+
+    pub fn get_vertices(&self) -> &Vec<Vector3<f32>> {
+        &self.vertices
+    }
+
+    pub fn get_radii(&self) -> &Vec<f32> {
+        &self.radii
+    }
+
+    pub fn get_burntime(&self) -> &Vec<Option<f32>> {
+        &self.burntime
+    }
+
+    pub fn get_erosion_thickness(&self) -> &Vec<Option<f32>> {
+        &self.erosion_thickness
+    }
+
+    pub fn get_boundary(&self) -> &Vec<bool> {
+        &self.boundary
+    }
+
+    /// Vertex each vertex was burnt from, None for boundary and unburnt vertices
+    pub fn get_prime_neighbor(&self) -> &Vec<Option<usize>> {
+        &self.prime_neighbor
+    }
+
+    /// Number of skeleton vertices, coming first
+    pub fn nb_original(&self) -> usize {
+        self.nb_original
+    }
+
+    /// Pairs of vertices neighbor in the burning, i.e. sharing a subdivided face
+    pub fn get_edges(&self) -> &Vec<[usize; 2]> {
+        &self.edges
+    }
+
+    /// Subdivided faces, as cycles of vertices
+    pub fn get_faces(&self) -> &Vec<Vec<usize>> {
+        &self.faces
+    }
+
+    /// Ply header comments, written on export
+    pub fn get_comments(&self) -> &Vec<String> {
+        &self.comments
+    }
+
+    pub fn add_comment(&mut self, comment: &str) {
+        self.comments.push(comment.to_string());
+    }
+
+    pub(super) fn vertex_header_element(&self) -> ElementDef {
+        let mut vertex_element = ElementDef::new("vertex".to_string());
+        for key in ["x", "y", "z", "radius", "burntime", "erosion_thickness"] {
+            vertex_element.properties.add(PropertyDef::new(
+                key.to_string(),
+                PropertyType::Scalar(ScalarType::Float),
+            ));
+        }
+        for key in ["boundary", "original"] {
+            vertex_element.properties.add(PropertyDef::new(
+                key.to_string(),
+                PropertyType::Scalar(ScalarType::UChar),
+            ));
+        }
+        vertex_element.properties.add(PropertyDef::new(
+            "prime".to_string(),
+            PropertyType::Scalar(ScalarType::Int),
+        ));
+        vertex_element
+    }
+
+    /// Vertices, never burnt ones having infinite burn time and erosion thickness, and
+    /// a prime neighbor of -1
+    pub(super) fn vertex_payload_element(&self) -> Vec<DefaultElement> {
+        let mut vertices = Vec::new();
+
+        for i in 0..self.vertices.len() {
+            let mut vertex = DefaultElement::new();
+            vertex.insert("x".to_string(), Property::Float(self.vertices[i].x));
+            vertex.insert("y".to_string(), Property::Float(self.vertices[i].y));
+            vertex.insert("z".to_string(), Property::Float(self.vertices[i].z));
+            vertex.insert("radius".to_string(), Property::Float(self.radii[i]));
+            vertex.insert(
+                "burntime".to_string(),
+                Property::Float(self.burntime[i].unwrap_or(f32::INFINITY)),
+            );
+            vertex.insert(
+                "erosion_thickness".to_string(),
+                Property::Float(self.erosion_thickness[i].unwrap_or(f32::INFINITY)),
+            );
+            vertex.insert(
+                "boundary".to_string(),
+                Property::UChar(u8::from(self.boundary[i])),
+            );
+            vertex.insert(
+                "original".to_string(),
+                Property::UChar(u8::from(i < self.nb_original)),
+            );
+            vertex.insert(
+                "prime".to_string(),
+                Property::Int(self.prime_neighbor[i].map_or(-1, |v| v as i32)),
+            );
+            vertices.push(vertex);
+        }
+
+        vertices
+    }
+
+    pub(super) fn edge_header_element(&self) -> ElementDef {
+        let mut edge_element = ElementDef::new("edge".to_string());
+        for key in ["vertex1", "vertex2"] {
+            edge_element.properties.add(PropertyDef::new(
+                key.to_string(),
+                PropertyType::Scalar(ScalarType::Int),
+            ));
+        }
+        edge_element
+    }
+
+    pub(super) fn edge_payload_element(&self) -> Vec<DefaultElement> {
+        let mut edges = Vec::new();
+
+        for &[v1, v2] in self.edges.iter() {
+            let mut edge = DefaultElement::new();
+            edge.insert("vertex1".to_string(), Property::Int(v1 as i32));
+            edge.insert("vertex2".to_string(), Property::Int(v2 as i32));
+            edges.push(edge);
+        }
+
+        edges
+    }
+
+    /// Face element, with an int vertex count when subdivided faces have more than 255
+    /// vertices
+    pub(super) fn face_header_element(&self) -> ElementDef {
+        let mut face_element = ElementDef::new("face".to_string());
+        let count_type = if self.faces.iter().any(|face| face.len() > u8::MAX as usize) {
+            ScalarType::UInt
+        } else {
+            ScalarType::UChar
+        };
+        face_element.properties.add(PropertyDef::new(
+            "vertex_indices".to_string(),
+            PropertyType::List(count_type, ScalarType::UInt),
+        ));
+        face_element
+    }
+
+    pub(super) fn face_payload_element(&self) -> Vec<DefaultElement> {
+        let mut faces = Vec::new();
+
+        for face_vertices in self.faces.iter() {
+            let mut face = DefaultElement::new();
+            face.insert(
+                "vertex_indices".to_string(),
+                Property::ListUInt(face_vertices.iter().map(|&i| i as u32).collect()),
+            );
+            faces.push(face);
+        }
+
+        faces
+    }
+}
@@ -0,0 +1,578 @@
+use anyhow::Result;
+
+use super::skeleton::Skeleton;
+
+/// Use of an edge by a face (radial-edge structure).
+///
+/// Half-edges of a face are chained with `next` and `prev`, all half-edges
+/// using the same edge are chained with `radial_next`, so that any number of
+/// faces can share an edge.
+#[derive(Clone, Copy, Debug)]
+pub struct HalfEdge {
+    pub origin: usize,
+    pub target: usize,
+    pub edge: usize,
+    pub face: usize,
+    pub next: usize,
+    pub prev: usize,
+    pub radial_next: usize,
+}
+
+/// Link of a vertex: vertices of its incident faces, connected when they
+/// follow each other in a face.
+pub struct LinkGraph {
+    nodes: Vec<usize>,         // vertex indices in skeleton
+    node_adj: Vec<Vec<usize>>, // adjacency, references to nodes
+}
+
+pub struct Topology {
+    edges: Vec<[usize; 2]>,
+    half_edges: Vec<HalfEdge>,
+    face_half_edge: Vec<usize>,
+    edge_half_edge: Vec<Option<usize>>,
+    edge_valence: Vec<usize>,
+    // vertex -> edges, and vertex -> outgoing half-edges, stored as compressed rows
+    vertex_edge_offsets: Vec<usize>,
+    vertex_edges: Vec<usize>,
+    vertex_half_edge_offsets: Vec<usize>,
+    vertex_half_edges: Vec<usize>,
+}
+
+fn compressed_rows(nb_rows: usize, entries: &[(usize, usize)]) -> (Vec<usize>, Vec<usize>) {
+    let mut offsets = vec![0; nb_rows + 1];
+    for &(row, _) in entries {
+        offsets[row + 1] += 1;
+    }
+    for i in 0..nb_rows {
+        offsets[i + 1] += offsets[i];
+    }
+    let mut fill = offsets.clone();
+    let mut values = vec![0; entries.len()];
+    for &(row, val) in entries {
+        values[fill[row]] = val;
+        fill[row] += 1;
+    }
+    (offsets, values)
+}
+
+impl LinkGraph {
+    pub fn nodes(&self) -> &Vec<usize> {
+        &self.nodes
+    }
+
+    pub fn node_adj(&self) -> &Vec<Vec<usize>> {
+        &self.node_adj
+    }
+
+    pub fn degree(&self, num_node: usize) -> usize {
+        self.node_adj[num_node].len()
+    }
+
+    /// Connected components, as lists of references to nodes
+    pub fn components(&self) -> Vec<Vec<usize>> {
+        let mut visited = vec![false; self.nodes.len()];
+        let mut components = Vec::new();
+        for i in 0..self.nodes.len() {
+            if visited[i] {
+                continue;
+            }
+            visited[i] = true;
+            let mut component = Vec::new();
+            let mut to_visit = vec![i];
+            while let Some(cur) = to_visit.pop() {
+                component.push(cur);
+                for &next in self.node_adj[cur].iter() {
+                    if !visited[next] {
+                        visited[next] = true;
+                        to_visit.push(next);
+                    }
+                }
+            }
+            components.push(component);
+        }
+        components
+    }
+}
+
+impl Topology {
+    pub fn new(skel: &Skeleton) -> Result<Topology> {
+        let nb_vertices = skel.get_vertices().len();
+        let edges = skel.get_edges().clone();
+        for &[v1, v2] in edges.iter() {
+            if v1 >= nb_vertices || v2 >= nb_vertices {
+                return Err(anyhow::Error::msg("Edge refers to a non existing vertex"));
+            }
+        }
+
+        let mut half_edges = Vec::new();
+        let mut face_half_edge = Vec::new();
+        for (ind_face, face_edges) in skel.get_faces().iter().enumerate() {
+            let face_vertices = &skel.get_face_vertices()[ind_face];
+            if face_vertices.is_empty() {
+                return Err(anyhow::Error::msg("Face without vertex"));
+            }
+            let first = half_edges.len();
+            let nb = face_vertices.len();
+            for i in 0..nb {
+                half_edges.push(HalfEdge {
+                    origin: face_vertices[i],
+                    target: face_vertices[(i + 1) % nb],
+                    edge: face_edges[i],
+                    face: ind_face,
+                    next: first + (i + 1) % nb,
+                    prev: first + (i + nb - 1) % nb,
+                    radial_next: first + i,
+                });
+            }
+            face_half_edge.push(first);
+        }
+        Ok(Self::from_half_edges(
+            nb_vertices,
+            edges,
+            half_edges,
+            face_half_edge,
+        ))
+    }
+
+    /// Topology of the same faces, each edge being replaced by a chain of edges
+    ///
+    /// `subdivisions` gives, for each edge, the chain of vertices replacing it, from its
+    /// first vertex to its second one, vertices added in chains being below `nb_vertices`.
+    pub fn subdivide(&self, nb_vertices: usize, subdivisions: &[Vec<usize>]) -> Topology {
+        let mut edges = Vec::new();
+        let mut first_sub_edge = Vec::with_capacity(self.edges.len());
+        for chain in subdivisions.iter() {
+            first_sub_edge.push(edges.len());
+            for j in 0..chain.len() - 1 {
+                edges.push([chain[j], chain[j + 1]]);
+            }
+        }
+
+        let mut half_edges = Vec::new();
+        let mut face_half_edge = Vec::new();
+        for ind_face in 0..self.nb_faces() {
+            let first = half_edges.len();
+            for ind_he in self.face_half_edges(ind_face) {
+                let he = &self.half_edges[ind_he];
+                let chain = &subdivisions[he.edge];
+                let nb_sub = chain.len() - 1;
+                let forward = chain[0] == he.origin;
+                for j in 0..nb_sub {
+                    let (origin, target, sub_edge) = if forward {
+                        (chain[j], chain[j + 1], j)
+                    } else {
+                        (chain[nb_sub - j], chain[nb_sub - j - 1], nb_sub - j - 1)
+                    };
+                    half_edges.push(HalfEdge {
+                        origin,
+                        target,
+                        edge: first_sub_edge[he.edge] + sub_edge,
+                        face: ind_face,
+                        next: 0,
+                        prev: 0,
+                        radial_next: half_edges.len(),
+                    });
+                }
+            }
+            let nb = half_edges.len() - first;
+            for i in 0..nb {
+                half_edges[first + i].next = first + (i + 1) % nb;
+                half_edges[first + i].prev = first + (i + nb - 1) % nb;
+            }
+            face_half_edge.push(first);
+        }
+        Self::from_half_edges(nb_vertices, edges, half_edges, face_half_edge)
+    }
+
+    /// Topology from the half-edges of faces, chained in faces but not around edges yet
+    fn from_half_edges(
+        nb_vertices: usize,
+        edges: Vec<[usize; 2]>,
+        mut half_edges: Vec<HalfEdge>,
+        face_half_edge: Vec<usize>,
+    ) -> Topology {
+        // radial cycles around edges
+        let mut edge_half_edge: Vec<Option<usize>> = vec![None; edges.len()];
+        let mut edge_valence = vec![0; edges.len()];
+        let mut edge_last: Vec<Option<usize>> = vec![None; edges.len()];
+        for ind_he in 0..half_edges.len() {
+            let ind_edge = half_edges[ind_he].edge;
+            edge_valence[ind_edge] += 1;
+            match edge_last[ind_edge] {
+                None => edge_half_edge[ind_edge] = Some(ind_he),
+                Some(last) => half_edges[last].radial_next = ind_he,
+            }
+            edge_last[ind_edge] = Some(ind_he);
+        }
+        for ind_edge in 0..edges.len() {
+            if let (Some(first), Some(last)) = (edge_half_edge[ind_edge], edge_last[ind_edge]) {
+                half_edges[last].radial_next = first;
+            }
+        }
+
+        let vertex_edge_entries: Vec<(usize, usize)> = edges
+            .iter()
+            .enumerate()
+            .flat_map(|(ind_edge, &[v1, v2])| {
+                if v1 == v2 {
+                    vec![(v1, ind_edge)]
+                } else {
+                    vec![(v1, ind_edge), (v2, ind_edge)]
+                }
+            })
+            .collect();
+        let (vertex_edge_offsets, vertex_edges) =
+            compressed_rows(nb_vertices, &vertex_edge_entries);
+
+        let vertex_half_edge_entries: Vec<(usize, usize)> = half_edges
+            .iter()
+            .enumerate()
+            .map(|(ind_he, he)| (he.origin, ind_he))
+            .collect();
+        let (vertex_half_edge_offsets, vertex_half_edges) =
+            compressed_rows(nb_vertices, &vertex_half_edge_entries);
+
+        Topology {
+            edges,
+            half_edges,
+            face_half_edge,
+            edge_half_edge,
+            edge_valence,
+            vertex_edge_offsets,
+            vertex_edges,
+            vertex_half_edge_offsets,
+            vertex_half_edges,
+        }
+    }
+
+    pub fn nb_vertices(&self) -> usize {
+        self.vertex_edge_offsets.len() - 1
+    }
+
+    pub fn nb_edges(&self) -> usize {
+        self.edges.len()
+    }
+
+    pub fn nb_faces(&self) -> usize {
+        self.face_half_edge.len()
+    }
+
+    pub fn nb_half_edges(&self) -> usize {
+        self.half_edges.len()
+    }
+
+    pub fn edge(&self, ind_edge: usize) -> [usize; 2] {
+        self.edges[ind_edge]
+    }
+
+    pub fn half_edge(&self, ind_he: usize) -> &HalfEdge {
+        &self.half_edges[ind_he]
+    }
+
+    /// Half-edges of a face, following the face orientation
+    pub fn face_half_edges(&self, ind_face: usize) -> Vec<usize> {
+        let first = self.face_half_edge[ind_face];
+        let mut hes = vec![first];
+        let mut cur = self.half_edges[first].next;
+        while cur != first {
+            hes.push(cur);
+            cur = self.half_edges[cur].next;
+        }
+        hes
+    }
+
+    /// Half-edges using an edge, one per incident face
+    pub fn edge_half_edges(&self, ind_edge: usize) -> Vec<usize> {
+        let mut hes = Vec::new();
+        if let Some(first) = self.edge_half_edge[ind_edge] {
+            hes.push(first);
+            let mut cur = self.half_edges[first].radial_next;
+            while cur != first {
+                hes.push(cur);
+                cur = self.half_edges[cur].radial_next;
+            }
+        }
+        hes
+    }
+
+    pub fn edge_faces(&self, ind_edge: usize) -> Vec<usize> {
+        self.edge_half_edges(ind_edge)
+            .into_iter()
+            .map(|he| self.half_edges[he].face)
+            .collect()
+    }
+
+    /// Number of face uses of an edge
+    pub fn edge_valence(&self, ind_edge: usize) -> usize {
+        self.edge_valence[ind_edge]
+    }
+
+    pub fn is_boundary_edge(&self, ind_edge: usize) -> bool {
+        self.edge_valence[ind_edge] == 1
+    }
+
+    pub fn is_non_manifold_edge(&self, ind_edge: usize) -> bool {
+        self.edge_valence[ind_edge] > 2
+    }
+
+    pub fn boundary_edges(&self) -> Vec<usize> {
+        (0..self.edges.len())
+            .filter(|&e| self.is_boundary_edge(e))
+            .collect()
+    }
+
+    pub fn non_manifold_edges(&self) -> Vec<usize> {
+        (0..self.edges.len())
+            .filter(|&e| self.is_non_manifold_edge(e))
+            .collect()
+    }
+
+    pub fn vertex_edges(&self, ind_vertex: usize) -> &[usize] {
+        &self.vertex_edges
+            [self.vertex_edge_offsets[ind_vertex]..self.vertex_edge_offsets[ind_vertex + 1]]
+    }
+
+    /// Half-edges starting from a vertex, one per face corner
+    pub fn vertex_half_edges(&self, ind_vertex: usize) -> &[usize] {
+        &self.vertex_half_edges[self.vertex_half_edge_offsets[ind_vertex]
+            ..self.vertex_half_edge_offsets[ind_vertex + 1]]
+    }
+
+    /// Vertices sharing an edge with a vertex
+    pub fn one_ring(&self, ind_vertex: usize) -> Vec<usize> {
+        let mut ring: Vec<usize> = self
+            .vertex_edges(ind_vertex)
+            .iter()
+            .map(|&e| {
+                let [v1, v2] = self.edges[e];
+                if v1 == ind_vertex {
+                    v2
+                } else {
+                    v1
+                }
+            })
+            .filter(|&v| v != ind_vertex)
+            .collect();
+        ring.sort();
+        ring.dedup();
+        ring
+    }
+
+    /// Link of a vertex, made of the one ring and of the chains of incident
+    /// polygons opposite to the vertex
+    pub fn link_graph(&self, ind_vertex: usize) -> LinkGraph {
+        let mut nodes = self.one_ring(ind_vertex);
+        for &ind_he in self.vertex_half_edges(ind_vertex) {
+            let last = self.half_edges[ind_he].prev;
+            let mut cur = self.half_edges[ind_he].next;
+            while cur != last && cur != ind_he {
+                let he = &self.half_edges[cur];
+                if he.target != ind_vertex {
+                    nodes.push(he.target);
+                }
+                cur = he.next;
+            }
+        }
+        nodes.sort();
+        nodes.dedup();
+        let mut node_adj = vec![Vec::new(); nodes.len()];
+        let num_node = |v: usize| nodes.binary_search(&v).ok();
+        for &ind_he in self.vertex_half_edges(ind_vertex) {
+            // chain of the face opposite to the vertex
+            let last = self.half_edges[ind_he].prev;
+            let mut cur = self.half_edges[ind_he].next;
+            while cur != last && cur != ind_he {
+                let he = &self.half_edges[cur];
+                if let (Some(n1), Some(n2)) = (num_node(he.origin), num_node(he.target)) {
+                    if n1 != n2 {
+                        node_adj[n1].push(n2);
+                        node_adj[n2].push(n1);
+                    }
+                }
+                cur = he.next;
+            }
+        }
+        for adj in node_adj.iter_mut() {
+            adj.sort();
+            adj.dedup();
+        }
+        LinkGraph { nodes, node_adj }
+    }
+
+    /// Closed or open chains of boundary edges, as vertex lists
+    ///
+    /// Open chains only appear around non manifold vertices.
+    pub fn boundary_loops(&self) -> Vec<Vec<usize>> {
+        let mut used = vec![false; self.edges.len()];
+        let mut loops = Vec::new();
+        for first_edge in self.boundary_edges() {
+            if used[first_edge] {
+                continue;
+            }
+            used[first_edge] = true;
+            let [first_vert, mut cur_vert] = self.edges[first_edge];
+            let mut boundary_loop = vec![first_vert, cur_vert];
+            while cur_vert != first_vert {
+                let next_edge = self
+                    .vertex_edges(cur_vert)
+                    .iter()
+                    .copied()
+                    .find(|&e| !used[e] && self.is_boundary_edge(e));
+                match next_edge {
+                    Some(e) => {
+                        used[e] = true;
+                        let [v1, v2] = self.edges[e];
+                        cur_vert = if v1 == cur_vert { v2 } else { v1 };
+                        boundary_loop.push(cur_vert);
+                    }
+                    None => break,
+                }
+            }
+            if boundary_loop.len() > 1 && boundary_loop.first() == boundary_loop.last() {
+                boundary_loop.pop();
+            }
+            loops.push(boundary_loop);
+        }
+        loops
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nalgebra::base::*;
+    use std::collections::HashMap;
+
+    fn skeleton(nb_vertices: usize, faces: &[Vec<usize>], edges: &[[usize; 2]]) -> Skeleton {
+        let mut skel = Skeleton::new();
+        for i in 0..nb_vertices {
+            skel.add_vertex(Vector3::new(i as f32, 0.0, 0.0), 0.1, HashMap::new());
+        }
+        for face in faces {
+            skel.add_face(face.clone(), HashMap::new());
+        }
+        for &edge in edges {
+            skel.add_edge(edge);
+        }
+        skel
+    }
+
+    /// Two triangles sharing only vertex 0, and an edge of no face from 1 to 5
+    fn bowtie() -> Topology {
+        let skel = skeleton(6, &[vec![0, 1, 2], vec![0, 3, 4]], &[[1, 5]]);
+        Topology::new(&skel).unwrap()
+    }
+
+    /// Three triangles sharing the edge (0, 1)
+    fn fin() -> Topology {
+        let skel = skeleton(5, &[vec![0, 1, 2], vec![1, 0, 3], vec![0, 1, 4]], &[]);
+        Topology::new(&skel).unwrap()
+    }
+
+    #[test]
+    fn one_ring() {
+        let topo = bowtie();
+        assert_eq!(topo.one_ring(0), vec![1, 2, 3, 4]);
+        assert_eq!(topo.one_ring(1), vec![0, 2, 5]);
+        assert_eq!(topo.one_ring(5), vec![1]);
+        assert_eq!(fin().one_ring(0), vec![1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn link_graph_of_non_manifold_vertices() {
+        let topo = bowtie();
+        let link = topo.link_graph(0);
+        assert_eq!(link.nodes(), &vec![1, 2, 3, 4]);
+        assert_eq!(link.node_adj(), &vec![vec![1], vec![0], vec![3], vec![2]]);
+        assert_eq!(link.components().len(), 2);
+
+        // the edge of no face gives a node without link edges
+        let link = topo.link_graph(1);
+        assert_eq!(link.nodes(), &vec![0, 2, 5]);
+        assert_eq!(link.degree(2), 0);
+        assert_eq!(link.components().len(), 2);
+
+        let link = fin().link_graph(0);
+        assert_eq!(link.nodes(), &vec![1, 2, 3, 4]);
+        assert_eq!(link.degree(0), 3);
+        assert_eq!(link.components().len(), 1);
+    }
+
+    #[test]
+    fn non_manifold_edges() {
+        let topo = fin();
+        assert_eq!(topo.non_manifold_edges(), vec![0]);
+        assert_eq!(topo.edge(0), [0, 1]);
+        assert_eq!(topo.edge_valence(0), 3);
+        assert_eq!(topo.edge_faces(0), vec![0, 1, 2]);
+        assert_eq!(topo.boundary_edges().len(), 6);
+
+        let topo = bowtie();
+        assert!(topo.non_manifold_edges().is_empty());
+        // the edge of no face is neither boundary nor non manifold
+        assert_eq!(topo.edge_valence(6), 0);
+        assert_eq!(topo.boundary_edges().len(), 6);
+    }
+
+    #[test]
+    fn boundary_loops() {
+        let topo = bowtie();
+        assert_eq!(topo.boundary_loops(), vec![vec![0, 1, 2], vec![0, 3, 4]]);
+
+        // two triangles sharing an edge, one loop around both
+        let skel = skeleton(4, &[vec![0, 1, 2], vec![2, 1, 3]], &[]);
+        let topo = Topology::new(&skel).unwrap();
+        assert_eq!(topo.boundary_loops(), vec![vec![0, 1, 3, 2]]);
+
+        // closed tetrahedron, no boundary
+        let skel = skeleton(
+            4,
+            &[vec![0, 1, 2], vec![0, 2, 3], vec![0, 3, 1], vec![1, 3, 2]],
+            &[],
+        );
+        assert!(Topology::new(&skel).unwrap().boundary_loops().is_empty());
+
+        // around the fin, a closed loop and an open chain between the non manifold vertices
+        assert_eq!(
+            fin().boundary_loops(),
+            vec![vec![1, 2, 0, 3], vec![1, 4, 0]]
+        );
+    }
+
+    #[test]
+    fn subdivide_edges() {
+        // triangles (0, 1, 2) and (1, 0, 4) sharing the edge (0, 1), split at vertex 3
+        let skel = skeleton(5, &[vec![0, 1, 2], vec![1, 0, 4]], &[]);
+        let topo = Topology::new(&skel).unwrap();
+        let mut subdivisions: Vec<Vec<usize>> = (0..topo.nb_edges())
+            .map(|e| topo.edge(e).to_vec())
+            .collect();
+        subdivisions[0] = vec![0, 3, 1];
+        let subdiv_topo = topo.subdivide(5, &subdivisions);
+
+        assert_eq!(subdiv_topo.nb_vertices(), 5);
+        assert_eq!(subdiv_topo.nb_edges(), topo.nb_edges() + 1);
+        assert_eq!(subdiv_topo.nb_half_edges(), 8);
+        let origins = |face| -> Vec<usize> {
+            subdiv_topo
+                .face_half_edges(face)
+                .iter()
+                .map(|&he| subdiv_topo.half_edge(he).origin)
+                .collect()
+        };
+        assert_eq!(origins(0), vec![0, 3, 1, 2]);
+        assert_eq!(origins(1), vec![1, 3, 0, 4]);
+        assert_eq!(subdiv_topo.edge(0), [0, 3]);
+        assert_eq!(subdiv_topo.edge(1), [3, 1]);
+        assert_eq!(subdiv_topo.edge_valence(0), 2);
+        assert_eq!(subdiv_topo.edge_valence(1), 2);
+        assert!(subdiv_topo.non_manifold_edges().is_empty());
+
+        let link = subdiv_topo.link_graph(3);
+        assert_eq!(link.nodes(), &vec![0, 1, 2, 4]);
+        assert_eq!(
+            link.node_adj(),
+            &vec![vec![2, 3], vec![2, 3], vec![0, 1], vec![0, 1]]
+        );
+    }
+}
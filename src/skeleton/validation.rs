@@ -0,0 +1,549 @@
+use std::fmt;
+
+use nalgebra::base::*;
+
+use super::skeleton::Skeleton;
+use super::topology::Topology;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Info,
+    Warning,
+    Error,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum IssueKind {
+    InvalidVertexIndex,
+    RepeatedVertexIndex,
+    DegenerateFace,
+    ZeroAreaFace,
+    NonFiniteCoordinate,
+    NonPositiveRadius,
+    IsolatedVertex,
+    HighValenceEdge,
+    NonManifoldVertex,
+    /// topology checks (edge valence, isolated and non manifold vertices) not run
+    TopologySkipped,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Location {
+    Vertex(usize),
+    Edge(usize),
+    Face(usize),
+    /// whole skeleton
+    Skeleton,
+}
+
+#[derive(Clone, Debug)]
+pub struct Issue {
+    pub severity: Severity,
+    pub kind: IssueKind,
+    pub location: Location,
+    pub message: String,
+}
+
+pub struct ValidationOptions {
+    /// faces with an area below `area_tolerance` times their squared longest edge are reported
+    pub area_tolerance: f32,
+    /// edges shared by more faces are reported
+    pub max_edge_valence: usize,
+}
+
+#[derive(Default)]
+pub struct ValidationReport {
+    issues: Vec<Issue>,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Severity::Info => write!(f, "info"),
+            Severity::Warning => write!(f, "warning"),
+            Severity::Error => write!(f, "error"),
+        }
+    }
+}
+
+impl fmt::Display for Location {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Location::Vertex(i) => write!(f, "vertex {}", i),
+            Location::Edge(i) => write!(f, "edge {}", i),
+            Location::Face(i) => write!(f, "face {}", i),
+            Location::Skeleton => write!(f, "skeleton"),
+        }
+    }
+}
+
+impl fmt::Display for Issue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "[{}] {}: {}", self.severity, self.location, self.message)
+    }
+}
+
+impl Default for ValidationOptions {
+    fn default() -> Self {
+        ValidationOptions {
+            area_tolerance: 1e-6,
+            max_edge_valence: 3,
+        }
+    }
+}
+
+impl ValidationReport {
+    fn push(&mut self, severity: Severity, kind: IssueKind, location: Location, message: String) {
+        self.issues.push(Issue {
+            severity,
+            kind,
+            location,
+            message,
+        });
+    }
+
+    pub fn issues(&self) -> &Vec<Issue> {
+        &self.issues
+    }
+
+    pub fn count(&self, severity: Severity) -> usize {
+        self.issues
+            .iter()
+            .filter(|issue| issue.severity == severity)
+            .count()
+    }
+
+    pub fn has_errors(&self) -> bool {
+        self.count(Severity::Error) != 0
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.issues.is_empty()
+    }
+}
+
+impl fmt::Display for ValidationReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for issue in self.issues.iter() {
+            writeln!(f, "{}", issue)?;
+        }
+        write!(
+            f,
+            "{} error(s), {} warning(s), {} info(s)",
+            self.count(Severity::Error),
+            self.count(Severity::Warning),
+            self.count(Severity::Info)
+        )
+    }
+}
+
+fn face_area(positions: &[Vector3<f32>]) -> f32 {
+    // Newell's method, valid for non planar polygons
+    let mut normal = Vector3::zeros();
+    for i in 0..positions.len() {
+        let j = (i + 1) % positions.len();
+        normal += positions[i].cross(&positions[j]);
+    }
+    0.5 * normal.norm()
+}
+
+impl Skeleton {
+    pub fn validate(&self) -> ValidationReport {
+        self.validate_with(&ValidationOptions::default())
+    }
+
+    pub fn validate_with(&self, options: &ValidationOptions) -> ValidationReport {
+        let mut report = ValidationReport::default();
+        let nb_vertices = self.get_vertices().len();
+
+        // vertices
+        for (i, (pos, &rad)) in self
+            .get_vertices()
+            .iter()
+            .zip(self.get_radii().iter())
+            .enumerate()
+        {
+            if pos.iter().any(|c| !c.is_finite()) || !rad.is_finite() {
+                report.push(
+                    Severity::Error,
+                    IssueKind::NonFiniteCoordinate,
+                    Location::Vertex(i),
+                    "NaN or infinite coordinate or radius".to_string(),
+                );
+            } else if rad <= 0.0 {
+                report.push(
+                    Severity::Warning,
+                    IssueKind::NonPositiveRadius,
+                    Location::Vertex(i),
+                    format!("non positive radius {}", rad),
+                );
+            }
+        }
+
+        // edges of no face, those of faces being checked with them
+        let mut valid_indices = true;
+        for e in self.get_faceless_edges() {
+            if let Some(&ind) = self.get_edges()[e].iter().find(|&&ind| ind >= nb_vertices) {
+                report.push(
+                    Severity::Error,
+                    IssueKind::InvalidVertexIndex,
+                    Location::Edge(e),
+                    format!("vertex index {} out of range", ind),
+                );
+                valid_indices = false;
+            }
+        }
+
+        // faces
+        for (i, face) in self.get_face_vertices().iter().enumerate() {
+            if let Some(&ind) = face.iter().find(|&&ind| ind >= nb_vertices) {
+                report.push(
+                    Severity::Error,
+                    IssueKind::InvalidVertexIndex,
+                    Location::Face(i),
+                    format!("vertex index {} out of range", ind),
+                );
+                valid_indices = false;
+                continue;
+            }
+            let mut sorted = face.clone();
+            sorted.sort();
+            sorted.dedup();
+            if sorted.len() < 3 {
+                report.push(
+                    Severity::Error,
+                    IssueKind::DegenerateFace,
+                    Location::Face(i),
+                    format!("only {} distinct vertices", sorted.len()),
+                );
+                continue;
+            }
+            if sorted.len() != face.len() {
+                report.push(
+                    Severity::Error,
+                    IssueKind::RepeatedVertexIndex,
+                    Location::Face(i),
+                    format!("repeated vertex index in {:?}", face),
+                );
+                continue;
+            }
+            let positions: Vec<Vector3<f32>> =
+                face.iter().map(|&ind| self.get_vertices()[ind]).collect();
+            let longest = (0..positions.len())
+                .map(|j| (positions[(j + 1) % positions.len()] - positions[j]).norm_squared())
+                .fold(0.0, f32::max);
+            if face_area(&positions) <= options.area_tolerance * longest {
+                report.push(
+                    Severity::Warning,
+                    IssueKind::ZeroAreaFace,
+                    Location::Face(i),
+                    "zero area".to_string(),
+                );
+            }
+        }
+
+        // topology, only available with valid indices
+        let topo = if valid_indices {
+            Topology::new(self)
+        } else {
+            Err(anyhow::Error::msg("vertex index out of range"))
+        };
+        let topo = match topo {
+            Ok(topo) => topo,
+            Err(err) => {
+                report.push(
+                    Severity::Warning,
+                    IssueKind::TopologySkipped,
+                    Location::Skeleton,
+                    format!("topology checks skipped: {}", err),
+                );
+                return report;
+            }
+        };
+        for e in 0..topo.nb_edges() {
+            if topo.edge_valence(e) > options.max_edge_valence {
+                report.push(
+                    Severity::Warning,
+                    IssueKind::HighValenceEdge,
+                    Location::Edge(e),
+                    format!(
+                        "{:?} shared by {} faces",
+                        topo.edge(e),
+                        topo.edge_valence(e)
+                    ),
+                );
+            }
+        }
+        for v in 0..nb_vertices {
+            if topo.vertex_edges(v).is_empty() {
+                report.push(
+                    Severity::Info,
+                    IssueKind::IsolatedVertex,
+                    Location::Vertex(v),
+                    "isolated vertex".to_string(),
+                );
+                continue;
+            }
+            let nb_components = topo.link_graph(v).components().len();
+            if nb_components > 1 {
+                report.push(
+                    Severity::Warning,
+                    IssueKind::NonManifoldVertex,
+                    Location::Vertex(v),
+                    format!("link made of {} disconnected components", nb_components),
+                );
+            }
+        }
+
+        report
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    /// Skeleton of unit radius vertices at `positions`, with faces and edges of no face
+    fn skeleton(positions: &[[f32; 3]], faces: &[Vec<usize>], edges: &[[usize; 2]]) -> Skeleton {
+        let mut skel = Skeleton::new();
+        for &pos in positions {
+            skel.add_vertex(Vector3::from(pos), 1.0, HashMap::new());
+        }
+        for face in faces {
+            skel.add_face(face.clone(), HashMap::new());
+        }
+        for &edge in edges {
+            skel.add_edge(edge);
+        }
+        skel
+    }
+
+    const TRIANGLE: [[f32; 3]; 3] = [[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]];
+
+    fn issues(skel: &Skeleton) -> Vec<(Severity, IssueKind, Location)> {
+        skel.validate()
+            .issues()
+            .iter()
+            .map(|issue| (issue.severity, issue.kind, issue.location))
+            .collect()
+    }
+
+    fn skipped() -> (Severity, IssueKind, Location) {
+        (
+            Severity::Warning,
+            IssueKind::TopologySkipped,
+            Location::Skeleton,
+        )
+    }
+
+    #[test]
+    fn valid_skeleton() {
+        let skel = skeleton(&TRIANGLE, &[vec![0, 1, 2]], &[]);
+        assert!(skel.validate().is_empty());
+    }
+
+    #[test]
+    fn invalid_vertex_index() {
+        let skel = skeleton(&TRIANGLE, &[vec![0, 1, 5]], &[]);
+        assert_eq!(
+            issues(&skel),
+            vec![
+                (
+                    Severity::Error,
+                    IssueKind::InvalidVertexIndex,
+                    Location::Face(0)
+                ),
+                skipped()
+            ]
+        );
+        let skel = skeleton(&TRIANGLE, &[vec![0, 1, 2]], &[[2, 9]]);
+        assert_eq!(
+            issues(&skel),
+            vec![
+                (
+                    Severity::Error,
+                    IssueKind::InvalidVertexIndex,
+                    Location::Edge(3)
+                ),
+                skipped()
+            ]
+        );
+    }
+
+    #[test]
+    fn repeated_vertex_index() {
+        let mut positions = TRIANGLE.to_vec();
+        positions.push([1.0, 1.0, 0.0]);
+        let skel = skeleton(&positions, &[vec![0, 1, 3, 1, 2]], &[]);
+        assert_eq!(
+            issues(&skel)[0],
+            (
+                Severity::Error,
+                IssueKind::RepeatedVertexIndex,
+                Location::Face(0)
+            )
+        );
+    }
+
+    #[test]
+    fn degenerate_face() {
+        let skel = skeleton(&TRIANGLE, &[vec![0, 1, 2], vec![0, 1, 1]], &[]);
+        assert_eq!(
+            issues(&skel)[0],
+            (
+                Severity::Error,
+                IssueKind::DegenerateFace,
+                Location::Face(1)
+            )
+        );
+        // a face without vertex has no topology
+        let skel = skeleton(&TRIANGLE, &[vec![0, 1, 2], vec![]], &[]);
+        assert_eq!(
+            issues(&skel),
+            vec![
+                (
+                    Severity::Error,
+                    IssueKind::DegenerateFace,
+                    Location::Face(1)
+                ),
+                skipped()
+            ]
+        );
+    }
+
+    #[test]
+    fn zero_area_face() {
+        let skel = skeleton(
+            &[[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [2.0, 0.0, 0.0]],
+            &[vec![0, 1, 2]],
+            &[],
+        );
+        assert_eq!(
+            issues(&skel),
+            vec![(
+                Severity::Warning,
+                IssueKind::ZeroAreaFace,
+                Location::Face(0)
+            )]
+        );
+    }
+
+    #[test]
+    fn non_finite_coordinate() {
+        for (pos, rad) in [
+            ([f32::INFINITY, 0.0, 0.0], 1.0),
+            ([0.0, f32::NAN, 0.0], 1.0),
+            ([0.0, 0.0, 0.0], f32::NAN),
+            ([0.0, 0.0, 0.0], f32::INFINITY),
+            ([0.0, 0.0, 0.0], f32::NEG_INFINITY),
+        ] {
+            let mut skel = skeleton(&TRIANGLE, &[vec![0, 1, 2]], &[]);
+            skel.add_vertex(Vector3::from(pos), rad, HashMap::new());
+            skel.add_face(vec![0, 1, 3], HashMap::new());
+            let issues = issues(&skel);
+            assert_eq!(
+                issues[0],
+                (
+                    Severity::Error,
+                    IssueKind::NonFiniteCoordinate,
+                    Location::Vertex(3)
+                ),
+                "{:?} {}",
+                pos,
+                rad
+            );
+            assert!(!issues
+                .iter()
+                .any(|issue| issue.1 == IssueKind::NonPositiveRadius));
+        }
+    }
+
+    #[test]
+    fn non_positive_radius() {
+        let mut skel = skeleton(&TRIANGLE, &[], &[]);
+        skel.add_vertex(Vector3::new(1.0, 1.0, 0.0), 0.0, HashMap::new());
+        skel.add_face(vec![0, 1, 3], HashMap::new());
+        skel.add_face(vec![1, 2, 3], HashMap::new());
+        assert_eq!(
+            issues(&skel),
+            vec![(
+                Severity::Warning,
+                IssueKind::NonPositiveRadius,
+                Location::Vertex(3)
+            )]
+        );
+    }
+
+    #[test]
+    fn isolated_vertex() {
+        let mut positions = TRIANGLE.to_vec();
+        positions.push([5.0, 5.0, 5.0]);
+        let skel = skeleton(&positions, &[vec![0, 1, 2]], &[]);
+        assert_eq!(
+            issues(&skel),
+            vec![(
+                Severity::Info,
+                IssueKind::IsolatedVertex,
+                Location::Vertex(3)
+            )]
+        );
+        // vertices of edges of no face are not isolated
+        let skel = skeleton(&positions, &[vec![0, 1, 2]], &[[2, 3]]);
+        assert!(!issues(&skel)
+            .iter()
+            .any(|issue| issue.1 == IssueKind::IsolatedVertex));
+    }
+
+    #[test]
+    fn high_valence_edge() {
+        // four faces on the edge (0, 1)
+        let positions = [
+            [0.0, 0.0, 0.0],
+            [1.0, 0.0, 0.0],
+            [0.0, 1.0, 0.0],
+            [0.0, -1.0, 0.0],
+            [0.0, 0.0, 1.0],
+            [0.0, 0.0, -1.0],
+        ];
+        let faces: Vec<Vec<usize>> = (2..6).map(|v| vec![0, 1, v]).collect();
+        let skel = skeleton(&positions, &faces, &[]);
+        assert_eq!(
+            issues(&skel),
+            vec![(
+                Severity::Warning,
+                IssueKind::HighValenceEdge,
+                Location::Edge(0)
+            )]
+        );
+        // three faces are allowed by default
+        let skel = skeleton(&positions[..5], &faces[..3], &[]);
+        assert!(skel.validate().is_empty());
+        let options = ValidationOptions {
+            max_edge_valence: 2,
+            ..ValidationOptions::default()
+        };
+        assert_eq!(skel.validate_with(&options).issues().len(), 1);
+    }
+
+    #[test]
+    fn non_manifold_vertex() {
+        // two triangles sharing only vertex 0
+        let positions = [
+            [0.0, 0.0, 0.0],
+            [1.0, 0.0, 0.0],
+            [0.0, 1.0, 0.0],
+            [-1.0, 0.0, 0.0],
+            [0.0, -1.0, 0.0],
+        ];
+        let skel = skeleton(&positions, &[vec![0, 1, 2], vec![0, 3, 4]], &[]);
+        assert_eq!(
+            issues(&skel),
+            vec![(
+                Severity::Warning,
+                IssueKind::NonManifoldVertex,
+                Location::Vertex(0)
+            )]
+        );
+    }
+}
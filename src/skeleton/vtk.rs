@@ -0,0 +1,545 @@
+use anyhow::Result;
+use std::io::Write;
+use std::path::Path;
+
+use ply_rs::ply::{DefaultElement, ElementDef, Property, PropertyType, ScalarType};
+
+use super::compression;
+use super::erosion_path::ErosionPath;
+use super::skeleton::Skeleton;
+
+const BASE64_CHARS: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// VTK file flavour
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum VtkFormat {
+    /// Legacy `.vtk`, binary
+    Legacy,
+    /// XML PolyData `.vtp`, with base64 encoded binary arrays
+    Xml,
+}
+
+impl VtkFormat {
+    /// Format given by the file extension (before `.gz` or `.zst`), None if not a vtk file
+    pub fn from_path(file_path: &Path) -> Option<VtkFormat> {
+        match compression::format_extension(file_path)? {
+            "vtk" => Some(VtkFormat::Legacy),
+            "vtp" => Some(VtkFormat::Xml),
+            _ => None,
+        }
+    }
+}
+
+/// Point or cell data array, from a ply property
+struct DataArray {
+    name: String,
+    data_type: ScalarType,
+    nb_components: usize,
+    /// values, component after component for each element
+    values: Vec<Property>,
+}
+
+/// Points, cells, and their data
+struct PolyData {
+    title: &'static str,
+    points: Vec<[f32; 3]>,
+    lines: Vec<Vec<usize>>,
+    polys: Vec<Vec<usize>>,
+    point_data: Vec<DataArray>,
+    /// values of the lines, then of the polygons, as vtk orders cells
+    cell_data: Vec<DataArray>,
+}
+
+fn property_f32(element: &DefaultElement, name: &str) -> Result<f32> {
+    match element.get(name) {
+        Some(Property::Float(val)) => Ok(*val),
+        _ => Err(anyhow::Error::msg(format!("No float {} property", name))),
+    }
+}
+
+fn property_index(element: &DefaultElement, name: &str) -> Result<usize> {
+    match element.get(name) {
+        Some(Property::Int(val)) => Ok(*val as usize),
+        Some(Property::UInt(val)) => Ok(*val as usize),
+        _ => Err(anyhow::Error::msg(format!("No index {} property", name))),
+    }
+}
+
+/// Data arrays of the scalar properties of an element, except `skipped` ones
+///
+/// Arrays are sorted by name, red, green and blue properties are merged into a `colors`
+/// array of three components. List properties have no vtk counterpart and are left out.
+fn data_arrays(
+    element_def: &ElementDef,
+    elements: &[DefaultElement],
+    skipped: &[&str],
+) -> Result<Vec<DataArray>> {
+    let colors = ["red", "green", "blue"];
+    let has_colors = colors.iter().all(|name| {
+        element_def.properties.get(*name).map(|def| &def.data_type)
+            == Some(&PropertyType::Scalar(ScalarType::UChar))
+    });
+    let mut arrays = Vec::new();
+    for (name, prop_def) in element_def.properties.iter() {
+        if skipped.contains(&name.as_str()) || (has_colors && colors.contains(&name.as_str())) {
+            continue;
+        }
+        if let PropertyType::Scalar(data_type) = &prop_def.data_type {
+            arrays.push((name.clone(), data_type.clone(), vec![name.as_str()]));
+        }
+    }
+    if has_colors {
+        arrays.push(("colors".to_string(), ScalarType::UChar, colors.to_vec()));
+    }
+    arrays.sort_by(|a, b| a.0.cmp(&b.0));
+
+    let mut data = Vec::new();
+    for (name, data_type, components) in arrays {
+        let mut values = Vec::new();
+        for element in elements.iter() {
+            for component in components.iter() {
+                let val = element.get(*component).ok_or(anyhow::Error::msg(format!(
+                    "Missing {} property value",
+                    component
+                )))?;
+                values.push(val.clone());
+            }
+        }
+        data.push(DataArray {
+            name,
+            data_type,
+            nb_components: components.len(),
+            values,
+        });
+    }
+    Ok(data)
+}
+
+/// Zero of a scalar type
+fn zero(data_type: &ScalarType) -> Property {
+    match data_type {
+        ScalarType::Char => Property::Char(0),
+        ScalarType::UChar => Property::UChar(0),
+        ScalarType::Short => Property::Short(0),
+        ScalarType::UShort => Property::UShort(0),
+        ScalarType::Int => Property::Int(0),
+        ScalarType::UInt => Property::UInt(0),
+        ScalarType::Float => Property::Float(0.0),
+        ScalarType::Double => Property::Double(0.0),
+    }
+}
+
+fn points(vertices: &[DefaultElement]) -> Result<Vec<[f32; 3]>> {
+    let mut points = Vec::new();
+    for vertex in vertices.iter() {
+        points.push([
+            property_f32(vertex, "x")?,
+            property_f32(vertex, "y")?,
+            property_f32(vertex, "z")?,
+        ]);
+    }
+    Ok(points)
+}
+
+impl PolyData {
+    /// Skeleton polygons and lines (edges of no face), with radius and other vertex and
+    /// face properties as data
+    ///
+    /// Lines have no face property, their cell data values are zero.
+    fn from_skeleton(skel: &Skeleton) -> Result<PolyData> {
+        let vertices = skel.vertex_payload_element();
+        let faces = skel.face_payload_element();
+        let lines: Vec<Vec<usize>> = skel
+            .get_faceless_edges()
+            .iter()
+            .map(|&edge_index| skel.get_edges()[edge_index].to_vec())
+            .collect();
+        let mut cell_data = data_arrays(&skel.face_header_element(), &faces, &["vertex_indices"])?;
+        for array in cell_data.iter_mut() {
+            let mut values = vec![zero(&array.data_type); lines.len() * array.nb_components];
+            values.append(&mut array.values);
+            array.values = values;
+        }
+        Ok(PolyData {
+            title: "skeleton",
+            points: points(&vertices)?,
+            lines,
+            polys: skel.get_face_vertices().clone(),
+            point_data: data_arrays(&skel.vertex_header_element(), &vertices, &["x", "y", "z"])?,
+            cell_data,
+        })
+    }
+
+    /// Erosion path lines, with burn time and other vertex properties as data
+    fn from_erosion_path(erosion_path: &ErosionPath) -> Result<PolyData> {
+        let vertices = erosion_path.vertex_payload_element();
+        let edges = erosion_path.edge_payload_element();
+        let mut lines = Vec::new();
+        for edge in edges.iter() {
+            lines.push(vec![
+                property_index(edge, "vertex1")?,
+                property_index(edge, "vertex2")?,
+            ]);
+        }
+        let edge_def = erosion_path.edge_header_element();
+        Ok(PolyData {
+            title: "erosion path",
+            points: points(&vertices)?,
+            lines,
+            polys: Vec::new(),
+            point_data: data_arrays(
+                &erosion_path.vertex_header_element(),
+                &vertices,
+                &["x", "y", "z"],
+            )?,
+            cell_data: data_arrays(&edge_def, &edges, &["vertex1", "vertex2"])?,
+        })
+    }
+}
+
+/// Big endian (legacy) or little endian (xml) bytes of a scalar property
+fn property_bytes(prop: &Property, big_endian: bool) -> Vec<u8> {
+    macro_rules! bytes {
+        ($val:expr) => {
+            if big_endian {
+                $val.to_be_bytes().to_vec()
+            } else {
+                $val.to_le_bytes().to_vec()
+            }
+        };
+    }
+    match prop {
+        Property::Char(val) => bytes!(val),
+        Property::UChar(val) => bytes!(val),
+        Property::Short(val) => bytes!(val),
+        Property::UShort(val) => bytes!(val),
+        Property::Int(val) => bytes!(val),
+        Property::UInt(val) => bytes!(val),
+        Property::Float(val) => bytes!(val),
+        Property::Double(val) => bytes!(val),
+        _ => Vec::new(),
+    }
+}
+
+fn legacy_type_name(data_type: &ScalarType) -> &'static str {
+    match data_type {
+        ScalarType::Char => "char",
+        ScalarType::UChar => "unsigned_char",
+        ScalarType::Short => "short",
+        ScalarType::UShort => "unsigned_short",
+        ScalarType::Int => "int",
+        ScalarType::UInt => "unsigned_int",
+        ScalarType::Float => "float",
+        ScalarType::Double => "double",
+    }
+}
+
+fn xml_type_name(data_type: &ScalarType) -> &'static str {
+    match data_type {
+        ScalarType::Char => "Int8",
+        ScalarType::UChar => "UInt8",
+        ScalarType::Short => "Int16",
+        ScalarType::UShort => "UInt16",
+        ScalarType::Int => "Int32",
+        ScalarType::UInt => "UInt32",
+        ScalarType::Float => "Float32",
+        ScalarType::Double => "Float64",
+    }
+}
+
+fn write_legacy_arrays<W: Write>(out: &mut W, arrays: &[DataArray]) -> Result<()> {
+    for array in arrays.iter() {
+        if array.nb_components == 3 && array.data_type == ScalarType::UChar {
+            // binary color scalars are unsigned chars, as stored
+            writeln!(out, "COLOR_SCALARS {} 3", array.name)?;
+        } else {
+            writeln!(
+                out,
+                "SCALARS {} {} {}",
+                array.name,
+                legacy_type_name(&array.data_type),
+                array.nb_components
+            )?;
+            writeln!(out, "LOOKUP_TABLE default")?;
+        }
+        for val in array.values.iter() {
+            out.write_all(&property_bytes(val, true))?;
+        }
+        writeln!(out)?;
+    }
+    Ok(())
+}
+
+fn write_legacy<W: Write>(out: &mut W, poly_data: &PolyData) -> Result<()> {
+    writeln!(out, "# vtk DataFile Version 3.0")?;
+    writeln!(out, "{}", poly_data.title)?;
+    writeln!(out, "BINARY")?;
+    writeln!(out, "DATASET POLYDATA")?;
+    writeln!(out, "POINTS {} float", poly_data.points.len())?;
+    for point in poly_data.points.iter() {
+        for coord in point.iter() {
+            out.write_all(&coord.to_be_bytes())?;
+        }
+    }
+    writeln!(out)?;
+    for (keyword, cells) in [("LINES", &poly_data.lines), ("POLYGONS", &poly_data.polys)] {
+        if cells.is_empty() {
+            continue;
+        }
+        let size: usize = cells.iter().map(|cell| cell.len() + 1).sum();
+        writeln!(out, "{} {} {}", keyword, cells.len(), size)?;
+        for cell in cells.iter() {
+            out.write_all(&(cell.len() as i32).to_be_bytes())?;
+            for &index in cell.iter() {
+                out.write_all(&(index as i32).to_be_bytes())?;
+            }
+        }
+        writeln!(out)?;
+    }
+    if !poly_data.point_data.is_empty() {
+        writeln!(out, "POINT_DATA {}", poly_data.points.len())?;
+        write_legacy_arrays(out, &poly_data.point_data)?;
+    }
+    let nb_cells = poly_data.lines.len() + poly_data.polys.len();
+    if nb_cells != 0 && !poly_data.cell_data.is_empty() {
+        writeln!(out, "CELL_DATA {}", nb_cells)?;
+        write_legacy_arrays(out, &poly_data.cell_data)?;
+    }
+    Ok(())
+}
+
+fn base64(bytes: &[u8]) -> String {
+    let mut text = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let group = (chunk[0] as u32) << 16
+            | (*chunk.get(1).unwrap_or(&0) as u32) << 8
+            | *chunk.get(2).unwrap_or(&0) as u32;
+        for i in 0..4 {
+            if i <= chunk.len() {
+                text.push(BASE64_CHARS[(group >> (18 - 6 * i) & 63) as usize] as char);
+            } else {
+                text.push('=');
+            }
+        }
+    }
+    text
+}
+
+/// Inline binary xml data array, its byte count (UInt32) followed by its values
+fn write_xml_array<W: Write>(
+    out: &mut W,
+    attributes: &str,
+    values: impl Iterator<Item = Vec<u8>>,
+) -> Result<()> {
+    let data: Vec<u8> = values.flatten().collect();
+    let mut bytes = (data.len() as u32).to_le_bytes().to_vec();
+    bytes.extend(data);
+    writeln!(
+        out,
+        "<DataArray {} format=\"binary\">{}</DataArray>",
+        attributes,
+        base64(&bytes)
+    )?;
+    Ok(())
+}
+
+fn write_xml_data<W: Write>(out: &mut W, tag: &str, arrays: &[DataArray]) -> Result<()> {
+    writeln!(out, "<{}>", tag)?;
+    for array in arrays.iter() {
+        let attributes = format!(
+            "type=\"{}\" Name=\"{}\" NumberOfComponents=\"{}\"",
+            xml_type_name(&array.data_type),
+            array.name,
+            array.nb_components
+        );
+        let values = array.values.iter().map(|val| property_bytes(val, false));
+        write_xml_array(out, &attributes, values)?;
+    }
+    writeln!(out, "</{}>", tag)?;
+    Ok(())
+}
+
+/// Xml cells, their connectivity and offsets
+fn write_xml_cells<W: Write>(out: &mut W, tag: &str, cells: &[Vec<usize>]) -> Result<()> {
+    writeln!(out, "<{}>", tag)?;
+    write_xml_array(
+        out,
+        "type=\"Int32\" Name=\"connectivity\"",
+        cells
+            .iter()
+            .flatten()
+            .map(|&index| (index as i32).to_le_bytes().to_vec()),
+    )?;
+    let offsets = cells.iter().scan(0, |offset, cell| {
+        *offset += cell.len() as i32;
+        Some(offset.to_le_bytes().to_vec())
+    });
+    write_xml_array(out, "type=\"Int32\" Name=\"offsets\"", offsets)?;
+    writeln!(out, "</{}>", tag)?;
+    Ok(())
+}
+
+fn write_xml<W: Write>(out: &mut W, poly_data: &PolyData) -> Result<()> {
+    writeln!(out, "<?xml version=\"1.0\"?>")?;
+    writeln!(
+        out,
+        "<VTKFile type=\"PolyData\" version=\"1.0\" byte_order=\"LittleEndian\" header_type=\"UInt32\">"
+    )?;
+    writeln!(out, "<PolyData>")?;
+    writeln!(
+        out,
+        "<Piece NumberOfPoints=\"{}\" NumberOfVerts=\"0\" NumberOfLines=\"{}\" NumberOfStrips=\"0\" NumberOfPolys=\"{}\">",
+        poly_data.points.len(),
+        poly_data.lines.len(),
+        poly_data.polys.len()
+    )?;
+    write_xml_data(out, "PointData", &poly_data.point_data)?;
+    write_xml_data(out, "CellData", &poly_data.cell_data)?;
+    writeln!(out, "<Points>")?;
+    write_xml_array(
+        out,
+        "type=\"Float32\" NumberOfComponents=\"3\"",
+        poly_data
+            .points
+            .iter()
+            .flat_map(|point| point.map(|coord| coord.to_le_bytes().to_vec())),
+    )?;
+    writeln!(out, "</Points>")?;
+    if !poly_data.lines.is_empty() {
+        write_xml_cells(out, "Lines", &poly_data.lines)?;
+    }
+    if !poly_data.polys.is_empty() {
+        write_xml_cells(out, "Polys", &poly_data.polys)?;
+    }
+    writeln!(out, "</Piece>")?;
+    writeln!(out, "</PolyData>")?;
+    writeln!(out, "</VTKFile>")?;
+    Ok(())
+}
+
+fn export_poly_data(poly_data: &PolyData, file_path: &str, format: VtkFormat) -> Result<()> {
+    let mut file = compression::create_writer(Path::new(file_path))?;
+    match format {
+        VtkFormat::Legacy => write_legacy(&mut file, poly_data)?,
+        VtkFormat::Xml => write_xml(&mut file, poly_data)?,
+    }
+    file.finish()
+}
+
+/// Writes a skeleton as vtk poly data (`-` for standard output)
+///
+/// Faces become polygons and edges of no face lines, vertex properties (radius, erosion
+/// thickness, ...) point data, and face properties cell data (zero on lines).
+pub fn export_to_vtk(skel: &Skeleton, file_path: &str, format: VtkFormat) -> Result<()> {
+    export_poly_data(&PolyData::from_skeleton(skel)?, file_path, format)
+}
+
+/// Writes an erosion path as vtk poly data lines, burn time being point data
+pub fn export_erosion_path_to_vtk(
+    erosion_path: &ErosionPath,
+    file_path: &str,
+    format: VtkFormat,
+) -> Result<()> {
+    export_poly_data(
+        &PolyData::from_erosion_path(erosion_path)?,
+        file_path,
+        format,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::skeleton::io::import_from_ply_reader;
+
+    /// Triangle with a face property and an edge of no face
+    fn skeleton() -> Skeleton {
+        let ply = "ply\nformat ascii 1.0\nelement vertex 4\nproperty float x\nproperty float y\n\
+                   property float z\nproperty float radius\nelement face 1\n\
+                   property list uchar int vertex_indices\nproperty int component\n\
+                   element edge 1\nproperty int vertex1\nproperty int vertex2\nend_header\n\
+                   0 0 0 0.5\n1 0 0 0.5\n0 1 0 0.5\n0 0 1 0.25\n3 0 1 2 7\n2 3\n";
+        import_from_ply_reader(&mut ply.as_bytes()).unwrap()
+    }
+
+    #[test]
+    fn base64_known_vectors() {
+        // RFC 4648 test vectors
+        for (bytes, text) in [
+            ("", ""),
+            ("f", "Zg=="),
+            ("fo", "Zm8="),
+            ("foo", "Zm9v"),
+            ("foob", "Zm9vYg=="),
+            ("fooba", "Zm9vYmE="),
+            ("foobar", "Zm9vYmFy"),
+        ] {
+            assert_eq!(base64(bytes.as_bytes()), text);
+        }
+        assert_eq!(base64(&[0xfb, 0xff, 0xbf]), "+/+/");
+        assert_eq!(base64(&[0, 0, 0, 0xff]), "AAAA/w==");
+    }
+
+    #[test]
+    fn vtp_snapshot() {
+        let mut out = Vec::new();
+        write_xml(&mut out, &PolyData::from_skeleton(&skeleton()).unwrap()).unwrap();
+        // component is 0 on the line, 7 on the triangle
+        let expected = r#"<?xml version="1.0"?>
+<VTKFile type="PolyData" version="1.0" byte_order="LittleEndian" header_type="UInt32">
+<PolyData>
+<Piece NumberOfPoints="4" NumberOfVerts="0" NumberOfLines="1" NumberOfStrips="0" NumberOfPolys="1">
+<PointData>
+<DataArray type="Float32" Name="radius" NumberOfComponents="1" format="binary">EAAAAAAAAD8AAAA/AAAAPwAAgD4=</DataArray>
+</PointData>
+<CellData>
+<DataArray type="Int32" Name="component" NumberOfComponents="1" format="binary">CAAAAAAAAAAHAAAA</DataArray>
+</CellData>
+<Points>
+<DataArray type="Float32" NumberOfComponents="3" format="binary">MAAAAAAAAAAAAAAAAAAAAAAAgD8AAAAAAAAAAAAAAAAAAIA/AAAAAAAAAAAAAAAAAACAPw==</DataArray>
+</Points>
+<Lines>
+<DataArray type="Int32" Name="connectivity" format="binary">CAAAAAIAAAADAAAA</DataArray>
+<DataArray type="Int32" Name="offsets" format="binary">BAAAAAIAAAA=</DataArray>
+</Lines>
+<Polys>
+<DataArray type="Int32" Name="connectivity" format="binary">DAAAAAAAAAABAAAAAgAAAA==</DataArray>
+<DataArray type="Int32" Name="offsets" format="binary">BAAAAAMAAAA=</DataArray>
+</Polys>
+</Piece>
+</PolyData>
+</VTKFile>
+"#;
+        assert_eq!(String::from_utf8(out).unwrap(), expected);
+    }
+
+    #[test]
+    fn legacy_lines_and_polygons() {
+        let mut out = Vec::new();
+        write_legacy(&mut out, &PolyData::from_skeleton(&skeleton()).unwrap()).unwrap();
+
+        let be = |values: &[i32]| -> Vec<u8> {
+            values.iter().flat_map(|val| val.to_be_bytes()).collect()
+        };
+        let mut expected =
+            b"# vtk DataFile Version 3.0\nskeleton\nBINARY\nDATASET POLYDATA\nPOINTS 4 float\n"
+                .to_vec();
+        for coord in [
+            0.0f32, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0,
+        ] {
+            expected.extend(coord.to_be_bytes());
+        }
+        expected.extend(b"\nLINES 1 3\n");
+        expected.extend(be(&[2, 2, 3]));
+        expected.extend(b"\nPOLYGONS 1 4\n");
+        expected.extend(be(&[3, 0, 1, 2]));
+        expected.extend(b"\nPOINT_DATA 4\nSCALARS radius float 1\nLOOKUP_TABLE default\n");
+        for radius in [0.5f32, 0.5, 0.5, 0.25] {
+            expected.extend(radius.to_be_bytes());
+        }
+        expected.extend(b"\nCELL_DATA 2\nSCALARS component int 1\nLOOKUP_TABLE default\n");
+        expected.extend(be(&[0, 7]));
+        expected.extend(b"\n");
+        assert_eq!(out, expected);
+    }
+}
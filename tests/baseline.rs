@@ -0,0 +1,28 @@
+use sha2::{Digest, Sha256};
+
+use erosion_thickness::et_algorithm::algorithm::erosion_thickness_computation;
+use erosion_thickness::skeleton::io;
+
+/// Hex sha256 of the erosion thickness of resources/skeleton.ply, as little endian f32,
+/// computed before the flat ETGraph storage (dist_max 0.005, subdiv_max 1)
+const BASELINE_ET_SHA256: &str = "69467e15cf39067bd40a548803b47fe3f93b51b3ca82b4874e9896f591d58060";
+
+#[test]
+fn erosion_thickness_matches_baseline() {
+    let path = concat!(env!("CARGO_MANIFEST_DIR"), "/resources/skeleton.ply");
+    let mut skeleton = io::import_from_ply(path).unwrap();
+    erosion_thickness_computation(&mut skeleton, 0.005, 1).unwrap();
+    let et_values = skeleton.get_property_f32("erosion_thickness").unwrap();
+    assert_eq!(et_values.len(), 22372);
+
+    let mut hasher = Sha256::new();
+    for et in et_values.iter() {
+        hasher.update(et.to_le_bytes());
+    }
+    let digest: String = hasher
+        .finalize()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect();
+    assert_eq!(digest, BASELINE_ET_SHA256);
+}
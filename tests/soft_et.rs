@@ -0,0 +1,219 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+
+/// Skeleton of a single triangle
+const TRIANGLE_PLY: &str = "ply\nformat ascii 1.0\nelement vertex 3\nproperty float x\n\
+    property float y\nproperty float z\nproperty float radius\nelement face 1\n\
+    property list uchar uint vertex_indices\nend_header\n\
+    0 0 0 0.1\n1 0 0 0.1\n0 1 0 0.1\n3 0 1 2\n";
+
+/// Empty scratch directory for one test
+fn scratch_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("soft_et_{}_{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+#[test]
+fn compute_rejects_out_of_range_face_vertex() {
+    let dir = scratch_dir("out_of_range");
+    let input = dir.join("skeleton.ply");
+    fs::write(
+        &input,
+        "ply\nformat ascii 1.0\nelement vertex 3\nproperty float x\nproperty float y\n\
+         property float z\nproperty float radius\nelement face 1\n\
+         property list uchar uint vertex_indices\nend_header\n\
+         0 0 0 0.1\n1 0 0 0.1\n0 1 0 0.1\n3 0 1 5\n",
+    )
+    .unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_soft_et"))
+        .arg("compute")
+        .arg("--input_skel")
+        .arg(&input)
+        .arg("--pathout")
+        .arg(dir.join("out"))
+        .output()
+        .unwrap();
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    // an error, not a panic
+    assert_eq!(output.status.code(), Some(1), "{}", stderr);
+    assert!(stderr.contains("out of range"), "{}", stderr);
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn compute_rejects_negative_radius_scale() {
+    let dir = scratch_dir("radius_scale");
+    let output = Command::new(env!("CARGO_BIN_EXE_soft_et"))
+        .arg("compute")
+        .arg("--input_skel")
+        .arg(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/resources/skeleton.ply"
+        ))
+        .arg("--pathout")
+        .arg(&dir)
+        .arg("--metric")
+        .arg("scaled-radius")
+        .arg("--radius_scale=-1")
+        .output()
+        .unwrap();
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert_eq!(output.status.code(), Some(1), "{}", stderr);
+    assert!(stderr.contains("Invalid radius scale -1"), "{}", stderr);
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn compute_flags_override_the_config_file() {
+    let dir = scratch_dir("config_precedence");
+    let input = dir.join("skeleton.ply");
+    fs::write(&input, TRIANGLE_PLY).unwrap();
+    let config = dir.join("config.toml");
+    fs::write(
+        &config,
+        "[subdivision]\ndist_max = 0.5\nsubdiv_max = 3\n\
+         [burn]\nmetric = \"medial-ball\"\n\
+         [color]\nrange = [0.0, 1.0]\n\
+         [output]\npathout = \"unused\"\nskeleton = \"from_config.ply\"\n",
+    )
+    .unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_soft_et"))
+        .arg("compute")
+        .arg("--input_skel")
+        .arg(&input)
+        .arg("--config")
+        .arg(&config)
+        .arg("--pathout")
+        .arg(dir.join("out"))
+        .arg("--dist_max")
+        .arg("0.25")
+        .arg("--metric")
+        .arg("euclidean")
+        .arg("--color_percentile")
+        .arg("5,95")
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert!(dir.join("out/from_config.ply").exists());
+    assert!(!dir.join("unused").exists());
+
+    // the configuration of the run, written next to the skeleton
+    let written: toml::Value =
+        toml::from_str(&fs::read_to_string(dir.join("out/run_config.toml")).unwrap()).unwrap();
+    // flags win
+    assert_eq!(written["subdivision"]["dist_max"].as_float(), Some(0.25));
+    assert_eq!(written["burn"]["metric"].as_str(), Some("euclidean"));
+    assert_eq!(written["color"]["percentile"].as_array().unwrap().len(), 2);
+    assert!(written["color"].get("range").is_none());
+    // the file fills in what no flag gives
+    assert_eq!(written["subdivision"]["subdiv_max"].as_integer(), Some(3));
+    assert_eq!(
+        written["output"]["skeleton"].as_str(),
+        Some("from_config.ply")
+    );
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+/// Runs the batch subcommand on `input`, writing to `out`
+fn batch(input: &Path, out: &Path) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_soft_et"))
+        .arg("batch")
+        .arg("--input")
+        .arg(input)
+        .arg("--pathout")
+        .arg(out)
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn batch_directory_manifest_and_glob() {
+    let dir = scratch_dir("batch_inputs");
+    fs::create_dir_all(dir.join("in/sub")).unwrap();
+    fs::write(dir.join("in/a.ply"), TRIANGLE_PLY).unwrap();
+    fs::write(dir.join("in/sub/b.ply"), TRIANGLE_PLY).unwrap();
+    fs::write(dir.join("in/notes.txt"), "not a skeleton").unwrap();
+    fs::write(
+        dir.join("list.txt"),
+        "# skeletons\nin/a.ply\n\nin/sub/b.ply\n",
+    )
+    .unwrap();
+
+    for (input, out, prefix) in [
+        (dir.join("in"), dir.join("out_dir"), ""),
+        (dir.join("list.txt"), dir.join("out_manifest"), "in/"),
+        (dir.join("in/**/*.ply"), dir.join("out_glob"), ""),
+    ] {
+        let output = batch(&input, &out);
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        assert!(
+            output.status.success(),
+            "{}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+        // outputs mirror the input tree
+        for file in [
+            "a.ply",
+            "a_erosion_path.ply",
+            "sub/b.ply",
+            "sub/b_components.csv",
+        ] {
+            let path = out.join(format!("{}{}", prefix, file));
+            assert!(path.exists(), "{}", path.display());
+        }
+        assert!(out.join("run_config.toml").exists());
+
+        // summary table, a header and a line per file
+        let lines: Vec<&str> = stdout.lines().collect();
+        assert_eq!(lines.len(), 3, "{}", stdout);
+        assert!(lines[0].starts_with("file"));
+        assert!(lines[0].ends_with("time (s)  vertices  et range"));
+        for (line, file) in lines[1..].iter().zip(["a.ply", "b.ply"]) {
+            let columns: Vec<&str> = line.split_whitespace().collect();
+            assert!(columns[0].ends_with(file), "{}", line);
+            assert_eq!(columns[1], "ok");
+            assert_eq!(columns[3], "3");
+        }
+    }
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn batch_continues_after_a_failing_file() {
+    let dir = scratch_dir("batch_failure");
+    fs::create_dir_all(dir.join("in")).unwrap();
+    fs::write(dir.join("in/a.ply"), TRIANGLE_PLY).unwrap();
+    fs::write(
+        dir.join("in/bad.ply"),
+        TRIANGLE_PLY.replace("3 0 1 2", "3 0 1 5"),
+    )
+    .unwrap();
+    fs::write(dir.join("in/c.ply"), TRIANGLE_PLY).unwrap();
+
+    let output = batch(&dir.join("in"), &dir.join("out"));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert_eq!(output.status.code(), Some(1), "{}", stderr);
+    assert!(stderr.contains("1 of 3 skeletons failed"), "{}", stderr);
+
+    let lines: Vec<&str> = stdout.lines().collect();
+    assert_eq!(lines.len(), 4, "{}", stdout);
+    assert!(lines[1].contains("a.ply") && lines[1].contains(" ok "));
+    assert!(lines[2].contains("bad.ply") && lines[2].contains(" failed: "));
+    assert!(lines[2].contains("out of range"), "{}", lines[2]);
+    assert!(lines[3].contains("c.ply") && lines[3].contains(" ok "));
+    // files after the failing one are still computed
+    assert!(dir.join("out/a.ply").exists());
+    assert!(dir.join("out/c.ply").exists());
+    assert!(!dir.join("out/bad.ply").exists());
+    fs::remove_dir_all(&dir).unwrap();
+}